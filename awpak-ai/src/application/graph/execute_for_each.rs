@@ -0,0 +1,151 @@
+use std::{collections::HashMap, sync::Arc};
+
+use awpak_utils::result::result::AwpakResult;
+use serde_json::Value;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{application::graph::execute_parallel::execute_parallel_item, domain::{data::{data::{DataMerge, DataToContext, DataType}, data_insert::value_to_context, data_selection::data_selection}, error::Error, for_each::for_each::ForEachNode, graph::graph::Graph}};
+
+
+pub async fn execute_for_each(
+    parent_graph : &Graph,
+    for_each : ForEachNode
+) -> AwpakResult<( ForEachNode, Vec<Value> ), Error>
+{
+    let items = match for_each_items( parent_graph, &for_each ).await
+    {
+        Ok( i ) => i,
+        Err( e ) => return AwpakResult::new_err( ( for_each, vec![] ), e )
+    };
+
+    match for_each.for_each.concurrency
+    {
+        Some( c ) if c > 1 => execute_concurrent( parent_graph, for_each, items, c ).await,
+        _ => execute_sequential( parent_graph, for_each, items ).await
+    }
+}
+
+async fn for_each_items( graph : &Graph, for_each : &ForEachNode ) -> Result<Vec<Value>, Error>
+{
+    match data_selection( graph, &for_each.for_each.from ).await?
+    {
+        Value::Array( a ) => Ok( a ),
+        Value::Null => Ok( vec![] ),
+        v => Err( Error::ParseData( format!( "ForEach. {} is not an array", v ) ) )
+    }
+}
+
+async fn execute_sequential(
+    parent_graph : &Graph,
+    mut for_each : ForEachNode,
+    items : Vec<Value>
+) -> AwpakResult<( ForEachNode, Vec<Value> ), Error>
+{
+    let mut values = vec![];
+
+    for ( idx, item ) in items.into_iter().enumerate()
+    {
+        let graph = match item_graph( parent_graph, &for_each, item, idx )
+        {
+            Ok( g ) => g,
+            Err( e ) => return AwpakResult::new_err( ( for_each, vec![] ), e )
+        };
+
+        let executor = *for_each.for_each.executor.clone();
+
+        let ( _, executor, graph_node, result ) = execute_parallel_item( graph, executor, for_each.graph.take(), idx, None ).await;
+
+        for_each.for_each.executor = Box::new( executor );
+        for_each.graph = graph_node;
+
+        match result
+        {
+            Ok( v ) => values.push( v ),
+            Err( e ) => return AwpakResult::new_err( ( for_each, vec![] ), e.prepend_str( format!( "ForEach item {}\n", idx ) ) )
+        }
+    }
+
+    AwpakResult::new( ( for_each, values ) )
+}
+
+async fn execute_concurrent(
+    parent_graph : &Graph,
+    mut for_each : ForEachNode,
+    items : Vec<Value>,
+    concurrency : usize
+) -> AwpakResult<( ForEachNode, Vec<Value> ), Error>
+{
+    let semaphore = Arc::new( Semaphore::new( concurrency ) );
+
+    let mut tasks = JoinSet::new();
+
+    for ( idx, item ) in items.into_iter().enumerate()
+    {
+        let graph = match item_graph( parent_graph, &for_each, item, idx )
+        {
+            Ok( g ) => g,
+            Err( e ) => return AwpakResult::new_err( ( for_each, vec![] ), e )
+        };
+
+        tasks.spawn(
+            execute_parallel_item( 
+                graph, 
+                *for_each.for_each.executor.clone(), 
+                for_each.graph.clone(), 
+                idx, 
+                Some( semaphore.clone() ) 
+            )
+        );
+    }
+
+    let mut results = tasks.join_all().await;
+
+    results.sort_by_key( | ( idx, _, _, _ ) | *idx );
+
+    let mut values = vec![];
+    let mut err = None;
+
+    // The state of the last item (agent history, sub-graph) is kept in the node
+    for ( idx, executor, graph_node, result ) in results
+    {
+        for_each.for_each.executor = Box::new( executor );
+        for_each.graph = graph_node;
+
+        match result
+        {
+            Ok( v ) => values.push( v ),
+            Err( e ) => if err.is_none() { err = Some( e.prepend_str( format!( "ForEach item {}\n", idx ) ) ) }
+        }
+    }
+
+    match err
+    {
+        Some( e ) => AwpakResult::new_err( ( for_each, vec![] ), e ),
+        None => AwpakResult::new( ( for_each, values ) )
+    }
+}
+
+fn item_graph( parent_graph : &Graph, for_each : &ForEachNode, item : Value, idx : usize ) -> Result<Graph, Error>
+{
+    let mut graph = parent_graph.clone();
+
+    let context = std::mem::take( &mut graph.context );
+
+    let context = insert_value( context, item, &for_each.for_each.item )?;
+    let context = insert_value( context, Value::Number( idx.into() ), &for_each.for_each.index )?;
+
+    graph.context = context;
+
+    Ok( graph )
+}
+
+fn insert_value( context : HashMap<String, Value>, value : Value, path : &str ) -> Result<HashMap<String, Value>, Error>
+{
+    let conf = DataToContext { path : path.to_string(), ty : DataType::default(), merge : DataMerge::Insert, optional : false, schema : None };
+
+    match value_to_context( context, value, &conf ).collect()
+    {
+        ( ( c, _ ), None ) => Ok( c ),
+        ( _, Some( e ) ) => Err( e.prepend_str( format!( "ForEach. Insert {}\n", path ) ) )
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use awpak_utils::result::result::AwpakResult;
 use serde_json::json;
 
@@ -9,7 +11,17 @@ pub async fn execute_graph(
     mut graph_node : GraphNode
 ) -> AwpakResult<( GraphNode, String ), Error>
 {
-    let graph = graph_node.graph;
+    let mut graph = graph_node.graph;
+
+    if graph.human_input.is_none()
+    {
+        graph.human_input = parent_graph.human_input.clone();
+    }
+
+    if graph.cassette.is_none()
+    {
+        graph.cassette = parent_graph.cassette.clone();
+    }
 
     let input = data_to_string( parent_graph, graph_node.input.clone() ).await;
 
@@ -17,6 +29,8 @@ pub async fn execute_graph(
     {
         ( g, None ) =>
         {
+            add_usage_to_parent( parent_graph, &g );
+
             let output = graph_output( &g, &graph_node.output );
 
             graph_node.graph = g;
@@ -28,6 +42,8 @@ pub async fn execute_graph(
             // Cuando hay error no estamos recogiendo la salida así que no la calculamos. Esto podría cambiar en un futuro.
             //let output = graph_output( &g, &graph_node.output );
 
+            add_usage_to_parent( parent_graph, &g );
+
             graph_node.graph = g;
 
             AwpakResult::new_err( ( graph_node, "".into() ), e )
@@ -35,6 +51,13 @@ pub async fn execute_graph(
     }
 }
 
+fn add_usage_to_parent( parent_graph : &Graph, graph : &Graph )
+{
+    if Arc::ptr_eq( &parent_graph.usage, &graph.usage ) { return }
+
+    parent_graph.usage.add( parent_graph.current_node.as_deref(), &graph.usage.get().run );
+}
+
 fn graph_output(
     graph : &Graph,
     output : &Vec<GraphNodeOutput>
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use awpak_utils::result::result::AwpakResult;
+use serde_json::Value;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{application::graph::execute_graph::execute_graph, domain::{data::{data_compare::compare_data, data_utils::str_to_value}, error::Error, graph::{graph::Graph, graph_node::GraphNode}, parallel::{execute_parallel::execute_parallel_executor, parallel::{ParallelExecutor, ParallelNode}}}};
+
+
+pub async fn execute_parallel(
+    parent_graph : &Graph,
+    mut parallel : ParallelNode
+) -> AwpakResult<( ParallelNode, Vec<Value> ), Error>
+{
+    let semaphore = parallel.parallel.concurrency.map( | c | Arc::new( Semaphore::new( c.max( 1 ) ) ) );
+
+    let mut tasks = JoinSet::new();
+
+    let executors = std::mem::take( &mut parallel.parallel.executors );
+
+    for ( idx, executor ) in executors.into_iter().enumerate()
+    {
+        let graph_node = parallel.graphs.remove( &idx );
+
+        tasks.spawn(
+            execute_parallel_item( parent_graph.clone(), executor, graph_node, idx, semaphore.clone() )
+        );
+    }
+
+    let mut results = tasks.join_all().await;
+
+    results.sort_by_key( | ( idx, _, _, _ ) | *idx );
+
+    let mut values = vec![];
+    let mut err = None;
+
+    for ( idx, executor, graph_node, result ) in results
+    {
+        parallel.parallel.executors.push( executor );
+
+        if let Some( g ) = graph_node { parallel.graphs.insert( idx, g ); }
+
+        match result
+        {
+            Ok( v ) => values.push( v ),
+            Err( e ) => if err.is_none() { err = Some( e.prepend_str( format!( "ParallelExecutor {}\n", idx ) ) ) }
+        }
+    }
+
+    match err
+    {
+        Some( e ) => AwpakResult::new_err( ( parallel, vec![] ), e ),
+        None => AwpakResult::new( ( parallel, values ) )
+    }
+}
+
+pub async fn execute_parallel_item(
+    graph : Graph,
+    executor : ParallelExecutor,
+    graph_node : Option<GraphNode>,
+    idx : usize,
+    semaphore : Option<Arc<Semaphore>>
+) -> ( usize, ParallelExecutor, Option<GraphNode>, Result<Value, Error> )
+{
+    match compare_data( 
+            &graph,
+            executor.condition() 
+    ).await
+    {
+        Ok( r ) if r => {},
+        Ok( _ ) => return ( idx, executor, graph_node, Ok( Value::Null ) ),
+        Err( e ) => return ( idx, executor, graph_node, Err( e ) )
+    };
+
+    let _permit = match semaphore
+    {
+        Some( s ) => s.acquire_owned().await.ok(),
+        None => None
+    };
+
+    match graph_node
+    {
+        Some( g ) =>
+        {
+            let ty = executor.ty();
+
+            match execute_graph( &graph, g ).await.collect()
+            {
+                ( ( g, o ), None ) => ( idx, executor, Some( g ), str_to_value( &o, &ty, true ) ),
+                ( ( g, _ ), Some( e ) ) => ( idx, executor, Some( g ), Err( e ) )
+            }
+        },
+        None =>
+        {
+            let ( executor, result ) = execute_parallel_executor( &graph, executor ).await;
+
+            ( idx, executor, None, result )
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod run_graph;
-pub mod execute_graph;
\ No newline at end of file
+pub mod execute_graph;
+pub mod execute_parallel;
+pub mod execute_for_each;
\ No newline at end of file
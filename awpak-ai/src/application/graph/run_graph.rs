@@ -1,16 +1,52 @@
 
+use std::{collections::HashMap, time::Instant};
+
+use tokio::task::JoinSet;
+
 use awpak_utils::result::result::AwpakResult;
 use serde_json::Value;
 use async_recursion::async_recursion;
 use tracing::info;
 
-use crate::{application::graph::execute_graph::execute_graph, domain::{agent::execute_agent::execute_agent, agent_history_mut::change_agent_history::change_agent_history, command::execute_command::execute_command, context_mut::change_context::change_context, data::{data::{DataComparator, DataType}, data_compare::compare_data, data_insert::{str_to_context, value_to_context}, data_selection::data_to_string, data_utils::str_to_value}, error::{ChangeError, Error}, graph::{graph::Graph, node::{NodeDestination, NodeExecutor, NodeNext}}, parallel::execute_parallel::execute_parallel, tracing::filter_layer::{GRAPH_INPUT, GRAPH_OUTPUT_ERR, GRAPH_OUTPUT_OK, NODE_DESTINATION, NODE_EXECUTION, NODE_OUTPUT}, utils::string_utils::option_string_to_str, web_client::execute_web_client::execute_web_client}};
+use crate::{application::graph::{execute_for_each::execute_for_each, execute_graph::execute_graph, execute_parallel::execute_parallel}, domain::{checkpoint::{checkpoint::GraphCheckpoint, graph_checkpoint::{checkpoint_from_graph, graph_from_checkpoint}}, agent::{agent::{AIAgent, AIAgentFallback, AIAgentProviderConfig}, execute_agent::{execute_agent, execute_structured_agent}}, agent_history_mut::change_agent_history::change_agent_history, command::execute_command::execute_command, context_mut::change_context::change_context, data::{data::{DataComparator, DataMerge, DataToContext, DataType}, data_compare::compare_data, data_insert::{str_to_context, value_to_context}, data_schema::validate_schema, data_selection::data_to_string, data_utils::str_to_value}, error::{ChangeError, Error}, graph::{graph::Graph, node::{NodeDestination, NodeExecutor, NodeNext}}, human_input::execute_human_input::execute_human_input, join::join_contexts::join_contexts, tracing::filter_layer::{GRAPH_INPUT, GRAPH_OUTPUT_ERR, GRAPH_OUTPUT_OK, NODE_DESTINATION, NODE_ERROR, NODE_EXECUTION, NODE_OUTPUT, NODE_RETRY}, utils::string_utils::option_string_to_str, web_client::execute_web_client::execute_web_client}};
+
 
+const TRAIL_LEN : usize = 50;
 
 struct GraphRunner
 {
     graph : Graph,
-    next : String
+    next : String,
+
+    steps : usize,
+    visits : HashMap<String, usize>,
+    trail : Vec<String>,
+    start : Instant
+}
+
+impl GraphRunner
+{
+    fn new( next : String, graph : Graph ) -> Self
+    {
+        Self { graph, next, steps : 0, visits : HashMap::new(), trail : vec![], start : Instant::now() }
+    }
+
+    fn fork( &self, next : String ) -> Self
+    {
+        let mut graph = self.graph.clone();
+
+        graph.checkpoint = None;
+
+        Self 
+        { 
+            graph, 
+            next, 
+            steps : self.steps, 
+            visits : self.visits.clone(), 
+            trail : self.trail.clone(), 
+            start : self.start 
+        }
+    }
 }
 
 #[async_recursion]
@@ -27,12 +63,64 @@ pub async fn run_graph(
         ( g, Some( e ) ) => return AwpakResult::new_err( g, e )    
     };
 
-    let runner = GraphRunner { next : graph.first.clone() , graph };
+    let next = graph.first.clone();
+
+    graph.usage.start_run();
+    graph.mock_calls.start_run();
+
+    run_graph_from( next, graph ).await
+}
+
+pub async fn resume_graph(
+    checkpoint : GraphCheckpoint,
+    graph : Graph
+) -> AwpakResult<Graph, Error>
+{
+    let next = checkpoint.next.clone();
+    let steps = checkpoint.steps;
+    let visits = checkpoint.visits.clone();
+    let trail = checkpoint.trail.clone();
+    let usage = checkpoint.usage.clone();
+    let mock_calls = checkpoint.mock_calls.clone();
+
+    let graph = match graph_from_checkpoint( graph, checkpoint ).collect()
+    {
+        ( g, None ) => g,
+        ( g, Some( e ) ) => return AwpakResult::new_err( g, e.prepend_str( "Resume graph.\n" ) )
+    };
 
+    graph.usage.restore( usage );
+    graph.mock_calls.restore( mock_calls );
+
+    let next = match next
+    {
+        Some( n ) => n,
+        None => return AwpakResult::new( graph )
+    };
+
+    let mut runner = GraphRunner::new( next, graph );
+
+    runner.steps = steps;
+    runner.visits = visits;
+    runner.trail = trail;
+
+    run_graph_runner( runner ).await
+}
+
+async fn run_graph_from(
+    next : String,
+    graph : Graph
+) -> AwpakResult<Graph, Error>
+{
+    run_graph_runner( GraphRunner::new( next, graph ) ).await
+}
+
+async fn run_graph_runner( runner : GraphRunner ) -> AwpakResult<Graph, Error>
+{
     AwpakResult::new( runner )
     .write()
     .map_while(
-        async | r | next_step( r ).await
+        async | r | save_checkpoint( next_step( r ).await )
     )
     .await
     .finalize()
@@ -47,6 +135,33 @@ pub async fn run_graph(
     .read()
 }
 
+fn save_checkpoint( ( result, next ) : ( AwpakResult<GraphRunner, Error>, bool ) ) -> ( AwpakResult<GraphRunner, Error>, bool )
+{
+    let runner = match result.collect()
+    {
+        ( r, None ) => r,
+        ( r, Some( e ) ) => return ( AwpakResult::new_err( r, e ), next )
+    };
+
+    let store = match &runner.graph.checkpoint
+    {
+        Some( s ) => s,
+        None => return ( AwpakResult::new( runner ), next )
+    };
+
+    let mut checkpoint = checkpoint_from_graph( &runner.graph, if next { Some( runner.next.as_str() ) } else { None } );
+
+    checkpoint.steps = runner.steps;
+    checkpoint.visits = runner.visits.clone();
+    checkpoint.trail = runner.trail.clone();
+
+    match store.save( &checkpoint )
+    {
+        Ok( _ ) => ( AwpakResult::new( runner ), next ),
+        Err( e ) => ( AwpakResult::new_err( runner, e.prepend_str( "Save checkpoint.\n" ) ), false )
+    }
+}
+
 fn trace_graph_input( graph_id : Option<&String>, input : &str )
 {
     info!(
@@ -103,6 +218,23 @@ fn init_graph(
         }
     };
 
+    if let Some( schema ) = &graph.input_schema
+    {
+        let value = match ( &graph.input_type, &opt_input )
+        {
+            ( Some( _ ), _ ) => parsed_input.clone(),
+            ( None, Some( i ) ) => Value::String( i.clone() ),
+            ( None, None ) => Value::Null
+        };
+
+        if let Err( e ) = validate_schema( schema, &value )
+        {
+            let msg = format!( "Init graph {:?}. Input schema.\n", graph.id );
+
+            return AwpakResult::new_err( graph, e.prepend_str( msg ) )
+        }
+    }
+
     graph.input = opt_input;
     graph.parsed_input = parsed_input;
     graph.final_output = None;
@@ -112,21 +244,207 @@ fn init_graph(
     AwpakResult::new( graph )
 }
 
-async fn next_step( runner : GraphRunner ) -> ( AwpakResult<GraphRunner, Error>, bool )
+async fn next_step( mut runner : GraphRunner ) -> ( AwpakResult<GraphRunner, Error>, bool )
 {
     let next = runner.next.clone();
 
     match runner.graph.nodes.get( next.as_str() )
     {
-        Some( _ ) => execute_node( runner ).await,
+        Some( _ ) => match check_limits( &mut runner )
+        {
+            Ok( _ ) => execute_node( runner ).await,
+            Err( e ) => ( AwpakResult::new_err( runner, e ), false )
+        },
         None => ( AwpakResult::new_err( runner, Error::NodeNotFound( next ) ), false )
     }
 }
 
+fn check_limits( runner : &mut GraphRunner ) -> Result<(), Error>
+{
+    runner.steps += 1;
+
+    if runner.trail.len() == TRAIL_LEN { runner.trail.remove( 0 ); }
+
+    runner.trail.push( runner.next.clone() );
+
+    let visits = runner.visits.entry( runner.next.clone() ).or_insert( 0 );
+
+    *visits += 1;
+
+    let visits = *visits;
+
+    let msg = if let Some( max ) = runner.graph.max_steps && runner.steps > max
+    {
+        format!( "Max steps {} exceeded in node {}.", max, runner.next )
+    }
+    else if let Some( max ) = runner.graph.max_duration && runner.start.elapsed() > max
+    {
+        format!( "Max duration {:?} exceeded in node {}.", max, runner.next )
+    }
+    else if let Some( max ) = runner.graph.nodes.get( runner.next.as_str() ).and_then( | n | n.max_visits ) && visits > max
+    {
+        format!( "Max visits {} exceeded in node {}.", max, runner.next )
+    }
+    else if let Some( max ) = runner.graph.max_tokens_budget && let tokens = runner.graph.usage.get().run.total_tokens && tokens > max
+    {
+        format!( "Max tokens budget {} exceeded in node {}. Tokens: {}.", max, runner.next, tokens )
+    }
+    else
+    {
+        return Ok( () )
+    };
+
+    Err( Error::LimitExceeded( format!( "{}\nGraph: {:?}. Steps: {}.\nTrail: {}", msg, runner.graph.id, runner.steps, node_trail( runner ) ) ) )
+}
+
+fn node_trail( runner : &GraphRunner ) -> String
+{
+    let trail = runner.trail.join( " -> " );
+
+    if runner.steps > runner.trail.len() { format!( "... -> {}", trail ) } else { trail }
+}
+
+enum NodeResult
+{
+    Text( String ),
+    Value( Value ),
+    Values( Vec<Value> )
+}
+
 async fn execute_node( mut runner : GraphRunner ) -> ( AwpakResult<GraphRunner, Error>, bool )
+{
+    let retry = runner.graph.nodes.get( runner.next.as_str() ).and_then( | n | n.retry.clone() );
+
+    let mut attempt : usize = 1;
+
+    loop
+    {
+        let ( r, result ) = execute_executor( runner ).await;
+
+        let result = match result
+        {
+            Ok( NodeResult::Text( s ) ) => proccess_result( s, r ).await,
+            Ok( NodeResult::Value( v ) ) => proccess_value_result( v, r ),
+            Ok( NodeResult::Values( v ) ) => proccess_value_result( Value::Array( v ), r ),
+            Err( e ) => AwpakResult::new_err( r, e )
+        };
+
+        runner = match result.collect()
+        {
+            ( r, None ) => r,
+            ( r, Some( e ) ) =>
+            {
+                let e = node_err( &r, e );
+
+                match &retry
+                {
+                    Some( p ) if p.retry_on( &e, attempt ) =>
+                    {
+                        trace_node_retry( &r, attempt, &e.to_string() );
+
+                        tokio::time::sleep( p.delay( attempt ) ).await;
+
+                        attempt += 1;
+
+                        runner = r;
+
+                        continue
+                    },
+                    _ => return node_error( r, e ).await
+                }
+            }
+        };
+
+        let retry_condition = match &retry
+        {
+            Some( p ) if attempt < p.attempts => p.condition.as_ref(),
+            _ => None
+        };
+
+        let Some( condition ) = retry_condition else { return redirect_or_exit( runner ).await };
+
+        match check_node_destination_condition( &runner.graph, condition ).await
+        {
+            Ok( true ) =>
+            {
+                trace_node_retry( &runner, attempt, "Retry condition" );
+
+                tokio::time::sleep( retry.as_ref().unwrap().delay( attempt ) ).await;
+
+                attempt += 1;
+            },
+            Ok( false ) => return redirect_or_exit( runner ).await,
+            Err( e ) =>
+            {
+                let msg = format!( "NodeRetry condition. NodeId: {}\n", runner.next );
+
+                let e = node_err( &runner, e.prepend_str( msg ) );
+
+                return ( AwpakResult::new_err( runner, e ), false )
+            }
+        }
+    }
+}
+
+fn node_err( runner : &GraphRunner, err : Error ) -> Error
+{
+    let executor = runner.graph.nodes.get( runner.next.as_str() ).map( | n | n.executor.kind() ).unwrap_or( "" );
+
+    err.in_node( runner.graph.id.as_ref(), runner.next.clone(), executor )
+}
+
+fn trace_node_retry( runner : &GraphRunner, attempt : usize, reason : &str )
+{
+    info!( 
+        target:NODE_RETRY, 
+        id=option_string_to_str( runner.graph.id.as_ref() ), 
+        text=format!( "Node: {}. Attempt: {}. {}", runner.next, attempt, reason ) 
+    );
+}
+
+async fn node_error( mut runner : GraphRunner, err : Error ) -> ( AwpakResult<GraphRunner, Error>, bool )
+{
+    let node = runner.graph.nodes.get( runner.next.as_str() ).unwrap();
+
+    let on_error = match &node.on_error
+    {
+        Some( o ) => o.clone(),
+        None => return ( AwpakResult::new_err( runner, err ), false )
+    };
+
+    let from = node.id.clone();
+
+    info!( 
+        target:NODE_ERROR, 
+        id=option_string_to_str( runner.graph.id.as_ref() ), 
+        text=format!( "Node: {}. {}", from, err ) 
+    );
+
+    if let Some( o ) = &on_error.output
+    {
+        match str_to_context( err.to_string(), runner.graph.context, o ).collect()
+        {
+            ( c, None ) => runner.graph.context = c,
+            ( c, Some( e ) ) =>
+            {
+                runner.graph.context = c;
+
+                let msg = format!( "NodeOnError output. NodeId: {}\n", from );
+
+                return ( AwpakResult::new_err( runner, e.prepend_str( msg ) ), false )
+            }
+        }
+    }
+
+    update_next( from, runner, NodeDestination { next : on_error.next, condition : DataComparator::True } ).await
+}
+
+async fn execute_executor( mut runner : GraphRunner ) -> ( GraphRunner, Result<NodeResult, Error> )
 {
     let mut node = runner.graph.nodes.remove( runner.next.as_str() ).unwrap();
 
+    runner.graph.current_node = Some( node.id.clone() );
+
     info!( target:NODE_EXECUTION, id=option_string_to_str( runner.graph.id.as_ref() ), text=format!( "{}", node.id ) );
 
     let ( 
@@ -134,21 +452,86 @@ async fn execute_node( mut runner : GraphRunner ) -> ( AwpakResult<GraphRunner,
         result
     ) = match &node.executor
     {
-        NodeExecutor::Parallel( p ) =>
+        NodeExecutor::Parallel( _ ) =>
         {
-            let result = execute_parallel(
-                &runner.graph, 
-                p
-            ).await
-            .prepend_err( format!( "NodeExecutor::Parallel {}\n", node.id ) );
+            let ( _, p ) = node.executor.own_parallel();
+
+            let result = match execute_parallel( &runner.graph, p.unwrap() ).await.collect()
+            {
+                ( ( p, v ), None ) =>
+                {
+                    node.executor = NodeExecutor::Parallel( p );
+
+                    Ok( v )
+                },
+                ( ( p, _ ), Some( e ) ) =>
+                {
+                    node.executor = NodeExecutor::Parallel( p );
+
+                    Err( e.prepend_str( format!( "NodeExecutor::Parallel {}\n", node.id ) ) )
+                }
+            };
 
             runner.graph.nodes.insert( runner.next.clone(), node );
 
-            return match result
+            return ( runner, result.map( NodeResult::Values ) )
+        },
+        NodeExecutor::ForEach( _ ) =>
+        {
+            let ( _, f ) = node.executor.own_for_each();
+
+            let result = match execute_for_each( &runner.graph, f.unwrap() ).await.collect()
             {
-                Ok( r ) => proccess_parallel_result( r, runner ).await,
-                Err( e ) => ( AwpakResult::new_err( runner, e ), false )
+                ( ( f, v ), None ) =>
+                {
+                    node.executor = NodeExecutor::ForEach( f );
+
+                    Ok( v )
+                },
+                ( ( f, _ ), Some( e ) ) =>
+                {
+                    node.executor = NodeExecutor::ForEach( f );
+
+                    Err( e.prepend_str( format!( "NodeExecutor::ForEach {}\n", node.id ) ) )
+                }
+            };
+
+            runner.graph.nodes.insert( runner.next.clone(), node );
+
+            return ( runner, result.map( NodeResult::Values ) )
+        },
+        NodeExecutor::Agent( a ) =>
+        {
+            let result = match a.output_schema
+            {
+                Some( _ ) => execute_structured_agent( &runner.graph, a ).await.map( | ( v, h, p ) | ( NodeResult::Value( v ), h, p ) ),
+                None => execute_agent( &runner.graph, a ).await.map( | ( s, h, p ) | ( NodeResult::Text( s ), h, p ) )
             }
+            .prepend_err( format!( "NodeExecutor::Agent {}\n", node.id ) );
+
+            let result = match result.and_then( | ( r, h, p ) | save_fallback_provider( &mut runner.graph, a, p, &node.id ).map( | _ | ( r, h ) ) )
+            {
+                Ok( ( r, h ) ) =>
+                {
+                    if a.save_history
+                    {
+                        let ( _, agent ) = node.executor.own_agent();
+
+                        let mut agent = agent.unwrap();
+
+                        agent.history = h;
+
+                        node.executor = NodeExecutor::Agent( agent );
+                    }
+
+                    Ok( r )
+                },
+                Err( e ) => Err( e )
+            };
+
+            runner.graph.nodes.insert( runner.next.clone(), node );
+
+            return ( runner, result )
         },
         NodeExecutor::WebClient( c ) =>
         {
@@ -176,38 +559,6 @@ async fn execute_node( mut runner : GraphRunner ) -> ( AwpakResult<GraphRunner,
                 result
             )
         },
-        NodeExecutor::Agent( a ) =>
-        {
-            let result = execute_agent( 
-                &runner.graph, 
-                a 
-            ).await
-            .prepend_err( format!( "NodeExecutor::Agent {}\n", node.id ) );
-
-            match result
-            {
-                Ok( ( s, h ) ) =>
-                {
-                    if a.save_history
-                    {
-                        let ( _, agent ) = node.executor.own_agent();
-
-                        let mut agent = agent.unwrap();
-
-                        agent.history = h;
-
-                        node.executor = NodeExecutor::Agent( agent );
-
-                        ( node, Ok( s ) )
-                    }
-                    else
-                    {
-                        ( node, Ok( s ) )
-                    }
-                },
-                Err( e ) => ( node, Err( e ) )
-            }
-        },
         NodeExecutor::Graph( _ ) =>
         {
             let ( _, g ) = node.executor.own_graph();
@@ -303,19 +654,49 @@ async fn execute_node( mut runner : GraphRunner ) -> ( AwpakResult<GraphRunner,
                     )
                 }
             }
-        }
+        },
+        NodeExecutor::HumanInput( h ) =>
+        {
+            let result = execute_human_input( 
+                &runner.graph, 
+                &node.id,
+                h 
+            ).await
+            .prepend_err( format!( "NodeExecutor::HumanInput {}\n", node.id ) );
+
+            (
+                node,
+                result
+            )
+        },
+        NodeExecutor::Join( _ ) => ( node, Ok( "".into() ) )
     };
 
     runner.graph.nodes.insert( runner.next.clone(), node );
 
-    match result
+    ( runner, result.map( NodeResult::Text ) )
+}
+
+/// Saves the provider that answered in the `output` of a `Fallback` provider
+fn save_fallback_provider( graph : &mut Graph, agent : &AIAgent, provider : Option<Value>, node_id : &str ) -> Result<(), Error>
+{
+    let ( Some( provider ), AIAgentProviderConfig::Fallback( AIAgentFallback { output : Some( path ), .. } ) ) = ( provider, &agent.provider ) else { return Ok( () ) };
+
+    let conf = DataToContext { path : path.clone(), ty : DataType::default(), merge : DataMerge::Insert, optional : false, schema : None };
+
+    match value_to_context( std::mem::take( &mut graph.context ), provider, &conf ).collect()
     {
-        Ok( s ) => proccess_result( s, runner ).await,
-        Err( e ) => ( AwpakResult::new_err( runner, e ), false )
+        ( ( c, _ ), None ) => { graph.context = c; Ok( () ) },
+        ( ( c, _ ), Some( e ) ) =>
+        {
+            graph.context = c;
+
+            Err( e.prepend_str( format!( "NodeExecutor::Agent {}. Fallback output {}\n", node_id, path ) ) )
+        }
     }
 }
 
-async fn proccess_parallel_result( result : Vec<Value>, mut runner : GraphRunner ) -> ( AwpakResult<GraphRunner, Error>, bool )
+fn proccess_value_result( result : Value, mut runner : GraphRunner ) -> AwpakResult<GraphRunner, Error>
 {
     info!(
         target:NODE_OUTPUT, 
@@ -331,27 +712,27 @@ async fn proccess_parallel_result( result : Vec<Value>, mut runner : GraphRunner
         {
             let context = runner.graph.context;
 
-            match value_to_context( context, Value::Array( result ), o ).collect()
+            match value_to_context( context, result, o ).collect()
             {
                 ( ( c, _ ), None ) =>
                 {
                     runner.graph.context = c;
 
-                    redirect_or_exit( runner ).await
+                    AwpakResult::new( runner )
                 },
                 ( ( c, _ ), Some( e ) ) =>
                 {
                     runner.graph.context = c;
 
-                    ( AwpakResult::new_err( runner, e ), false )
+                    AwpakResult::new_err( runner, e )
                 }
             }
         },
-        None => redirect_or_exit( runner ).await
+        None => AwpakResult::new( runner )
     }
 }
 
-async fn proccess_result( str_result : String, runner : GraphRunner ) -> ( AwpakResult<GraphRunner, Error>, bool )
+async fn proccess_result( str_result : String, runner : GraphRunner ) -> AwpakResult<GraphRunner, Error>
 {
     info!(
         target:NODE_OUTPUT, 
@@ -359,17 +740,15 @@ async fn proccess_result( str_result : String, runner : GraphRunner ) -> ( Awpak
         text=str_result
     );
 
-    match output_to_context( str_result, runner ).await.collect()
-    {
-        ( g, None ) => redirect_or_exit( g ).await,
-        ( g, Some( e ) ) => ( AwpakResult::new_err( g, e ), false )
-    }
+    output_to_context( str_result, runner ).await
 }
 
 async fn redirect_or_exit( runner : GraphRunner ) -> ( AwpakResult<GraphRunner, Error>, bool )
 {
     let node = runner.graph.nodes.get( runner.next.as_str() ).unwrap();
 
+    if node.fan_out { return fan_out( runner ).await }
+
     let mut count : usize = 0;
 
     for d in &node.destination
@@ -387,10 +766,12 @@ async fn redirect_or_exit( runner : GraphRunner ) -> ( AwpakResult<GraphRunner,
             {
                 let msg = format!( "NodeDestination condition. NodeId: {}. Destination {}\n", node.id, count );
 
+                let e = node_err( &runner, e.prepend_str( msg ) );
+
                 return ( 
                     AwpakResult::new_err( 
                         runner, 
-                        e.prepend_str( msg )
+                        e
                     ), 
                     false 
                 )
@@ -402,7 +783,184 @@ async fn redirect_or_exit( runner : GraphRunner ) -> ( AwpakResult<GraphRunner,
 
     let msg = format!( "Destination not found in node: {}", node.id );
 
-    ( AwpakResult::new_err( runner, Error::NodeNotFound( msg ) ), false )
+    let e = node_err( &runner, Error::NodeNotFound( msg ) );
+
+    ( AwpakResult::new_err( runner, e ), false )
+}
+
+#[async_recursion]
+async fn fan_out( mut runner : GraphRunner ) -> ( AwpakResult<GraphRunner, Error>, bool )
+{
+    let node = runner.graph.nodes.get( runner.next.as_str() ).unwrap();
+
+    let mut branches = vec![];
+    let mut exit = None;
+
+    for ( idx, d ) in node.destination.iter().enumerate()
+    {
+        match check_node_destination_condition( &runner.graph, &d.condition ).await
+        {
+            Ok( true ) => match &d.next
+            {
+                NodeNext::Node( n ) => branches.push( n.clone() ),
+                _ => if exit.is_none() { exit = Some( d.clone() ) }
+            },
+            Ok( false ) => {},
+            Err( e ) =>
+            {
+                let msg = format!( "NodeDestination condition. NodeId: {}. Destination {}\n", node.id, idx );
+
+                let e = node_err( &runner, e.prepend_str( msg ) );
+
+                return ( AwpakResult::new_err( runner, e ), false )
+            }
+        }
+    }
+
+    let from = node.id.clone();
+
+    if branches.is_empty()
+    {
+        return match exit
+        {
+            Some( d ) => update_next( from, runner, d ).await,
+            None =>
+            {
+                let e = node_err( &runner, Error::NodeNotFound( format!( "Destination not found in node: {}", from ) ) );
+
+                ( AwpakResult::new_err( runner, e ), false )
+            }
+        }
+    }
+
+    trace_node_destination( from.clone(), &branches.join( ", " ), runner.graph.id.as_ref() );
+
+    let mut tasks = JoinSet::new();
+
+    for ( idx, b ) in branches.iter().enumerate()
+    {
+        tasks.spawn( run_branch( runner.fork( b.clone() ), idx ) );
+    }
+
+    let mut results = tasks.join_all().await;
+
+    results.sort_by_key( | ( _, idx ) | *idx );
+
+    let mut forks = vec![];
+
+    for ( ( result, _ ), name ) in results.into_iter().zip( branches )
+    {
+        match result.collect()
+        {
+            ( r, None ) => forks.push( ( name, r ) ),
+            ( _, Some( e ) ) =>
+            {
+                let e = node_err( &runner, e );
+
+                return ( AwpakResult::new_err( runner, e ), false )
+            }
+        }
+    }
+
+    let join_id = forks[ 0 ].1.next.clone();
+
+    let join = match runner.graph.nodes.get( join_id.as_str() ).map( | n | &n.executor )
+    {
+        Some( NodeExecutor::Join( j ) ) if forks.iter().all( | ( _, f ) | f.next == join_id ) => j.clone(),
+        _ =>
+        {
+            let msg = format!( "Fan-out from {}. Branches end in different nodes: {:?}", from, forks.iter().map( | ( _, f ) | f.next.as_str() ).collect::<Vec<_>>() );
+
+            let e = node_err( &runner, Error::Validation( msg ) );
+
+            return ( AwpakResult::new_err( runner, e ), false )
+        }
+    };
+
+    if let Some( b ) = join.branches.iter().find( | b | ! forks.iter().any( | ( n, _ ) | n == *b ) )
+    {
+        let msg = format!( "Join {}. Branch {} was not started from {}", join_id, b, from );
+
+        let e = node_err( &runner, Error::Validation( msg ) );
+
+        return ( AwpakResult::new_err( runner, e ), false )
+    }
+
+    let mut contexts = vec![];
+
+    let steps = runner.steps;
+    let visits = runner.visits.clone();
+
+    for ( name, mut fork ) in forks
+    {
+        // Nodes executed in the branch keep their state (agent histories, sub-graphs)
+        for ( id, v ) in fork.visits
+        {
+            let delta = v - visits.get( &id ).copied().unwrap_or( 0 );
+
+            if delta == 0 { continue }
+
+            *runner.visits.entry( id.clone() ).or_insert( 0 ) += delta;
+
+            if let Some( n ) = fork.graph.nodes.remove( &id ) { runner.graph.nodes.insert( id, n ); }
+        }
+
+        runner.steps += fork.steps - steps;
+
+        contexts.push( ( name, fork.graph.context ) );
+    }
+
+    match join_contexts( runner.graph.context, contexts, &join ).collect()
+    {
+        ( c, None ) => runner.graph.context = c,
+        ( c, Some( e ) ) =>
+        {
+            runner.graph.context = c;
+
+            let e = node_err( &runner, e );
+
+            return ( AwpakResult::new_err( runner, e ), false )
+        }
+    }
+
+    trace_node_destination( format!( "Fan-out {}", from ), join_id.as_str(), runner.graph.id.as_ref() );
+
+    runner.next = join_id;
+
+    next_step( runner ).await
+}
+
+#[async_recursion]
+async fn run_branch( mut runner : GraphRunner, idx : usize ) -> ( AwpakResult<GraphRunner, Error>, usize )
+{
+    let start = runner.next.clone();
+
+    loop
+    {
+        if let Some( NodeExecutor::Join( _ ) ) = runner.graph.nodes.get( runner.next.as_str() ).map( | n | &n.executor )
+        {
+            return ( AwpakResult::new( runner ), idx )
+        }
+
+        runner = match next_step( runner ).await
+        {
+            ( r, true ) => match r.collect()
+            {
+                ( r, None ) => r,
+                ( r, Some( e ) ) => return ( AwpakResult::new_err( r, e ), idx )
+            },
+            ( r, false ) => match r.collect()
+            {
+                ( r, None ) =>
+                {
+                    let msg = format!( "Branch {} exited before reaching a Join node", start );
+
+                    return ( AwpakResult::new_err( r, Error::Validation( msg ) ), idx )
+                },
+                ( r, Some( e ) ) => return ( AwpakResult::new_err( r, e ), idx )
+            }
+        };
+    }
 }
 
 fn trace_node_destination( from : String, to : &str, graph_id : Option<&String> )
@@ -476,7 +1034,7 @@ async fn output_to_context( output : String, mut runner : GraphRunner ) -> Awpak
 {
     let node = runner.graph.nodes.get( runner.next.as_str() ).unwrap();
 
-    if let NodeExecutor::ContextMut( _ ) | NodeExecutor::AgentHistoryMut( _ ) = &node.executor
+    if let NodeExecutor::ContextMut( _ ) | NodeExecutor::AgentHistoryMut( _ ) | NodeExecutor::Join( _ ) = &node.executor
     {
         return AwpakResult::new( runner );
     }
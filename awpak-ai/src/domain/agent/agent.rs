@@ -2,7 +2,7 @@ use rig::message::Message;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::domain::{data::data::{DataFrom, DataToString}, mcp::mcp::NodeMCPServer};
+use crate::domain::{agent::{agent_mock::MockResponse, agent_tool::AgentTool}, data::data::{DataFrom, DataToString}, error::Error, mcp::mcp::NodeMCPServer, web_client::web_client::WebClientNameValue};
 
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,6 +18,10 @@ pub struct AIAgent
     #[serde(default)]
     pub servers : Vec<NodeMCPServer>,
 
+    /// Tools declared in the graph, executed without an MCP server
+    #[serde(default)]
+    pub tools : Vec<AgentTool>,
+
     #[serde(default)]
     pub prompt : Vec<DataToString>,
 
@@ -30,10 +34,25 @@ pub struct AIAgent
     #[serde(default)]
     pub turns : Option<usize>,
 
+    #[serde(default)]
+    pub parameters : Box<AIAgentParameters>,
+
+    /// JSON Schema of the response. The response is parsed and validated as JSON
+    #[serde(default)]
+    pub output_schema : Option<Value>,
+    /// Times the agent is asked to fix a response that does not match `output_schema`
+    #[serde(default = "agent_default_output_retries")]
+    pub output_retries : usize,
+
     #[serde(default = "agent_default_id")]
     pub __id : String
 }
 
+fn agent_default_output_retries() -> usize
+{
+    2
+}
+
 fn agent_default_id() -> String
 {
     uuid::Uuid::new_v4().to_string()
@@ -45,20 +64,45 @@ impl Default for AIAgent
     {
         Self 
         { 
-            provider : AIAgentProviderConfig::Ollama( OllamaConfig { model : DataFrom::Static( Value::String( "llama3.1".into() ) ) } ), 
+            provider : AIAgentProviderConfig::Ollama( OllamaConfig { model : DataFrom::Static( Value::String( "llama3.1".into() ) ), base_url : None } ), 
             system_prompt : vec![], 
             save_history : false, 
             servers : vec![], 
+            tools : vec![],
             prompt : vec![], 
             history: vec![],
             is_stream : false,
             turns : None,
+            parameters : Box::default(),
+            output_schema : None,
+            output_retries : agent_default_output_retries(),
             // embeddings : vec![],
             __id : "".into()
         }
     }
 }
 
+/// Sampling parameters. Each value is sent with the name the provider uses
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AIAgentParameters
+{
+    #[serde(default)]
+    pub temperature : Option<DataFrom>,
+    #[serde(default)]
+    pub top_p : Option<DataFrom>,
+    /// Overrides the `max_tokens` of the provider config
+    #[serde(default)]
+    pub max_tokens : Option<DataFrom>,
+    /// A string or an array of strings
+    #[serde(default)]
+    pub stop : Option<DataFrom>,
+    #[serde(default)]
+    pub seed : Option<DataFrom>,
+    /// Object merged into the request of the provider. Its keys replace the other parameters
+    #[serde(default)]
+    pub additional_params : Option<DataFrom>
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum AIAgentProviderConfig
 {
@@ -66,13 +110,120 @@ pub enum AIAgentProviderConfig
     OpenAI( OpenAIConfig ),
     Anthropic( AnthropicConfig ),
     DeepSeek( DeepSeekConfig ),
-    Gemini( GeminiConfig )
+    Gemini( GeminiConfig ),
+    /// Any server with the OpenAI chat completions API (vLLM, llama.cpp server, gateways...)
+    OpenAICompatible( OpenAICompatibleConfig ),
+    /// Scripted responses, for tests without a live model
+    Mock( MockConfig ),
+    /// Providers tried in order until one answers
+    Fallback( AIAgentFallback )
+}
+
+impl AIAgentProviderConfig
+{
+    /// `DataFrom::Null` for `Fallback`, whose providers have their own model
+    pub fn model( &self ) -> &DataFrom
+    {
+        match self
+        {
+            AIAgentProviderConfig::Ollama( c ) => &c.model,
+            AIAgentProviderConfig::OpenAI( c ) => &c.model,
+            AIAgentProviderConfig::Anthropic( c ) => &c.model,
+            AIAgentProviderConfig::DeepSeek( c ) => &c.model,
+            AIAgentProviderConfig::Gemini( c ) => &c.model,
+            AIAgentProviderConfig::OpenAICompatible( c ) => &c.model,
+            AIAgentProviderConfig::Mock( c ) => &c.model,
+            AIAgentProviderConfig::Fallback( _ ) => &DataFrom::Null
+        }
+    }
+
+    pub fn name( &self ) -> &'static str
+    {
+        match self
+        {
+            AIAgentProviderConfig::Ollama( _ ) => "Ollama",
+            AIAgentProviderConfig::OpenAI( _ ) => "OpenAI",
+            AIAgentProviderConfig::Anthropic( _ ) => "Anthropic",
+            AIAgentProviderConfig::DeepSeek( _ ) => "DeepSeek",
+            AIAgentProviderConfig::Gemini( _ ) => "Gemini",
+            AIAgentProviderConfig::OpenAICompatible( _ ) => "OpenAICompatible",
+            AIAgentProviderConfig::Mock( _ ) => "Mock",
+            AIAgentProviderConfig::Fallback( _ ) => "Fallback"
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AIAgentFallback
+{
+    pub providers : Vec<AIAgentFallbackProvider>,
+    /// Context key where the provider that answered is saved
+    #[serde(default)]
+    pub output : Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AIAgentFallbackProvider
+{
+    pub provider : AIAgentProviderConfig,
+    /// Milliseconds. A slower call fails with a timeout
+    #[serde(default)]
+    pub timeout : Option<u64>,
+    /// Errors that move to the next provider. Empty for any error
+    #[serde(default)]
+    pub fallback_on : Vec<AIAgentFallbackCondition>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AIAgentFallbackCondition
+{
+    /// Error kind, like in `retry.errors`
+    Error( String ),
+    /// Text in the error message, e.g. "429" or "rate limit". Case insensitive
+    Contains( String ),
+    Timeout,
+    /// The prompt does not fit in the context window of the model
+    ContextTooLong
 }
 
+impl AIAgentFallbackProvider
+{
+    pub fn falls_back( &self, err : &Error, timed_out : bool ) -> bool
+    {
+        if self.fallback_on.is_empty() { return true }
+
+        let text = err.to_string().to_lowercase();
+
+        self.fallback_on.iter().any(
+            | c | match c
+            {
+                AIAgentFallbackCondition::Error( k ) => k == err.kind(),
+                AIAgentFallbackCondition::Contains( s ) => text.contains( &s.to_lowercase() ),
+                AIAgentFallbackCondition::Timeout => timed_out,
+                AIAgentFallbackCondition::ContextTooLong => CONTEXT_TOO_LONG_ERRORS.iter().any( | e | text.contains( e ) )
+            }
+        )
+    }
+}
+
+/// Messages of the providers when the prompt exceeds the context window
+const CONTEXT_TOO_LONG_ERRORS : [&str; 7] = [
+    "context_length_exceeded",
+    "context length",
+    "context window",
+    "maximum context",
+    "prompt is too long",
+    "input is too long",
+    "too many tokens"
+];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OllamaConfig
 {
-    pub model : DataFrom
+    pub model : DataFrom,
+    /// Default http://localhost:11434
+    #[serde(default)]
+    pub base_url : Option<DataFrom>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -104,4 +255,40 @@ pub struct GeminiConfig
 {
     pub api_key : String,
     pub model : DataFrom
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenAICompatibleConfig
+{
+    /// URL before `/chat/completions`, e.g. http://localhost:8000/v1
+    pub base_url : DataFrom,
+    /// Environment variable with the API key. No key is sent if empty
+    #[serde(default)]
+    pub api_key : Option<String>,
+    pub model : DataFrom,
+    #[serde(default)]
+    pub headers : Vec<WebClientNameValue>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MockConfig
+{
+    #[serde(default = "mock_default_model")]
+    pub model : DataFrom,
+    /// Responses with `pattern` are chosen by regex on the prompt. The others by call index
+    #[serde(default)]
+    pub responses : Vec<MockResponse>,
+    /// JSONL file with a response per line. Its responses are added after `responses`
+    #[serde(default)]
+    pub fixture : Option<String>,
+
+    /// Key of the call index. The index starts at 0 in each graph run.
+    /// Not serialized, so it is not part of the cassette key of the agent
+    #[serde(default = "agent_default_id", skip_serializing)]
+    pub __id : String
+}
+
+fn mock_default_model() -> DataFrom
+{
+    DataFrom::Static( Value::String( "mock".into() ) )
+}
@@ -0,0 +1,299 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+
+use regex::Regex;
+use rig::{completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Usage}, message::{AssistantContent, Message, ToolResultContent, UserContent}, streaming::{RawStreamingChoice, StreamingCompletionResponse}, OneOrMany};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::error::Error;
+
+
+/// Scripted response of the `Mock` provider
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MockResponse
+{
+    /// Regex on the prompt. Responses without pattern are returned by call index
+    #[serde(default)]
+    pub pattern : Option<String>,
+    #[serde(default)]
+    pub text : String,
+    /// Tool calls of the response. The model is called again with the tool results
+    #[serde(default)]
+    pub tool_calls : Vec<MockToolCall>,
+    /// The call fails with this error
+    #[serde(default)]
+    pub error : Option<String>,
+    /// Milliseconds before the response, to test timeouts
+    #[serde(default)]
+    pub delay : Option<u64>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MockToolCall
+{
+    pub name : String,
+    #[serde(default)]
+    pub args : Value
+}
+
+/// Usage of the streamed response, in the format read by `usage_from_stream_response`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MockStreamingResponse
+{
+    pub usage : Value
+}
+
+/// Completion model that returns scripted responses
+#[derive(Clone)]
+pub struct MockCompletionModel
+{
+    /// Id of the `MockConfig`
+    pub id : String,
+    pub calls : Arc<MockCalls>,
+    /// Responses with their compiled pattern
+    pub responses : Arc<Vec<( Option<Regex>, MockResponse )>>
+}
+
+/// Calls of each mock in a graph run, by `MockConfig` id
+#[derive(Debug, Default)]
+pub struct MockCalls
+{
+    calls : Mutex<HashMap<String, usize>>
+}
+
+impl MockCalls
+{
+    /// Returns the call index of the mock `id` and increments it
+    pub fn next( &self, id : &str ) -> usize
+    {
+        let mut calls = self.calls.lock().unwrap();
+
+        let call = calls.entry( id.to_string() ).or_default();
+
+        *call += 1;
+
+        *call - 1
+    }
+
+    pub fn start_run( &self )
+    {
+        self.calls.lock().unwrap().clear();
+    }
+
+    pub fn get( &self ) -> HashMap<String, usize>
+    {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Continues the run of a checkpoint
+    pub fn restore( &self, calls : HashMap<String, usize> )
+    {
+        *self.calls.lock().unwrap() = calls;
+    }
+}
+
+/// Compiles the pattern of the response
+pub fn compile_mock_response( response : MockResponse ) -> Result<( Option<Regex>, MockResponse ), Error>
+{
+    let regex = match &response.pattern
+    {
+        Some( p ) => Some( Regex::new( p ).map_err( | e | Error::ParseData( format!( "Invalid regex: {}. Error: {}", p, e ) ).with_source( e ) )? ),
+        None => None
+    };
+
+    Ok( ( regex, response ) )
+}
+
+/// Parses a JSONL fixture. Each line is a `MockResponse`
+pub fn parse_mock_fixture( content : &str ) -> Result<Vec<( Option<Regex>, MockResponse )>, Error>
+{
+    content.lines()
+    .enumerate()
+    .filter( | ( _, l ) | ! l.trim().is_empty() )
+    .map(
+        | ( i, l ) |
+        serde_json::from_str::<MockResponse>( l )
+        .map_err( | e | Error::ParseData( e.to_string() ).with_source( e ) )
+        .and_then( compile_mock_response )
+        .map_err( | e | e.prepend_str( format!( "Mock fixture line {}. ", i + 1 ) ) )
+    )
+    .collect()
+}
+
+impl MockCompletionModel
+{
+    /// The first response whose pattern matches the prompt. Otherwise the response of the call index.
+    ///
+    /// The last response without pattern is repeated when the calls exceed them.
+    pub fn response( &self, prompt : &str ) -> Result<MockResponse, CompletionError>
+    {
+        for ( regex, r ) in self.responses.iter()
+        {
+            if let Some( regex ) = regex && regex.is_match( prompt ) { return Ok( r.clone() ) }
+        }
+
+        let indexed = self.responses.iter().filter( | ( regex, _ ) | regex.is_none() ).map( | ( _, r ) | r ).collect::<Vec<_>>();
+
+        let call = self.calls.next( &self.id );
+
+        indexed.get( call ).or( indexed.last() ).map( | r | ( *r ).clone() )
+        .ok_or( CompletionError::ProviderError( format!( "No mock response for call {} and prompt {}", call, prompt ) ) )
+    }
+
+    async fn request_response( &self, request : &CompletionRequest ) -> Result<( MockResponse, Usage ), CompletionError>
+    {
+        let prompt = request.chat_history.iter().last().map( message_text ).unwrap_or_default();
+
+        let response = self.response( &prompt )?;
+
+        if let Some( d ) = response.delay { tokio::time::sleep( Duration::from_millis( d ) ).await }
+
+        if let Some( e ) = &response.error { return Err( CompletionError::ProviderError( e.clone() ) ) }
+
+        let input_tokens = request.chat_history.iter().map( | m | count_words( &message_text( m ) ) ).sum::<u64>()
+        + request.preamble.as_deref().map( count_words ).unwrap_or_default();
+
+        let output_tokens = count_words( &response.text );
+
+        Ok( ( response, Usage { input_tokens, output_tokens, total_tokens : input_tokens + output_tokens } ) )
+    }
+}
+
+/// Text of the user and assistant contents and of the tool results
+fn message_text( message : &Message ) -> String
+{
+    match message
+    {
+        Message::User { content } => content.iter().filter_map(
+            | c | match c
+            {
+                UserContent::Text( t ) => Some( t.text.clone() ),
+                UserContent::ToolResult( r ) => Some(
+                    r.content.iter().filter_map( | c | match c { ToolResultContent::Text( t ) => Some( tool_result_text( &t.text ) ), _ => None } )
+                    .collect::<Vec<_>>()
+                    .join( "\n" )
+                ),
+                _ => None
+            }
+        )
+        .collect::<Vec<_>>()
+        .join( "\n" ),
+        Message::Assistant { content, .. } => content.iter().filter_map(
+            | c | match c { AssistantContent::Text( t ) => Some( t.text.clone() ), _ => None }
+        )
+        .collect::<Vec<_>>()
+        .join( "\n" )
+    }
+}
+
+/// rig sends string tool results as JSON strings
+fn tool_result_text( text : &str ) -> String
+{
+    match serde_json::from_str::<Value>( text )
+    {
+        Ok( Value::String( s ) ) => s,
+        _ => text.to_string()
+    }
+}
+
+fn count_words( text : &str ) -> u64
+{
+    text.split_whitespace().count() as u64
+}
+
+fn tool_call_id( call : usize ) -> String
+{
+    format!( "mock_call_{}", call )
+}
+
+impl CompletionModel for MockCompletionModel
+{
+    type Response = Value;
+    type StreamingResponse = MockStreamingResponse;
+
+    async fn completion( &self, request : CompletionRequest ) -> Result<CompletionResponse<Self::Response>, CompletionError>
+    {
+        let ( response, usage ) = self.request_response( &request ).await?;
+
+        let mut content = response.tool_calls.iter().enumerate()
+        .map( | ( i, c ) | AssistantContent::tool_call( tool_call_id( i ), &c.name, c.args.clone() ) )
+        .collect::<Vec<_>>();
+
+        if content.is_empty() || ! response.text.is_empty()
+        {
+            content.insert( 0, AssistantContent::text( &response.text ) );
+        }
+
+        Ok(
+            CompletionResponse
+            {
+                choice : OneOrMany::many( content ).map_err( | e | CompletionError::ResponseError( e.to_string() ) )?,
+                usage,
+                raw_response : serde_json::to_value( &response )?
+            }
+        )
+    }
+
+    async fn stream( &self, request : CompletionRequest ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError>
+    {
+        let ( response, usage ) = self.request_response( &request ).await?;
+
+        let mut chunks = response.text.split_inclusive( ' ' )
+        .map( | t | Ok( RawStreamingChoice::Message( t.to_string() ) ) )
+        .collect::<Vec<_>>();
+
+        chunks.extend(
+            response.tool_calls.iter().enumerate()
+            .map(
+                | ( i, c ) |
+                Ok( RawStreamingChoice::ToolCall { id : tool_call_id( i ), call_id : None, name : c.name.clone(), arguments : c.args.clone() } )
+            )
+        );
+
+        chunks.push(
+            Ok(
+                RawStreamingChoice::FinalResponse(
+                    MockStreamingResponse
+                    {
+                        usage : json!(
+                            { "input_tokens" : usage.input_tokens, "output_tokens" : usage.output_tokens, "total_tokens" : usage.total_tokens }
+                        )
+                    }
+                )
+            )
+        );
+
+        Ok( StreamingCompletionResponse::stream( Box::pin( tokio_stream::iter( chunks ) ) ) )
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_mock_response()
+    {
+        let responses = parse_mock_fixture(
+            "{ \"text\" : \"first\" }\n\n{ \"text\" : \"second\" }\n{ \"pattern\" : \"^weather\", \"text\" : \"sunny\" }"
+        ).unwrap();
+
+        let model = MockCompletionModel { id : "mock".into(), calls : Arc::default(), responses : Arc::new( responses ) };
+
+        assert_eq!( model.response( "hello" ).unwrap().text, "first" );
+        assert_eq!( model.response( "weather in Madrid" ).unwrap().text, "sunny" );
+        assert_eq!( model.response( "hello" ).unwrap().text, "second" );
+        assert_eq!( model.response( "hello" ).unwrap().text, "second" );
+
+        model.calls.start_run();
+
+        assert_eq!( model.response( "hello" ).unwrap().text, "first" );
+
+        assert!( parse_mock_fixture( "{ \"text\" : 1 }" ).is_err() );
+
+        let err = parse_mock_fixture( "{ \"text\" : \"first\" }\n{ \"pattern\" : \"(\", \"text\" : \"second\" }" ).unwrap_err();
+
+        assert!( err.to_string().contains( "Mock fixture line 2. Invalid regex: (" ), "{}", err );
+    }
+}
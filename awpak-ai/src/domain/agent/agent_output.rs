@@ -0,0 +1,92 @@
+use serde_json::{json, Value};
+
+use crate::domain::{agent::agent::AIAgentProviderConfig, error::Error};
+
+
+/// Provider params for native JSON output. `None` if the provider has no JSON mode.
+pub fn native_output_params( provider : &AIAgentProviderConfig, schema : &Value ) -> Option<Value>
+{
+    match provider
+    {
+        AIAgentProviderConfig::OpenAI( _ ) => Some(
+            json!( { "text" : { "format" : { "type" : "json_schema", "name" : "output", "schema" : schema, "strict" : false } } } )
+        ),
+        AIAgentProviderConfig::DeepSeek( _ ) |
+        AIAgentProviderConfig::OpenAICompatible( _ ) => Some( json!( { "response_format" : { "type" : "json_object" } } ) ),
+        AIAgentProviderConfig::Gemini( _ ) => Some( json!( { "responseMimeType" : "application/json" } ) ),
+        AIAgentProviderConfig::Ollama( _ ) |
+        AIAgentProviderConfig::Anthropic( _ ) |
+        AIAgentProviderConfig::Mock( _ ) |
+        AIAgentProviderConfig::Fallback( _ ) => None
+    }
+}
+
+pub fn structured_output_prompt( prompt : String, schema : &Value ) -> String
+{
+    format!(
+        "{}\n\nRespond only with a JSON value that matches this JSON Schema. Do not add any other text.\n{}",
+        prompt,
+        schema
+    )
+}
+
+pub fn repair_output_prompt( err : &Error ) -> String
+{
+    format!(
+        "Your previous response is not valid:\n{}\n\nRespond again only with a JSON value that matches the JSON Schema.",
+        err
+    )
+}
+
+/// Extracts a JSON value from a model response.
+///
+/// Accepts plain JSON, JSON inside a markdown code block, or the first object or array in the text.
+pub fn extract_json( text : &str ) -> Result<Value, Error>
+{
+    let text = text.trim();
+
+    if let Ok( v ) = serde_json::from_str( text ) { return Ok( v ) }
+
+    if let Some( v ) = fenced_json( text ) { return Ok( v ) }
+
+    for ( open, close ) in [ ( '{', '}' ), ( '[', ']' ) ]
+    {
+        if let ( Some( start ), Some( end ) ) = ( text.find( open ), text.rfind( close ) ) &&
+           start < end &&
+           let Ok( v ) = serde_json::from_str( &text[ start..=end ] )
+        {
+            return Ok( v )
+        }
+    }
+
+    Err( Error::ParseData( format!( "JSON not found in agent response: {}", text ) ) )
+}
+
+fn fenced_json( text : &str ) -> Option<Value>
+{
+    let start = text.find( "```" )?;
+
+    let after = &text[ start + 3.. ];
+
+    let after = &after[ after.find( '\n' )? + 1.. ];
+
+    let end = after.find( "```" )?;
+
+    serde_json::from_str( after[ ..end ].trim() ).ok()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_extract_json()
+    {
+        assert_eq!( extract_json( r#" { "a" : 1 } "# ).unwrap(), json!( { "a" : 1 } ) );
+        assert_eq!( extract_json( "Sure!\n```json\n{ \"a\" : [ 1, 2 ] }\n```\nAnything else?" ).unwrap(), json!( { "a" : [ 1, 2 ] } ) );
+        assert_eq!( extract_json( "The result is { \"ok\" : true }. Bye" ).unwrap(), json!( { "ok" : true } ) );
+        assert_eq!( extract_json( "Values: [ 1, 2, 3 ]" ).unwrap(), json!( [ 1, 2, 3 ] ) );
+        assert!( extract_json( "No JSON here" ).is_err() );
+    }
+}
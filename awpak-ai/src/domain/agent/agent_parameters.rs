@@ -0,0 +1,207 @@
+use rig::{agent::AgentBuilder, completion::CompletionModel};
+use serde_json::{json, Map, Value};
+
+use crate::domain::{agent::{agent::{AIAgent, AIAgentParameters, AIAgentProviderConfig}, agent_output::native_output_params}, data::{data::DataFrom, data_insert::merge_values, data_selection::data_selection, data_utils::value_to_string}, error::{ChangeError, Error}, graph::graph::Graph};
+
+
+/// Values of `AIAgentParameters` with their `DataFrom` resolved
+#[derive(Debug, Default, PartialEq)]
+pub struct SamplingParameters
+{
+    pub temperature : Option<f64>,
+    pub top_p : Option<f64>,
+    pub max_tokens : Option<u64>,
+    pub stop : Vec<String>,
+    pub seed : Option<u64>,
+    pub additional_params : Option<Value>
+}
+
+/// Sets the agent parameters and the native output params of `output_schema`
+pub async fn apply_agent_parameters<M: CompletionModel>(
+    graph : &Graph,
+    ai_agent : &AIAgent,
+    mut agent : AgentBuilder<M>
+) -> Result<AgentBuilder<M>, Error>
+{
+    let parameters = sampling_parameters( graph, &ai_agent.parameters ).await.prepend_err( "Agent parameters.\n" )?;
+
+    if let Some( t ) = parameters.temperature { agent = agent.temperature( t ) }
+
+    if let Some( m ) = parameters.max_tokens { agent = agent.max_tokens( m ) }
+
+    let native = ai_agent.output_schema.as_ref().and_then( | s | native_output_params( &ai_agent.provider, s ) );
+
+    let params = [
+        native,
+        provider_params( &ai_agent.provider, &parameters )?,
+        parameters.additional_params
+    ]
+    .into_iter()
+    .flatten()
+    .fold( Value::Null, merge_values );
+
+    if ! params.is_null() { agent = agent.additional_params( params ) }
+
+    Ok( agent )
+}
+
+/// Parameters without a field in the rig agent builder, with the names of each provider
+pub fn provider_params( provider : &AIAgentProviderConfig, parameters : &SamplingParameters ) -> Result<Option<Value>, Error>
+{
+    let unsupported = | name : &str, provider : &str | Err( Error::Agent( format!( "{} is not supported by {}", name, provider ) ) );
+
+    let stop = if parameters.stop.is_empty() { None } else { Some( &parameters.stop ) };
+
+    let params = match provider
+    {
+        AIAgentProviderConfig::OpenAI( _ ) =>
+        {
+            if stop.is_some() { return unsupported( "stop", "OpenAI" ) }
+            if parameters.seed.is_some() { return unsupported( "seed", "OpenAI" ) }
+
+            json!( { "top_p" : parameters.top_p } )
+        },
+        AIAgentProviderConfig::Anthropic( _ ) =>
+        {
+            if parameters.seed.is_some() { return unsupported( "seed", "Anthropic" ) }
+
+            json!( { "top_p" : parameters.top_p, "stop_sequences" : stop } )
+        },
+        AIAgentProviderConfig::Gemini( _ ) =>
+        {
+            if parameters.seed.is_some() { return unsupported( "seed", "Gemini" ) }
+
+            json!( { "topP" : parameters.top_p, "stopSequences" : stop } )
+        },
+        // Sent in `options`. Ollama ignores max_tokens
+        AIAgentProviderConfig::Ollama( _ ) => json!(
+            { "top_p" : parameters.top_p, "stop" : stop, "seed" : parameters.seed, "num_predict" : parameters.max_tokens }
+        ),
+        // rig does not send max_tokens to chat completions APIs
+        AIAgentProviderConfig::DeepSeek( c ) => json!(
+            { "top_p" : parameters.top_p, "stop" : stop, "seed" : parameters.seed, "max_tokens" : parameters.max_tokens.or( c.max_tokens ) }
+        ),
+        AIAgentProviderConfig::OpenAICompatible( _ ) => json!(
+            { "top_p" : parameters.top_p, "stop" : stop, "seed" : parameters.seed, "max_tokens" : parameters.max_tokens }
+        ),
+        // Ignored by the mock. Fallback providers use their own
+        AIAgentProviderConfig::Mock( _ ) |
+        AIAgentProviderConfig::Fallback( _ ) => json!( {} )
+    };
+
+    let params = params.as_object().unwrap().iter()
+    .filter( | ( _, v ) | ! v.is_null() )
+    .map( | ( k, v ) | ( k.clone(), v.clone() ) )
+    .collect::<Map<String, Value>>();
+
+    Ok( if params.is_empty() { None } else { Some( Value::Object( params ) ) } )
+}
+
+pub async fn sampling_parameters( graph : &Graph, parameters : &AIAgentParameters ) -> Result<SamplingParameters, Error>
+{
+    Ok(
+        SamplingParameters
+        {
+            temperature : number( graph, parameters.temperature.as_ref(), "temperature" ).await?,
+            top_p : number( graph, parameters.top_p.as_ref(), "top_p" ).await?,
+            max_tokens : integer( graph, parameters.max_tokens.as_ref(), "max_tokens" ).await?,
+            stop : stop( graph, parameters.stop.as_ref() ).await?,
+            seed : integer( graph, parameters.seed.as_ref(), "seed" ).await?,
+            additional_params : match &parameters.additional_params
+            {
+                Some( p ) => match data_selection( graph, p ).await?
+                {
+                    v @ Value::Object( _ ) => Some( v ),
+                    Value::Null => None,
+                    v => return Err( Error::ParseData( format!( "additional_params {} is not an object", v ) ) )
+                },
+                None => None
+            }
+        }
+    )
+}
+
+async fn number( graph : &Graph, from : Option<&DataFrom>, name : &str ) -> Result<Option<f64>, Error>
+{
+    let Some( from ) = from else { return Ok( None ) };
+
+    match data_selection( graph, from ).await?
+    {
+        Value::Null => Ok( None ),
+        Value::Number( n ) => Ok( n.as_f64() ),
+        v => value_to_string( &v ).trim().parse::<f64>().map( Some )
+        .map_err( | e | Error::ParseData( format!( "{} {} is not a number. {}", name, v, e ) ) )
+    }
+}
+
+async fn integer( graph : &Graph, from : Option<&DataFrom>, name : &str ) -> Result<Option<u64>, Error>
+{
+    match number( graph, from, name ).await?
+    {
+        Some( n ) if n >= 0.0 && n.fract() == 0.0 => Ok( Some( n as u64 ) ),
+        Some( n ) => Err( Error::ParseData( format!( "{} {} is not a positive integer", name, n ) ) ),
+        None => Ok( None )
+    }
+}
+
+async fn stop( graph : &Graph, from : Option<&DataFrom> ) -> Result<Vec<String>, Error>
+{
+    let Some( from ) = from else { return Ok( vec![] ) };
+
+    Ok(
+        match data_selection( graph, from ).await?
+        {
+            Value::Null => vec![],
+            Value::Array( a ) => a.iter().map( value_to_string ).collect(),
+            v => vec![ value_to_string( &v ) ]
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::domain::agent::agent::{AnthropicConfig, OllamaConfig};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sampling_parameters()
+    {
+        let mut graph = Graph::default();
+
+        graph.context.insert( "temperature".into(), json!( "0.2" ) );
+
+        let parameters : AIAgentParameters = serde_json::from_value( json!(
+            {
+                "temperature" : { "Context" : { "path" : "temperature" } },
+                "max_tokens" : { "Static" : 200 },
+                "stop" : { "Static" : "END" },
+                "seed" : { "Static" : 7 },
+                "additional_params" : { "Static" : { "top_k" : 40 } }
+            }
+        ) ).unwrap();
+
+        let parameters = sampling_parameters( &graph, &parameters ).await.unwrap();
+
+        assert_eq!( parameters.temperature, Some( 0.2 ) );
+        assert_eq!( parameters.max_tokens, Some( 200 ) );
+        assert_eq!( parameters.stop, vec![ "END".to_string() ] );
+        assert_eq!( parameters.seed, Some( 7 ) );
+
+        let ollama = AIAgentProviderConfig::Ollama( OllamaConfig { model : DataFrom::Static( json!( "llama3.1" ) ), base_url : None } );
+
+        assert_eq!(
+            provider_params( &ollama, &parameters ).unwrap(),
+            Some( json!( { "stop" : [ "END" ], "seed" : 7, "num_predict" : 200 } ) )
+        );
+
+        let anthropic = AIAgentProviderConfig::Anthropic( AnthropicConfig { api_key : "".into(), model : DataFrom::Static( json!( "claude" ) ), max_tokens : 100 } );
+
+        assert!( provider_params( &anthropic, &parameters ).is_err() );
+
+        let bad : AIAgentParameters = serde_json::from_value( json!( { "seed" : { "Static" : -1 } } ) ).unwrap();
+
+        assert!( sampling_parameters( &graph, &bad ).await.is_err() );
+    }
+}
@@ -1,11 +1,15 @@
 use rig::agent::Agent;
 
+use crate::domain::{agent::agent_mock::MockCompletionModel, mcp::mcp_pool::MCPClient};
+
 
 pub enum AIAgentProvider
 {
-    Ollama( Agent<rig::providers::ollama::CompletionModel>, Vec<rmcp::service::RunningService<rmcp::RoleClient, ()>> ),
-    OpenAI( Agent<rig::providers::openai::responses_api::ResponsesCompletionModel>, Vec<rmcp::service::RunningService<rmcp::RoleClient, ()>> ),
-    Anthropic( Agent<rig::providers::anthropic::completion::CompletionModel>, Vec<rmcp::service::RunningService<rmcp::RoleClient, ()>> ),
-    DeepSeek( Agent<rig::providers::deepseek::CompletionModel>, Vec<rmcp::service::RunningService<rmcp::RoleClient, ()>> ),
-    Gemini( Agent<rig::providers::gemini::completion::CompletionModel>, Vec<rmcp::service::RunningService<rmcp::RoleClient, ()>> )
+    Ollama( Agent<rig::providers::ollama::CompletionModel>, Vec<MCPClient> ),
+    OpenAI( Agent<rig::providers::openai::responses_api::ResponsesCompletionModel>, Vec<MCPClient> ),
+    Anthropic( Agent<rig::providers::anthropic::completion::CompletionModel>, Vec<MCPClient> ),
+    DeepSeek( Agent<rig::providers::deepseek::CompletionModel>, Vec<MCPClient> ),
+    Gemini( Agent<rig::providers::gemini::completion::CompletionModel>, Vec<MCPClient> ),
+    OpenAICompatible( Agent<rig::providers::openai::completion::CompletionModel>, Vec<MCPClient> ),
+    Mock( Agent<MockCompletionModel>, Vec<MCPClient> )
 }
\ No newline at end of file
@@ -0,0 +1,253 @@
+use std::{fmt::Debug, future::Future, sync::Arc};
+
+use rig::{agent::AgentBuilder, completion::{CompletionModel, ToolDefinition}, tool::Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{application::graph::execute_graph::execute_graph, domain::{command::{command::Command, execute_command::execute_command}, data::data_schema::validate_schema, error::Error, graph::{graph::Graph, graph_node::GraphNode, node::GraphNodeConfig}, tracing::filter_layer::{AGENT_TOOL_CALL, AGENT_TOOL_RESULT}, utils::string_utils::option_string_to_str, web_client::{execute_web_client::execute_web_client, web_client::WebClient}}};
+
+
+/// Tool declared in the graph. Calls are executed by a `Command`, a `WebClient` or a sub-graph
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentTool
+{
+    pub name : String,
+    #[serde(default)]
+    pub description : String,
+    /// JSON Schema of the arguments
+    #[serde(default = "agent_tool_default_parameters")]
+    pub parameters : Value,
+    /// Context key where the arguments are inserted before the executor runs
+    #[serde(default = "agent_tool_default_args")]
+    pub args : String,
+    pub executor : AgentToolExecutor,
+    /// Sub-graph of a `Graph` executor. Built with the graph
+    #[serde(skip)]
+    pub graph : Option<AgentToolGraph>
+}
+
+/// Built sub-graph of a tool. Each call runs a copy
+#[derive(Clone)]
+pub struct AgentToolGraph( pub Arc<GraphNode> );
+
+impl Debug for AgentToolGraph
+{
+    fn fmt( &self, f : &mut std::fmt::Formatter<'_> ) -> std::fmt::Result
+    {
+        f.write_str( "AgentToolGraph" )
+    }
+}
+
+fn agent_tool_default_parameters() -> Value
+{
+    json!( { "type" : "object", "properties" : {} } )
+}
+
+fn agent_tool_default_args() -> String
+{
+    "args".into()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum AgentToolExecutor
+{
+    Command( Command ),
+    WebClient( WebClient ),
+    Graph( GraphNodeConfig )
+}
+
+pub fn add_tools_to_agent<M: CompletionModel>(
+    graph : &Graph,
+    agent : AgentBuilder<M>,
+    tools : &[AgentTool],
+    trace : bool
+) -> AgentBuilder<M>
+{
+    tools.iter().fold(
+        agent,
+        | builder, tool |
+        {
+            builder.tool( GraphTool { graph : graph.clone(), tool : tool.clone(), trace } )
+        }
+    )
+}
+
+struct GraphTool
+{
+    graph : Graph,
+    tool : AgentTool,
+    /// Streaming agents already trace tool calls
+    trace : bool
+}
+
+impl Tool for GraphTool
+{
+    const NAME : &'static str = "graph_tool";
+
+    type Error = Error;
+    type Args = Value;
+    type Output = String;
+
+    fn name( &self ) -> String
+    {
+        self.tool.name.clone()
+    }
+
+    async fn definition( &self, _prompt : String ) -> ToolDefinition
+    {
+        ToolDefinition
+        {
+            name : self.tool.name.clone(),
+            description : self.tool.description.clone(),
+            parameters : self.tool.parameters.clone()
+        }
+    }
+
+    fn call( &self, args : Self::Args ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send + Sync
+    {
+        // Executors are not Sync, so the call runs in its own task
+        let task = tokio::spawn( call_tool( self.graph.clone(), self.tool.clone(), args, self.trace ) );
+
+        async move
+        {
+            task.await.map_err( | e | Error::Agent( e.to_string() ).with_source( e ) )
+        }
+    }
+}
+
+/// Errors are returned to the agent as the tool result, so it can fix the arguments or try another way
+async fn call_tool( mut graph : Graph, tool : AgentTool, args : Value, trace : bool ) -> String
+{
+    let id = graph.id.clone();
+
+    if trace
+    {
+        info!(
+            target:AGENT_TOOL_CALL,
+            id=option_string_to_str( id.as_ref() ),
+            text=format!( "\nToolCall:\n- Function name: {}\n- Args: {}\n", tool.name, args )
+        );
+    }
+
+    let result = match validate_schema( &tool.parameters, &args ).map_err( | e | e.prepend_str( "Tool arguments.\n" ) )
+    {
+        Ok( _ ) =>
+        {
+            graph.context.insert( tool.args.clone(), args );
+
+            execute_tool( &graph, &tool ).await
+        },
+        Err( e ) => Err( e )
+    };
+
+    let result = match result
+    {
+        Ok( r ) => r,
+        Err( e ) => format!( "Error: {}", e )
+    };
+
+    if trace
+    {
+        info!(
+            target:AGENT_TOOL_RESULT,
+            id=option_string_to_str( id.as_ref() ),
+            text=format!( "\nTool result:\n{}\n", result )
+        );
+    }
+
+    result
+}
+
+async fn execute_tool( graph : &Graph, tool : &AgentTool ) -> Result<String, Error>
+{
+    match ( &tool.executor, &tool.graph )
+    {
+        ( AgentToolExecutor::Command( c ), _ ) => execute_command( graph, c ).await,
+        ( AgentToolExecutor::WebClient( c ), _ ) => execute_web_client( graph, c ).await,
+        ( AgentToolExecutor::Graph( _ ), Some( g ) ) =>
+        {
+            match execute_graph( graph, g.0.as_ref().clone() ).await.collect()
+            {
+                ( ( _, o ), None ) => Ok( o ),
+                ( _, Some( e ) ) => Err( e )
+            }
+        },
+        ( AgentToolExecutor::Graph( c ), None ) => Err( Error::Agent( format!( "Tool {} graph {} is not built", tool.name, c.path ) ) )
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::domain::{agent::agent::AIAgent, graph::build_graph_node::agent_from_config};
+
+    use super::*;
+
+    fn echo_tool() -> AgentTool
+    {
+        serde_json::from_value( json!(
+            {
+                "name" : "echo",
+                "parameters" : { "type" : "object", "properties" : { "text" : { "type" : "string" } }, "required" : [ "text" ] },
+                "executor" : {
+                    "Command" : {
+                        "command" : { "Static" : "echo" },
+                        "args" : [ { "Static" : "-n" }, { "Context" : { "path" : "args/text" } } ],
+                        "output" : [ { "Out" : {} } ]
+                    }
+                }
+            }
+        ) ).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_command()
+    {
+        let result = call_tool( Graph::default(), echo_tool(), json!( { "text" : "hello" } ), false ).await;
+
+        assert_eq!( result, "hello" );
+    }
+
+    fn graph_tool( path : &str ) -> AgentTool
+    {
+        serde_json::from_value( json!(
+            {
+                "name" : "echo_graph",
+                "executor" : {
+                    "Graph" : {
+                        "path" : path,
+                        "input" : [ { "from" : { "Context" : { "path" : "args/text" } } } ],
+                        "output" : [ { "Out" : {} } ]
+                    }
+                }
+            }
+        ) ).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_graph()
+    {
+        let agent = agent_from_config( AIAgent { tools : vec![ graph_tool( "test_data/graphs/echo_graph.json" ) ], ..Default::default() } ).await.unwrap();
+
+        let result = call_tool( Graph::default(), agent.tools[ 0 ].clone(), json!( { "text" : "hello" } ), false ).await;
+
+        assert_eq!( result, "Exit ok\nhello" );
+
+        let agent = agent_from_config( AIAgent { tools : vec![ graph_tool( "test_data/graphs/missing_graph.json" ) ], ..Default::default() } ).await;
+
+        assert_eq!( agent.unwrap_err().kind(), "File" );
+
+        let result = call_tool( Graph::default(), graph_tool( "test_data/graphs/echo_graph.json" ), json!( {} ), false ).await;
+
+        assert!( result.contains( "is not built" ), "{}", result );
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_invalid_args()
+    {
+        let result = call_tool( Graph::default(), echo_tool(), json!( { "other" : 1 } ), false ).await;
+
+        assert!( result.starts_with( "Error: " ) && result.contains( "Tool arguments." ), "{}", result );
+    }
+}
@@ -1,8 +1,10 @@
 
-use rig::client::CompletionClient;
+use std::sync::Arc;
+
+use rig::{agent::AgentBuilder, client::CompletionClient};
 use tracing::info;
 
-use crate::domain::{agent::{agent::{AIAgent, AIAgentProviderConfig, AnthropicConfig, DeepSeekConfig, GeminiConfig, OllamaConfig, OpenAIConfig}, agent_provider::AIAgentProvider}, data::{data_selection::{data_selection, data_to_string}, data_utils::value_to_string}, error::Error, graph::graph::Graph, mcp::mcp_functions::add_mcp_clients_to_agent, tracing::filter_layer::AGENT_SYSTEM_PROMPT, utils::string_utils::option_string_to_str};
+use crate::domain::{agent::{agent_mock::{compile_mock_response, parse_mock_fixture, MockCompletionModel}, agent_parameters::apply_agent_parameters, agent_tool::add_tools_to_agent, agent::{AIAgent, AIAgentProviderConfig, AnthropicConfig, DeepSeekConfig, GeminiConfig, MockConfig, OllamaConfig, OpenAICompatibleConfig, OpenAIConfig}, agent_provider::AIAgentProvider}, data::{data_selection::{data_selection, data_to_string}, data_utils::value_to_string}, error::Error, graph::graph::Graph, mcp::mcp_functions::add_mcp_clients_to_agent, tracing::filter_layer::AGENT_SYSTEM_PROMPT, utils::string_utils::option_string_to_str, web_client::http_client::{http_client, resolve_headers}};
 
 // CREATE AGENT PROVIDER
 
@@ -17,7 +19,11 @@ pub async fn alternate_create_agent_provider(
         AIAgentProviderConfig::OpenAI( c ) => openai_agent_provider( graph, agent,c ).await,
         AIAgentProviderConfig::Anthropic( c ) => anthropic_agent_provider( graph, agent, c ).await,
         AIAgentProviderConfig::DeepSeek( c ) => deepseek_agent_provider( graph, agent, c ).await,
-        AIAgentProviderConfig::Gemini( c ) => gemini_agent_provider( graph, agent,c ).await
+        AIAgentProviderConfig::Gemini( c ) => gemini_agent_provider( graph, agent,c ).await,
+        AIAgentProviderConfig::OpenAICompatible( c ) => openai_compatible_agent_provider( graph, agent, c ).await,
+        AIAgentProviderConfig::Mock( c ) => mock_agent_provider( graph, agent, c ).await,
+        // Each provider of the chain is created by execute_agent
+        AIAgentProviderConfig::Fallback( _ ) => Err( Error::Agent( "Fallback provider inside a Fallback provider".into() ) )
     }
 }
 
@@ -44,7 +50,11 @@ async fn gemini_agent_provider(
 
     let agent = client.agent( &model );
 
-    let ( mut agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+    let agent = apply_agent_parameters( graph, ai_agent, agent ).await?;
+
+    let ( agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+
+    let mut agent = add_tools_to_agent( graph, agent, &ai_agent.tools, ! ai_agent.is_stream );
 
     let system_prompt = data_to_string( graph, ai_agent.system_prompt.clone() ).await;
 
@@ -79,7 +89,11 @@ async fn deepseek_agent_provider(
         agent = agent.max_tokens( m );
     }
 
-    let ( mut agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+    let agent = apply_agent_parameters( graph, ai_agent, agent ).await?;
+
+    let ( agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+
+    let mut agent = add_tools_to_agent( graph, agent, &ai_agent.tools, ! ai_agent.is_stream );
 
     let system_prompt = data_to_string( graph, ai_agent.system_prompt.clone() ).await;
 
@@ -101,13 +115,24 @@ async fn ollama_agent_provider(
     config : &OllamaConfig
 ) -> Result<AIAgentProvider, Error>
 {
-    let client = rig::providers::ollama::Client::new();
+    let client = match &config.base_url
+    {
+        Some( u ) =>
+        {
+            let base_url = value_to_string( &data_selection( graph, u ).await? );
+
+            rig::providers::ollama::Client::builder().base_url( &base_url ).build().map_err( | e | Error::Agent( e.to_string() ).with_source( e ) )?
+        },
+        None => rig::providers::ollama::Client::new()
+    };
 
     let model = value_to_string( &data_selection( graph, &config.model ).await? );
 
-    let agent = client.agent( &model );
+    let agent = apply_agent_parameters( graph, ai_agent, client.agent( &model ) ).await?;
+
+    let ( agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
 
-    let ( mut agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+    let mut agent = add_tools_to_agent( graph, agent, &ai_agent.tools, ! ai_agent.is_stream );
 
     let system_prompt = data_to_string( graph, ai_agent.system_prompt.clone() ).await;
 
@@ -135,7 +160,11 @@ async fn openai_agent_provider(
 
     let agent = client.agent( &model );
 
-    let ( mut agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+    let agent = apply_agent_parameters( graph, ai_agent, agent ).await?;
+
+    let ( agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+
+    let mut agent = add_tools_to_agent( graph, agent, &ai_agent.tools, ! ai_agent.is_stream );
 
     let system_prompt = data_to_string( graph, ai_agent.system_prompt.clone() ).await;
 
@@ -163,9 +192,11 @@ async fn anthropic_agent_provider(
 
     let model = value_to_string( &data_selection( graph, &config.model ).await? );
 
-    let agent = client.agent( &model ).max_tokens( config.max_tokens );
+    let agent = apply_agent_parameters( graph, ai_agent, client.agent( &model ).max_tokens( config.max_tokens ) ).await?;
 
-    let ( mut agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+    let ( agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+
+    let mut agent = add_tools_to_agent( graph, agent, &ai_agent.tools, ! ai_agent.is_stream );
 
     let system_prompt = data_to_string( graph, ai_agent.system_prompt.clone() ).await;
 
@@ -181,4 +212,238 @@ async fn anthropic_agent_provider(
     Ok( AIAgentProvider::Anthropic( agent, clients ) )
 }
 
-// END CREATE AGENT PROVIDER
\ No newline at end of file
+async fn openai_compatible_agent_provider( 
+    graph : &Graph,
+    ai_agent : &AIAgent,
+    config : &OpenAICompatibleConfig
+) -> Result<AIAgentProvider, Error>
+{
+    let api_key = match &config.api_key
+    {
+        Some( k ) => std::env::var( k ).map_err( | e | Error::Agent( e.to_string() ) )?,
+        None => "".into()
+    };
+
+    let base_url = value_to_string( &data_selection( graph, &config.base_url ).await? );
+
+    let client = rig::providers::openai::Client::builder( &api_key )
+    .base_url( &base_url )
+    .custom_client( http_client( &resolve_headers( graph, &config.headers ).await?, Error::Agent )? )
+    .build()
+    .map_err( | e | Error::Agent( e.to_string() ).with_source( e ) )?;
+
+    let model = value_to_string( &data_selection( graph, &config.model ).await? );
+
+    // Chat completions API. The default OpenAI agent uses the responses API
+    let agent = AgentBuilder::new( client.completion_model( &model ).completions_api() );
+
+    let agent = apply_agent_parameters( graph, ai_agent, agent ).await?;
+
+    let ( agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+
+    let mut agent = add_tools_to_agent( graph, agent, &ai_agent.tools, ! ai_agent.is_stream );
+
+    let system_prompt = data_to_string( graph, ai_agent.system_prompt.clone() ).await;
+
+    if system_prompt.trim() != ""
+    {
+        agent = agent.preamble( system_prompt.as_str() );
+    }
+
+    trace_system_prompt( graph.id.as_ref(), &system_prompt );
+
+    Ok( AIAgentProvider::OpenAICompatible( agent.build(), clients ) )
+}
+
+async fn mock_agent_provider( 
+    graph : &Graph,
+    ai_agent : &AIAgent,
+    config : &MockConfig
+) -> Result<AIAgentProvider, Error>
+{
+    let mut responses = config.responses.iter().cloned().map( compile_mock_response ).collect::<Result<Vec<_>, _>>()?;
+
+    if let Some( f ) = &config.fixture
+    {
+        let content = std::fs::read_to_string( f ).map_err( | e | Error::File( format!( "Mock fixture {}. {}", f, e ) ).with_source( e ) )?;
+
+        responses.extend( parse_mock_fixture( &content )? );
+    }
+
+    let model = MockCompletionModel { id : config.__id.clone(), calls : graph.mock_calls.clone(), responses : Arc::new( responses ) };
+
+    let agent = apply_agent_parameters( graph, ai_agent, AgentBuilder::new( model ) ).await?;
+
+    let ( agent, clients ) = add_mcp_clients_to_agent( graph, agent, &ai_agent.servers ).await?;
+
+    let mut agent = add_tools_to_agent( graph, agent, &ai_agent.tools, ! ai_agent.is_stream );
+
+    let system_prompt = data_to_string( graph, ai_agent.system_prompt.clone() ).await;
+
+    if system_prompt.trim() != ""
+    {
+        agent = agent.preamble( system_prompt.as_str() );
+    }
+
+    trace_system_prompt( graph.id.as_ref(), &system_prompt );
+
+    Ok( AIAgentProvider::Mock( agent.build(), clients ) )
+}
+
+// END CREATE AGENT PROVIDER
+
+#[cfg(test)]
+mod tests
+{
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    use crate::domain::{agent::execute_agent::execute_agent, usage::usage::TokenUsage};
+
+    use super::*;
+
+    async fn chat_completions( headers : HeaderMap, Json( body ) : Json<Value> ) -> Json<Value>
+    {
+        let team = headers.get( "x-team" ).and_then( | h | h.to_str().ok() ).unwrap_or( "" ).to_string();
+
+        completion( &body, format!( "{} {}", body[ "model" ].as_str().unwrap(), team ) )
+    }
+
+    /// Returns the sampling parameters of the request
+    async fn chat_completions_params( Json( body ) : Json<Value> ) -> Json<Value>
+    {
+        let params = [ "temperature", "top_p", "max_tokens", "stop", "seed", "top_k" ].iter()
+        .map( | k | ( k.to_string(), body[ k ].clone() ) )
+        .collect::<serde_json::Map<String, Value>>();
+
+        completion( &body, Value::Object( params ).to_string() )
+    }
+
+    fn completion( body : &Value, content : String ) -> Json<Value>
+    {
+        Json( json!(
+            {
+                "id" : "chatcmpl-1",
+                "object" : "chat.completion",
+                "created" : 0,
+                "model" : body[ "model" ],
+                "choices" : [
+                    {
+                        "index" : 0,
+                        "message" : { "role" : "assistant", "content" : content },
+                        "logprobs" : null,
+                        "finish_reason" : "stop"
+                    }
+                ],
+                "usage" : { "prompt_tokens" : 5, "total_tokens" : 8 }
+            }
+        ) )
+    }
+
+    async fn ollama_chat( Json( body ) : Json<Value> ) -> Json<Value>
+    {
+        Json( json!(
+            {
+                "model" : body[ "model" ],
+                "created_at" : "2025-01-01T00:00:00Z",
+                "message" : { "role" : "assistant", "content" : format!( "ollama {}", body[ "model" ].as_str().unwrap() ) },
+                "done" : true,
+                "prompt_eval_count" : 3,
+                "eval_count" : 2
+            }
+        ) )
+    }
+
+    async fn serve() -> String
+    {
+        let router = Router::new()
+        .route( "/v1/chat/completions", post( chat_completions ) )
+        .route( "/params/chat/completions", post( chat_completions_params ) )
+        .route( "/api/chat", post( ollama_chat ) );
+
+        let listener = tokio::net::TcpListener::bind( "127.0.0.1:0" ).await.unwrap();
+
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn( async move { let _ = axum::serve( listener, router ).await; } );
+
+        format!( "http://{}", addr )
+    }
+
+    fn agent( provider : Value ) -> AIAgent
+    {
+        serde_json::from_value( json!( { "provider" : provider, "prompt" : [ { "from" : { "Static" : "Hi" } } ] } ) ).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_provider()
+    {
+        let url = serve().await;
+
+        let agent = agent( json!(
+            {
+                "OpenAICompatible" : {
+                    "base_url" : { "Static" : format!( "{}/v1", url ) },
+                    "model" : { "Static" : "local-model" },
+                    "headers" : [ { "name" : { "Static" : "x-team" }, "value" : { "Static" : "docs" } } ]
+                }
+            }
+        ) );
+
+        let graph = Graph::default();
+
+        let ( response, _, _ ) = execute_agent( &graph, &agent ).await.unwrap();
+
+        assert_eq!( response, "local-model docs" );
+        assert_eq!( graph.usage.get().run, TokenUsage::new( 5, 3, 8 ) );
+    }
+
+    #[tokio::test]
+    async fn test_ollama_base_url()
+    {
+        let url = serve().await;
+
+        let agent = agent( json!( { "Ollama" : { "base_url" : { "Static" : url }, "model" : { "Static" : "llama3.1" } } } ) );
+
+        let ( response, _, _ ) = execute_agent( &Graph::default(), &agent ).await.unwrap();
+
+        assert_eq!( response, "ollama llama3.1" );
+    }
+
+    #[tokio::test]
+    async fn test_agent_parameters()
+    {
+        let url = serve().await;
+
+        let mut agent = agent( json!(
+            {
+                "OpenAICompatible" : {
+                    "base_url" : { "Static" : format!( "{}/params", url ) },
+                    "model" : { "Static" : "local-model" }
+                }
+            }
+        ) );
+
+        agent.parameters = serde_json::from_value( json!(
+            {
+                "temperature" : { "Context" : { "path" : "temperature" } },
+                "top_p" : { "Static" : 0.9 },
+                "max_tokens" : { "Static" : 64 },
+                "stop" : { "Static" : [ "END", "STOP" ] },
+                "seed" : { "Static" : 42 },
+                "additional_params" : { "Static" : { "top_k" : 20, "seed" : 43 } }
+            }
+        ) ).unwrap();
+
+        let mut graph = Graph::default();
+
+        graph.context.insert( "temperature".into(), json!( 0 ) );
+
+        let ( response, _, _ ) = execute_agent( &graph, &agent ).await.unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>( &response ).unwrap(),
+            json!( { "temperature" : 0.0, "top_p" : 0.9, "max_tokens" : 64, "stop" : [ "END", "STOP" ], "seed" : 43, "top_k" : 20 } )
+        );
+    }
+}
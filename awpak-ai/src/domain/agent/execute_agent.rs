@@ -1,40 +1,213 @@
+use std::time::Duration;
 
 use rig::message::Message;
+use serde_json::{json, Value};
 use tracing::info;
 
-use crate::domain::{agent::{agent::AIAgent, agent_provider::AIAgentProvider, create_agent_provider::alternate_create_agent_provider, run_agent::run_agent}, data::{data::DataToString, data_selection::data_to_string}, error::Error, graph::graph::Graph, tracing::filter_layer::AGENT_PROMPT, utils::string_utils::option_string_to_str};
+use crate::domain::{agent::{agent::{AIAgent, AIAgentFallback, AIAgentProviderConfig}, agent_output::{extract_json, repair_output_prompt, structured_output_prompt}, agent_provider::AIAgentProvider, create_agent_provider::alternate_create_agent_provider, run_agent::run_agent}, cassette::cassette::{with_cassette, CassetteKind}, data::{data::DataToString, data_schema::validate_schema, data_selection::{data_selection, data_to_string}, data_utils::value_to_string}, error::Error, graph::graph::Graph, tracing::filter_layer::{AGENT_FALLBACK, AGENT_PROMPT, AGENT_USAGE}, usage::usage::TokenUsage, utils::string_utils::option_string_to_str};
 
 
+/// Runs the agent. With `output_schema` the response is the validated JSON as a string.
+/// 
+/// With a `Fallback` provider, the last value is the provider that answered.
 pub async fn execute_agent(
     graph : &Graph,
     agent : &AIAgent
-) -> Result<( String, Vec<Message> ), Error>
+) -> Result<( String, Vec<Message>, Option<Value> ), Error>
 {
-    let provider = alternate_create_agent_provider( 
-        graph,
-        agent 
-    ).await?;
+    if agent.output_schema.is_some()
+    {
+        let ( value, history, provider ) = execute_structured_agent( graph, agent ).await?;
 
-    let id = graph.id.as_ref();
+        return Ok( ( value.to_string(), history, provider ) )
+    }
 
     let prompt = agent_prompt( graph, &agent.prompt ).await;
 
+    execute_agent_prompt( graph, agent, prompt ).await
+}
+
+/// Runs an agent with `output_schema`. 
+/// 
+/// If the response is not valid JSON or does not match the schema, the agent is asked to fix it up to `output_retries` times.
+pub async fn execute_structured_agent(
+    graph : &Graph,
+    agent : &AIAgent
+) -> Result<( Value, Vec<Message>, Option<Value> ), Error>
+{
+    let schema = agent.output_schema.as_ref().ok_or( Error::Agent( "Agent without output_schema".into() ) )?;
+
+    let mut prompt = structured_output_prompt( agent_prompt( graph, &agent.prompt ).await, schema );
+
+    let mut current = agent.clone();
+
+    let mut attempt = 0;
+
+    loop
+    {
+        let ( response, history, provider ) = execute_agent_prompt( graph, &current, prompt ).await?;
+
+        match extract_json( &response ).and_then( | v | validate_schema( schema, &v ).map( | _ | v ) )
+        {
+            Ok( v ) => return Ok( ( v, history, provider ) ),
+            Err( e ) if attempt < agent.output_retries =>
+            {
+                attempt += 1;
+
+                prompt = repair_output_prompt( &e );
+
+                current.history = history;
+            },
+            Err( e ) => return Err( e.prepend_str( format!( "Agent output. Attempts: {}\n", attempt + 1 ) ) )
+        }
+    }
+}
+
+async fn execute_agent_prompt(
+    graph : &Graph,
+    agent : &AIAgent,
+    prompt : String
+) -> Result<( String, Vec<Message>, Option<Value> ), Error>
+{
     info!(
         target:AGENT_PROMPT, 
-        id=option_string_to_str( id ), 
+        id=option_string_to_str( graph.id.as_ref() ), 
         text=prompt
     );
 
+    match &agent.provider
+    {
+        AIAgentProviderConfig::Fallback( f ) => execute_fallback_prompt( graph, agent, f, prompt ).await,
+        _ =>
+        {
+            let ( response, history ) = execute_provider_prompt( graph, agent, prompt ).await?;
+
+            Ok( ( response, history, None ) )
+        }
+    }
+}
+
+/// Tries the providers in order while their errors match `fallback_on`
+async fn execute_fallback_prompt(
+    graph : &Graph,
+    agent : &AIAgent,
+    fallback : &AIAgentFallback,
+    prompt : String
+) -> Result<( String, Vec<Message>, Option<Value> ), Error>
+{
+    let mut current = agent.clone();
+
+    for ( idx, p ) in fallback.providers.iter().enumerate()
+    {
+        current.provider = p.provider.clone();
+
+        let name = p.provider.name();
+
+        let ( result, timed_out ) = match p.timeout
+        {
+            Some( t ) => match tokio::time::timeout( Duration::from_millis( t ), execute_provider_prompt( graph, &current, prompt.clone() ) ).await
+            {
+                Ok( r ) => ( r, false ),
+                Err( _ ) => ( Err( Error::Agent( format!( "Timeout after {} ms", t ) ) ), true )
+            },
+            None => ( execute_provider_prompt( graph, &current, prompt.clone() ).await, false )
+        };
+
+        match result
+        {
+            Ok( ( response, history ) ) =>
+            {
+                let model = data_selection( graph, p.provider.model() ).await.map( | v | value_to_string( &v ) ).unwrap_or_default();
+
+                trace_fallback( graph, format!( "Provider {} {} {} answered", idx, name, model ) );
+
+                return Ok( ( response, history, Some( json!( { "index" : idx, "provider" : name, "model" : model } ) ) ) )
+            },
+            Err( e ) if idx + 1 < fallback.providers.len() && p.falls_back( &e, timed_out ) =>
+            {
+                trace_fallback( graph, format!( "Provider {} {} failed. Trying the next one.\n{}", idx, name, e ) );
+            },
+            Err( e ) => return Err( e.prepend_str( format!( "Fallback provider {} {}.\n", idx, name ) ) )
+        }
+    }
+
+    Err( Error::Agent( "Fallback without providers".into() ) )
+}
+
+fn trace_fallback( graph : &Graph, text : String )
+{
+    info!(
+        target:AGENT_FALLBACK,
+        id=option_string_to_str( graph.id.as_ref() ),
+        text=text
+    );
+}
+
+async fn execute_provider_prompt(
+    graph : &Graph,
+    agent : &AIAgent,
+    prompt : String
+) -> Result<( String, Vec<Message> ), Error>
+{
+    let system_prompt = match &graph.cassette
+    {
+        Some( _ ) => data_to_string( graph, agent.system_prompt.clone() ).await,
+        None => String::new()
+    };
+
+    let ( response, history, usage ) = with_cassette(
+        graph.cassette.as_ref(),
+        CassetteKind::Agent,
+        || Ok( json!( { "provider" : agent.provider, "system_prompt" : system_prompt, "history" : agent.history, "prompt" : prompt } ) ),
+        || run_agent_provider( graph, agent, prompt.clone() )
+    ).await?;
+
+    add_usage( graph, &usage );
+
+    Ok( ( response, history ) )
+}
+
+async fn run_agent_provider(
+    graph : &Graph,
+    agent : &AIAgent,
+    prompt : String
+) -> Result<( String, Vec<Message>, TokenUsage ), Error>
+{
+    let provider = alternate_create_agent_provider( 
+        graph,
+        agent 
+    ).await?;
+
+    let id = graph.id.as_ref();
+
     match provider
     {
         AIAgentProvider::Ollama( p, _ ) => run_agent( id, prompt, p, agent ).await,
         AIAgentProvider::OpenAI( p, _ ) => run_agent( id, prompt, p, agent ).await,
         AIAgentProvider::Gemini( p, _ ) => run_agent( id, prompt, p, agent ).await,
         AIAgentProvider::Anthropic( p, _ ) => run_agent( id, prompt, p, agent ).await,
-        AIAgentProvider::DeepSeek( p, _ ) => run_agent( id, prompt, p, agent ).await
+        AIAgentProvider::DeepSeek( p, _ ) => run_agent( id, prompt, p, agent ).await,
+        AIAgentProvider::OpenAICompatible( p, _ ) => run_agent( id, prompt, p, agent ).await,
+        AIAgentProvider::Mock( p, _ ) => run_agent( id, prompt, p, agent ).await
     }
 }
 
+fn add_usage( graph : &Graph, usage : &TokenUsage )
+{
+    let node_id = graph.current_node.as_deref();
+
+    graph.usage.add( node_id, usage );
+
+    info!(
+        target:AGENT_USAGE,
+        id=option_string_to_str( graph.id.as_ref() ),
+        text=format!( 
+            "Node: {}. Input tokens: {}. Output tokens: {}. Total tokens: {}", 
+            node_id.unwrap_or( "" ), usage.input_tokens, usage.output_tokens, usage.total_tokens 
+        )
+    );
+}
+
 async fn agent_prompt(
     graph : &Graph,
     from : &Vec<DataToString>
@@ -2,4 +2,8 @@ pub mod agent;
 pub mod execute_agent;
 pub mod agent_provider;
 pub mod create_agent_provider;
-pub mod run_agent;
\ No newline at end of file
+pub mod run_agent;
+pub mod agent_output;
+pub mod agent_tool;
+pub mod agent_parameters;
+pub mod agent_mock;
@@ -4,14 +4,14 @@ use rig::{agent::Agent, completion::{CompletionModel, Prompt}, message::{Assista
 use tokio_stream::{Stream, StreamExt};
 use tracing::info;
 type StreamingResult = Pin<Box<dyn Stream<Item = Result<Text, Error>> + Send>>;
-use crate::domain::{agent::agent::AIAgent, error::Error, signals::cancel_graph::is_graph_cancelled, tracing::filter_layer::{AGENT_STREAM, AGENT_SYNC, AGENT_TOOL_CALL, AGENT_TOOL_RESULT}, utils::string_utils::option_string_to_str};
+use crate::domain::{agent::agent::AIAgent, error::Error, signals::cancel_graph::is_graph_cancelled, tracing::filter_layer::{AGENT_STREAM, AGENT_SYNC, AGENT_TOOL_CALL, AGENT_TOOL_RESULT}, usage::usage::{usage_from_stream_response, TokenUsage}, utils::string_utils::option_string_to_str};
 
 pub async fn run_agent<T: CompletionModel + 'static>( 
     id : Option<&String>,
     prompt : String, 
     provider : Agent<T>, 
     agent : &AIAgent 
-) -> Result<( String, Vec<Message> ), Error>
+) -> Result<( String, Vec<Message>, TokenUsage ), Error>
 {
     match agent.is_stream
     {
@@ -25,10 +25,11 @@ pub async fn run_stream_agent<T: CompletionModel + 'static>(
     prompt : String, 
     provider : Agent<T>, 
     agent : &AIAgent 
-) -> Result<( String, Vec<Message> ), Error>
+) -> Result<( String, Vec<Message>, TokenUsage ), Error>
 {
     let chat_history = Arc::new( Mutex::new( agent.history.clone() ) );
     let chat_history_result = chat_history.clone();
+    let usage = Arc::new( Mutex::new( TokenUsage::default() ) );
 
     let mut result = stream_chat( 
         match id
@@ -38,7 +39,8 @@ pub async fn run_stream_agent<T: CompletionModel + 'static>(
         },
         provider, 
         prompt, 
-        chat_history
+        chat_history,
+        usage.clone()
     ).await;
     
     let response = string_from_stream( id, &mut result ).await?;
@@ -47,7 +49,9 @@ pub async fn run_stream_agent<T: CompletionModel + 'static>(
 
     history.push( Message::assistant( response.clone() ) );
 
-    Ok( ( response, history ) )
+    let usage = *usage.lock().unwrap();
+
+    Ok( ( response, history, usage ) )
 }
 
 async fn run_sync_agent<T: CompletionModel + 'static>( 
@@ -55,25 +59,22 @@ async fn run_sync_agent<T: CompletionModel + 'static>(
     prompt : String, 
     provider : Agent<T>, 
     agent : &AIAgent 
-) -> Result<( String, Vec<Message> ), Error>
+) -> Result<( String, Vec<Message>, TokenUsage ), Error>
 {
     let mut history = agent.history.clone();
 
     let response = provider
     .prompt( prompt )
+    .extended_details()
     .multi_turn( 
-        match agent.servers.len()
-        {
-            0 => 0,
-            _ => agent.turns.unwrap_or( 25 )
-        }
+        if agent.servers.is_empty() && agent.tools.is_empty() { 0 } else { agent.turns.unwrap_or( 25 ) }
     )
     .with_history( &mut history )
-    .await.map_err( | e | Error::Agent( e.to_string() ) )?;
+    .await.map_err( | e | Error::Agent( e.to_string() ).with_source( e ) )?;
 
-    info!( target:AGENT_SYNC, id=option_string_to_str( id ), text=response );
+    info!( target:AGENT_SYNC, id=option_string_to_str( id ), text=response.output );
     
-    Ok( ( response, history ) )
+    Ok( ( response.output, history, response.total_usage.into() ) )
 }
 
 async fn string_from_stream(
@@ -112,7 +113,8 @@ async fn stream_chat<M>(
     id : Option<String>,
     agent: Agent<M>,
     prompt: impl Into<Message> + Send,
-    chat_history: Arc<Mutex<Vec<Message>>>
+    chat_history: Arc<Mutex<Vec<Message>>>,
+    usage: Arc<Mutex<TokenUsage>>
 ) -> StreamingResult
 where
     M: CompletionModel + 'static,
@@ -130,9 +132,9 @@ where
 
             let mut stream = agent
                 .stream_completion(current_prompt.clone(), h )
-                .await.map_err( | e | Error::Agent( e.to_string() ) )?
+                .await.map_err( | e | Error::Agent( e.to_string() ).with_source( e ) )?
                 .stream()
-                .await.map_err( | e | Error::Agent( e.to_string() ) )?;
+                .await.map_err( | e | Error::Agent( e.to_string() ).with_source( e ) )?;
 
             chat_history.lock().unwrap().push( current_prompt );
 
@@ -165,7 +167,7 @@ where
 
                         let tool_result =
                             agent.tools.call(&tool_call.function.name, tool_call.function.arguments.to_string())
-                            .await.map_err( | e | Error::MCPTool( e.to_string() ) )?;
+                            .await.map_err( | e | Error::MCPTool( e.to_string() ).with_source( e ) )?;
 
                         info!( 
                             target:AGENT_TOOL_RESULT, 
@@ -188,11 +190,13 @@ where
                         yield Ok(Text { text: reasoning });
                         did_call_tool = false;
                     },
-                    Ok(_) => {
-                        // do nothing here as we don't need to accumulate token usage
+                    Ok(StreamedAssistantContent::Final(response)) => {
+                        if let Ok(response) = serde_json::to_value(&response) {
+                            usage.lock().unwrap().add(&usage_from_stream_response(&response));
+                        }
                     }
                     Err(e) => {
-                        yield Err( Error::Agent( e.to_string() ) );
+                        yield Err( Error::Agent( e.to_string() ).with_source( e ) );
                         break 'outer;
                     }
                 }
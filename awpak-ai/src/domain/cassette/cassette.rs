@@ -0,0 +1,305 @@
+use std::{collections::{HashMap, VecDeque}, fs::{File, OpenOptions}, future::Future, io::Write, path::PathBuf, sync::{Arc, Mutex}};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::domain::{error::Error, tracing::filter_layer::CASSETTE_ERROR};
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode
+{
+    Record,
+    Replay
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CassetteConfig
+{
+    pub path : String,
+    pub mode : CassetteMode
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CassetteKind
+{
+    Agent,
+    WebClient,
+    Command
+}
+
+impl CassetteKind
+{
+    /// Error of the executor
+    fn error( &self, text : String ) -> Error
+    {
+        match self
+        {
+            CassetteKind::Agent => Error::Agent( text ),
+            CassetteKind::WebClient => Error::WebClient( text ),
+            CassetteKind::Command => Error::Command( text )
+        }
+    }
+}
+
+/// Recorded error. Replayed with the same kind and text
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CassetteError
+{
+    pub kind : String,
+    pub text : String
+}
+
+impl From<&Error> for CassetteError
+{
+    fn from( error : &Error ) -> Self
+    {
+        let kind = error.kind();
+        let text = error.to_string();
+
+        Self
+        {
+            kind : kind.to_string(),
+            text : text.strip_prefix( &format!( "{}: ", kind ) ).map( str::to_string ).unwrap_or( text )
+        }
+    }
+}
+
+/// Line of a cassette file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CassetteEntry
+{
+    pub kind : CassetteKind,
+    /// SHA-256 of `request`
+    pub key : String,
+    pub request : Value,
+    pub response : Result<Value, CassetteError>
+}
+
+/// Requests and responses of agents, web clients and commands, saved as a JSONL file.
+///
+/// In `Replay` mode the responses are served by kind and request. Equal requests get their responses in the recorded order.
+pub struct Cassette
+{
+    pub path : PathBuf,
+    pub mode : CassetteMode,
+    entries : Mutex<CassetteResponses>
+}
+
+type CassetteResponses = HashMap<( CassetteKind, String ), VecDeque<Result<Value, CassetteError>>>;
+
+impl Cassette
+{
+    /// Creates the file, or truncates it if it exists
+    pub fn record( path : impl Into<PathBuf> ) -> Result<Self, Error>
+    {
+        let path = path.into();
+
+        File::create( &path ).map_err( | e | Error::File( format!( "Cassette {}. {}", path.display(), e ) ).with_source( e ) )?;
+
+        Ok( Self { path, mode : CassetteMode::Record, entries : Mutex::default() } )
+    }
+
+    pub fn replay( path : impl Into<PathBuf> ) -> Result<Self, Error>
+    {
+        let path = path.into();
+
+        let content = std::fs::read_to_string( &path ).map_err( | e | Error::File( format!( "Cassette {}. {}", path.display(), e ) ).with_source( e ) )?;
+
+        let mut entries = CassetteResponses::new();
+
+        for ( i, line ) in content.lines().enumerate().filter( | ( _, l ) | ! l.trim().is_empty() )
+        {
+            let entry = serde_json::from_str::<CassetteEntry>( line )
+            .map_err( | e | Error::ParseData( format!( "Cassette {} line {}. {}", path.display(), i + 1, e ) ).with_source( e ) )?;
+
+            entries.entry( ( entry.kind, entry.key ) ).or_default().push_back( entry.response );
+        }
+
+        Ok( Self { path, mode : CassetteMode::Replay, entries : Mutex::new( entries ) } )
+    }
+
+    pub fn from_config( config : &CassetteConfig ) -> Result<Self, Error>
+    {
+        match config.mode
+        {
+            CassetteMode::Record => Self::record( &config.path ),
+            CassetteMode::Replay => Self::replay( &config.path )
+        }
+    }
+
+    fn add( &self, kind : CassetteKind, request : Value, response : Result<Value, CassetteError> ) -> Result<(), Error>
+    {
+        let entry = CassetteEntry { kind, key : cassette_key( &request ), request, response };
+
+        let line = serde_json::to_string( &entry ).map_err( | e | Error::ParseData( e.to_string() ).with_source( e ) )?;
+
+        // The lock keeps the lines of parallel nodes whole
+        let _lock = self.entries.lock().unwrap();
+
+        OpenOptions::new().append( true ).open( &self.path )
+        .and_then( | mut f | writeln!( f, "{}", line ) )
+        .map_err( | e | Error::File( format!( "Cassette {}. {}", self.path.display(), e ) ).with_source( e ) )
+    }
+
+    fn take( &self, kind : CassetteKind, request : &Value ) -> Result<Result<Value, CassetteError>, Error>
+    {
+        let key = cassette_key( request );
+
+        self.entries.lock().unwrap()
+        .get_mut( &( kind, key.clone() ) )
+        .and_then( | r | r.pop_front() )
+        .ok_or( kind.error( format!( "No response in cassette {} for request {}. Key: {}", self.path.display(), request, key ) ) )
+    }
+}
+
+pub fn cassette_key( request : &Value ) -> String
+{
+    format!( "{:x}", Sha256::digest( request.to_string().as_bytes() ) )
+}
+
+const REDACTED : &str = "<redacted>";
+
+/// Replaces the values of `headers` and `auth_token` at any depth, so they are not hashed nor written to the cassette
+pub fn redact_secrets( request : &mut Value )
+{
+    match request
+    {
+        Value::Object( map ) => for ( k, v ) in map.iter_mut()
+        {
+            match ( k.as_str(), v )
+            {
+                ( "headers", Value::Array( headers ) ) =>
+                {
+                    headers.iter_mut()
+                    .filter_map( | h | h.get_mut( "value" ) )
+                    .for_each( | h | *h = Value::String( REDACTED.into() ) )
+                },
+                ( "auth_token", v ) if ! v.is_null() => *v = Value::String( REDACTED.into() ),
+                ( _, v ) => redact_secrets( v )
+            }
+        },
+        Value::Array( a ) => a.iter_mut().for_each( redact_secrets ),
+        _ => {}
+    }
+}
+
+/// Runs `f` and records its result, or returns the recorded result of `request`.
+///
+/// A result that can not be recorded is returned anyway, and the failure is traced with the `cassette_error` target
+pub async fn with_cassette<T, F, Fut>(
+    cassette : Option<&Arc<Cassette>>,
+    kind : CassetteKind,
+    request : impl FnOnce() -> Result<Value, Error>,
+    f : F
+) -> Result<T, Error>
+where
+    T : Serialize + DeserializeOwned,
+    F : FnOnce() -> Fut,
+    Fut : Future<Output = Result<T, Error>>
+{
+    let Some( cassette ) = cassette else { return f().await };
+
+    let mut request = request()?;
+
+    redact_secrets( &mut request );
+
+    match cassette.mode
+    {
+        CassetteMode::Replay => match cassette.take( kind, &request )?
+        {
+            Ok( v ) => serde_json::from_value( v ).map_err( | e | Error::ParseData( format!( "Cassette response. {}", e ) ).with_source( e ) ),
+            Err( e ) => Err( Error::from_kind( &e.kind, e.text.clone() ).unwrap_or( kind.error( e.text ) ) )
+        },
+        CassetteMode::Record =>
+        {
+            let result = f().await;
+
+            let recorded = match &result
+            {
+                Ok( v ) => serde_json::to_value( v ).map( Ok ).map_err( | e | Error::ParseData( e.to_string() ).with_source( e ) ),
+                Err( e ) => Ok( Err( e.into() ) )
+            }
+            .and_then( | response | cassette.add( kind, request, response ) );
+
+            if let Err( e ) = recorded
+            {
+                info!(
+                    target:CASSETTE_ERROR,
+                    text=format!( "Cassette {}. Result not recorded.\n{}", cassette.path.display(), e )
+                );
+            }
+
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_replay()
+    {
+        let path = std::env::temp_dir().join( format!( "{}.cassette.jsonl", uuid::Uuid::new_v4() ) );
+
+        let cassette = Some( Arc::new( Cassette::record( &path ).unwrap() ) );
+
+        for i in 0..2
+        {
+            let r = with_cassette( cassette.as_ref(), CassetteKind::Command, || Ok( json!( "date" ) ), || async move { Ok( i ) } ).await;
+
+            assert_eq!( r, Ok( i ) );
+        }
+
+        let r = with_cassette::<i32, _, _>( cassette.as_ref(), CassetteKind::Agent, || Ok( json!( "fail" ) ), || async { Err( Error::MCPTool( "down".into() ) ) } ).await;
+
+        assert!( r.is_err() );
+
+        let cassette = Some( Arc::new( Cassette::replay( &path ).unwrap() ) );
+
+        let _ = std::fs::remove_file( &path );
+
+        let unreachable = || async { Err::<i32, _>( Error::Ignore ) };
+
+        assert_eq!( with_cassette( cassette.as_ref(), CassetteKind::Command, || Ok( json!( "date" ) ), unreachable ).await, Ok( 0 ) );
+        assert_eq!( with_cassette( cassette.as_ref(), CassetteKind::Command, || Ok( json!( "date" ) ), unreachable ).await, Ok( 1 ) );
+        assert_eq!( with_cassette( cassette.as_ref(), CassetteKind::Agent, || Ok( json!( "fail" ) ), unreachable ).await, Err( Error::MCPTool( "down".into() ) ) );
+
+        let r = with_cassette( cassette.as_ref(), CassetteKind::Command, || Ok( json!( "date" ) ), unreachable ).await;
+
+        assert_eq!( r.unwrap_err().kind(), Error::Command( "".into() ).kind() );
+    }
+
+    #[tokio::test]
+    async fn test_record_redacts_secrets_and_keeps_results()
+    {
+        let path = std::env::temp_dir().join( format!( "{}.cassette.jsonl", uuid::Uuid::new_v4() ) );
+
+        let cassette = Some( Arc::new( Cassette::record( &path ).unwrap() ) );
+
+        let request = || Ok( json!( { "provider" : { "headers" : [ { "name" : "x-api-key", "value" : { "Static" : "secret" } } ] }, "auth_token" : "secret" } ) );
+
+        let r = with_cassette( cassette.as_ref(), CassetteKind::Agent, request, || async { Ok( 1 ) } ).await;
+
+        assert_eq!( r, Ok( 1 ) );
+
+        // Maps with non string keys can not be converted to JSON, so the result is not recorded
+        let r = with_cassette( cassette.as_ref(), CassetteKind::Agent, request, || async { Ok( HashMap::from( [ ( ( 1, 2 ), 3 ) ] ) ) } ).await;
+
+        assert_eq!( r, Ok( HashMap::from( [ ( ( 1, 2 ), 3 ) ] ) ) );
+
+        let content = std::fs::read_to_string( &path ).unwrap();
+
+        let _ = std::fs::remove_file( &path );
+
+        assert_eq!( content.lines().count(), 1 );
+        assert!( ! content.contains( "secret" ) && content.contains( "x-api-key" ) && content.contains( REDACTED ), "{}", content );
+    }
+}
@@ -0,0 +1,103 @@
+use std::{collections::HashMap, fs::File, io::{BufReader, BufWriter}, path::PathBuf};
+
+use rig::message::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::{error::Error, usage::usage::GraphUsage};
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GraphCheckpoint
+{
+    #[serde(default)]
+    pub id : Option<String>,
+
+    #[serde(default)]
+    pub input : Option<String>,
+    #[serde(default)]
+    pub parsed_input : Value,
+
+    #[serde(default)]
+    pub context : HashMap<String, Value>,
+
+    #[serde(default)]
+    pub next : Option<String>,
+    #[serde(default)]
+    pub final_output : Option<Result<String, String>>,
+
+    /// Steps, visits by node id and last visited nodes of the run. Only set in the checkpoint of the running graph
+    #[serde(default)]
+    pub steps : usize,
+    #[serde(default)]
+    pub visits : HashMap<String, usize>,
+    #[serde(default)]
+    pub trail : Vec<String>,
+    #[serde(default)]
+    pub usage : GraphUsage,
+    /// Call index of each `Mock` provider
+    #[serde(default)]
+    pub mock_calls : HashMap<String, usize>,
+
+    #[serde(default)]
+    pub histories : HashMap<String, Vec<Message>>,
+    #[serde(default)]
+    pub graphs : HashMap<String, GraphCheckpoint>,
+
+    #[serde(default)]
+    pub stores : Vec<String>
+}
+
+pub trait CheckpointStore : Send + Sync
+{
+    fn save( &self, checkpoint : &GraphCheckpoint ) -> Result<(), Error>;
+
+    fn load( &self, id : Option<&str> ) -> Result<Option<GraphCheckpoint>, Error>;
+}
+
+pub struct DirCheckpointStore
+{
+    pub dir : PathBuf
+}
+
+impl DirCheckpointStore
+{
+    pub fn new( dir : impl Into<PathBuf> ) -> Self
+    {
+        Self { dir : dir.into() }
+    }
+
+    fn path( &self, id : Option<&str> ) -> PathBuf
+    {
+        self.dir.join( format!( "{}.checkpoint.json", id.unwrap_or( "graph" ) ) )
+    }
+}
+
+impl CheckpointStore for DirCheckpointStore
+{
+    fn save( &self, checkpoint : &GraphCheckpoint ) -> Result<(), Error>
+    {
+        std::fs::create_dir_all( &self.dir ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )?;
+
+        let path = self.path( checkpoint.id.as_deref() );
+        let tmp = path.with_extension( "json.tmp" );
+
+        let file = File::create( &tmp ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )?;
+
+        serde_json::to_writer( BufWriter::new( file ), checkpoint ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )?;
+
+        // Rename so a crash while writing never leaves a truncated checkpoint
+        std::fs::rename( tmp, path ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )
+    }
+
+    fn load( &self, id : Option<&str> ) -> Result<Option<GraphCheckpoint>, Error>
+    {
+        let path = self.path( id );
+
+        if ! path.exists() { return Ok( None ) }
+
+        let file = File::open( path ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )?;
+
+        serde_json::from_reader( BufReader::new( file ) ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )
+    }
+}
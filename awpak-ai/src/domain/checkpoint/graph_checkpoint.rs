@@ -0,0 +1,176 @@
+use awpak_utils::result::result::AwpakResult;
+
+use rig::message::Message;
+
+use crate::domain::{checkpoint::checkpoint::GraphCheckpoint, error::Error, graph::{graph::Graph, node::NodeExecutor}, parallel::parallel::ParallelExecutor};
+
+
+pub fn checkpoint_from_graph( graph : &Graph, next : Option<&str> ) -> GraphCheckpoint
+{
+    let mut checkpoint = GraphCheckpoint
+    {
+        id : graph.id.clone(),
+        input : graph.input.clone(),
+        parsed_input : graph.parsed_input.clone(),
+        context : graph.context.clone(),
+        next : next.map( | n | n.to_string() ),
+        final_output : graph.final_output.clone(),
+        stores : graph.stores.keys().cloned().collect(),
+        usage : graph.usage.get(),
+        mock_calls : graph.mock_calls.get(),
+        ..Default::default()
+    };
+
+    for ( id, node ) in &graph.nodes
+    {
+        match &node.executor
+        {
+            NodeExecutor::Agent( a ) if a.save_history =>
+            {
+                checkpoint.histories.insert( id.clone(), a.history.clone() );
+            },
+            NodeExecutor::Graph( g ) =>
+            {
+                checkpoint.graphs.insert( id.clone(), checkpoint_from_graph( &g.graph, None ) );
+            },
+            NodeExecutor::Parallel( p ) =>
+            {
+                for ( idx, e ) in p.parallel.executors.iter().enumerate()
+                {
+                    if let ParallelExecutor::Agent { ty : _, executor, condition : _ } = e && executor.save_history
+                    {
+                        checkpoint.histories.insert( parallel_key( id, idx ), executor.history.clone() );
+                    }
+                }
+
+                for ( idx, g ) in &p.graphs
+                {
+                    checkpoint.graphs.insert( parallel_key( id, *idx ), checkpoint_from_graph( &g.graph, None ) );
+                }
+            },
+            _ => {}
+        }
+    }
+
+    checkpoint
+}
+
+/// Key of the item `idx` of a parallel node in `GraphCheckpoint.histories` and `GraphCheckpoint.graphs`
+fn parallel_key( id : &str, idx : usize ) -> String
+{
+    format!( "{}/{}", id, idx )
+}
+
+fn agent_history_mut<'a>( graph : &'a mut Graph, key : &str ) -> Option<&'a mut Vec<Message>>
+{
+    if graph.nodes.contains_key( key )
+    {
+        return match graph.nodes.get_mut( key ).map( | n | &mut n.executor )
+        {
+            Some( NodeExecutor::Agent( a ) ) => Some( &mut a.history ),
+            _ => None
+        }
+    }
+
+    let ( id, idx ) = key.rsplit_once( "/" )?;
+
+    let idx = idx.parse::<usize>().ok()?;
+
+    match graph.nodes.get_mut( id ).map( | n | &mut n.executor )
+    {
+        Some( NodeExecutor::Parallel( p ) ) => match p.parallel.executors.get_mut( idx )
+        {
+            Some( ParallelExecutor::Agent { ty : _, executor, condition : _ } ) => Some( &mut executor.history ),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+fn sub_graph_mut<'a>( graph : &'a mut Graph, key : &str ) -> Option<&'a mut Graph>
+{
+    if graph.nodes.contains_key( key )
+    {
+        return match graph.nodes.get_mut( key ).map( | n | &mut n.executor )
+        {
+            Some( NodeExecutor::Graph( g ) ) => Some( &mut g.graph ),
+            _ => None
+        }
+    }
+
+    let ( id, idx ) = key.rsplit_once( "/" )?;
+
+    let idx = idx.parse::<usize>().ok()?;
+
+    match graph.nodes.get_mut( id ).map( | n | &mut n.executor )
+    {
+        Some( NodeExecutor::Parallel( p ) ) => p.graphs.get_mut( &idx ).map( | g | &mut g.graph ),
+        _ => None
+    }
+}
+
+pub fn graph_from_checkpoint( mut graph : Graph, checkpoint : GraphCheckpoint ) -> AwpakResult<Graph, Error>
+{
+    match check_checkpoint( &mut graph, &checkpoint )
+    {
+        Ok( _ ) => AwpakResult::new( apply_checkpoint( graph, checkpoint ) ),
+        Err( e ) => AwpakResult::new_err( graph, e )
+    }
+}
+
+fn check_checkpoint( graph : &mut Graph, checkpoint : &GraphCheckpoint ) -> Result<(), Error>
+{
+    if let Some( s ) = checkpoint.stores.iter().find( | s | ! graph.stores.contains_key( *s ) )
+    {
+        return Err( Error::Store( format!( "Checkpoint store {} not found in graph", s ) ) )
+    }
+
+    if let Some( n ) = &checkpoint.next && ! graph.nodes.contains_key( n )
+    {
+        return Err( Error::NodeNotFound( format!( "Checkpoint next node {} not found in graph", n ) ) )
+    }
+
+    for id in checkpoint.histories.keys()
+    {
+        if agent_history_mut( graph, id ).is_none()
+        {
+            return Err( Error::Agent( format!( "Checkpoint agent {} not found in graph", id ) ) )
+        }
+    }
+
+    for ( id, sub_checkpoint ) in &checkpoint.graphs
+    {
+        match sub_graph_mut( graph, id )
+        {
+            Some( g ) => check_checkpoint( g, sub_checkpoint )?,
+            None => return Err( Error::NodeNotFound( format!( "Checkpoint graph node {} not found in graph", id ) ) )
+        }
+    }
+
+    Ok( () )
+}
+
+fn apply_checkpoint( mut graph : Graph, checkpoint : GraphCheckpoint ) -> Graph
+{
+    if checkpoint.id.is_some() { graph.id = checkpoint.id; }
+
+    graph.input = checkpoint.input;
+    graph.parsed_input = checkpoint.parsed_input;
+    graph.context = checkpoint.context;
+    graph.final_output = checkpoint.final_output;
+
+    for ( id, history ) in checkpoint.histories
+    {
+        if let Some( h ) = agent_history_mut( &mut graph, &id ) { *h = history; }
+    }
+
+    for ( id, sub_checkpoint ) in checkpoint.graphs
+    {
+        if let Some( g ) = sub_graph_mut( &mut graph, &id )
+        {
+            *g = apply_checkpoint( std::mem::take( g ), sub_checkpoint );
+        }
+    }
+
+    graph
+}
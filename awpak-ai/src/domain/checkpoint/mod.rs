@@ -0,0 +1,2 @@
+pub mod checkpoint;
+pub mod graph_checkpoint;
\ No newline at end of file
@@ -1,9 +1,10 @@
 use std::{os::unix::process::ExitStatusExt, process::{ExitStatus, Output, Stdio}, time::Duration};
 
+use serde_json::json;
 use tokio::{process::Child, select, time::sleep};
 use tracing::info;
 
-use crate::domain::{command::{command::{Command, CommandResult}, command_input::command_args, command_output::command_output}, data::{data_selection::data_selection, data_utils::value_to_string}, error::{ChangeError, Error}, graph::graph::Graph, signals::cancel_graph::is_graph_cancelled, tracing::filter_layer::{COMMAND_AND_ARGS, COMMAND_RESULT}, utils::string_utils::{bytes_to_str, option_string_to_str}};
+use crate::domain::{cassette::cassette::{with_cassette, CassetteKind}, command::{command::{Command, CommandResult}, command_input::command_args, command_output::command_output}, data::{data_selection::data_selection, data_utils::value_to_string}, error::{ChangeError, Error}, graph::graph::Graph, signals::cancel_graph::is_graph_cancelled, tracing::filter_layer::{COMMAND_AND_ARGS, COMMAND_RESULT}, utils::string_utils::{bytes_to_str, option_string_to_str}};
 
 pub async fn execute_command(
     graph : &Graph,
@@ -22,7 +23,24 @@ pub async fn execute_command(
 
     trace_command_and_args( id, &command_str, &args );
 
-    let result = match alternate_command_exec( id, command_str.trim(), args, command.timeout ).await
+    let result = with_cassette(
+        graph.cassette.as_ref(),
+        CassetteKind::Command,
+        || Ok( json!( { "command" : command_str.trim(), "args" : args } ) ),
+        || command_result( id, command_str.trim(), args.clone(), command.timeout )
+    ).await?;
+
+    command_output( &result, &command.output )
+}
+
+async fn command_result(
+    id : Option<&String>,
+    command : &str,
+    args : Vec<String>,
+    timeout : Option<u64>
+) -> Result<CommandResult, Error>
+{
+    match alternate_command_exec( id, command, args, timeout ).await
     {
         Ok( o ) =>
         {
@@ -41,11 +59,9 @@ pub async fn execute_command(
         },
         Err( e ) =>
         {
-            Err( Error::Command( format!( "Command execution. {:?}", e ) ) )
+            Err( Error::Command( format!( "Command execution. {:?}", e.root() ) ).with_source( e ) )
         }
-    }?;
-
-    command_output( &result, &command.output )
+    }
 }
 
 fn trace_command_and_args( graph_id : Option<&String>, command : &str, args : &Vec<String> )
@@ -77,7 +93,7 @@ async fn alternate_command_exec(
     .args( args ).spawn()
     {
         Ok( c ) => command_child_exec( id, c, timeout ).await,
-        Err( e ) => Err( Error::Command( e.to_string() ) )
+        Err( e ) => Err( Error::Command( e.to_string() ).with_source( e ) )
     }
 }
 
@@ -95,9 +111,9 @@ async fn command_child_exec(
             {
                 Ok( _ ) =>
                 {
-                    child.wait_with_output().await.map_err( | e | Error::Command( e.to_string() ) )
+                    child.wait_with_output().await.map_err( | e | Error::Command( e.to_string() ).with_source( e ) )
                 },
-                Err( e ) => Err( Error::Command( e.to_string() ) )
+                Err( e ) => Err( Error::Command( e.to_string() ).with_source( e ) )
             }
         },
         _ = async {
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::domain::mcp::mcp::NodeMCPServer;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub enum DataType
 {
@@ -24,6 +28,10 @@ pub enum DataFrom
     Operation( Box<DataOperation> ),
     AgentHistory( FromAgentHistory ),
     Store( Box<FromStore> ),
+    Expr( String ),
+    Template( Box<FromTemplate> ),
+    McpResource( Box<FromMcpResource> ),
+    McpPrompt( Box<FromMcpPrompt> ),
     Null
 }
 
@@ -33,7 +41,87 @@ pub enum DataOperation
     Len( DataFrom ),
     Substract { num_1 : DataFrom, num_2 : DataFrom },
     Add { num_1 : DataFrom, num_2 : DataFrom },
-    StringSplit { from : DataFrom, sep : String }
+    Multiply { num_1 : DataFrom, num_2 : DataFrom },
+    Divide { num_1 : DataFrom, num_2 : DataFrom },
+    Modulo { num_1 : DataFrom, num_2 : DataFrom },
+
+    StringSplit { from : DataFrom, sep : String },
+    Upper( DataFrom ),
+    Lower( DataFrom ),
+    Trim( DataFrom ),
+    Replace 
+    { 
+        from : DataFrom, 
+        search : String, 
+        replace : String, 
+        #[serde(default)]
+        regex : bool 
+    },
+    RegexCapture 
+    { 
+        from : DataFrom, 
+        regex : String, 
+        #[serde(default)]
+        all : bool 
+    },
+
+    Join { from : DataFrom, #[serde(default)] sep : String },
+    Slice 
+    { 
+        from : DataFrom, 
+        #[serde(default)]
+        start : Option<i64>, 
+        #[serde(default)]
+        end : Option<i64> 
+    },
+    Filter 
+    { 
+        from : DataFrom, 
+        condition : DataComparator, 
+        #[serde(default = "data_operation_default_item")]
+        item : String 
+    },
+    Map 
+    { 
+        from : DataFrom, 
+        map : DataFrom, 
+        #[serde(default = "data_operation_default_item")]
+        item : String 
+    },
+
+    Keys( DataFrom ),
+    Values( DataFrom ),
+    Merge( Vec<DataFrom> ),
+
+    ParseJson( DataFrom ),
+    ToJson { from : DataFrom, #[serde(default)] pretty : bool },
+
+    Base64Encode( DataFrom ),
+    Base64Decode( DataFrom ),
+    Hash { from : DataFrom, #[serde(default)] algorithm : HashAlgorithm },
+
+    Now { #[serde(default)] format : Option<String> },
+    FormatDate 
+    { 
+        from : DataFrom, 
+        format : String, 
+        #[serde(default)]
+        input_format : Option<String> 
+    }
+}
+
+fn data_operation_default_item() -> String
+{
+    "item".into()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub enum HashAlgorithm
+{
+    #[default]
+    Sha256,
+    Sha512,
+    Md5
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -52,6 +140,32 @@ pub struct FromStore
     pub samples : u64
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FromTemplate
+{
+    pub template : String,
+    #[serde(default)]
+    pub partials : HashMap<String, String>
+}
+
+/// Resource of an MCP server. The server is connected through the graph `MCPPool`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FromMcpResource
+{
+    pub server : NodeMCPServer,
+    pub uri : DataFrom
+}
+
+/// Prompt template of an MCP server. `args` are sent as strings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FromMcpPrompt
+{
+    pub server : NodeMCPServer,
+    pub name : String,
+    #[serde(default)]
+    pub args : HashMap<String, DataFrom>
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FromParsedInput
 {
@@ -106,7 +220,10 @@ pub struct DataToContext
     #[serde(default)]
     pub merge : DataMerge,
     #[serde(default)]
-    pub optional : bool
+    pub optional : bool,
+    /// JSON Schema the value must match before it is saved
+    #[serde(default)]
+    pub schema : Option<Value>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -137,6 +254,8 @@ pub enum DataComparator
     Empty( DataFrom ),
     NotEmpty( DataFrom ),
 
+    Expr( String ),
+
     True,
     False
 }
\ No newline at end of file
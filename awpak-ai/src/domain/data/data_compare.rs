@@ -2,7 +2,7 @@ use async_recursion::async_recursion;
 use regex::Regex;
 use serde_json::{Number, Value};
 
-use crate::domain::{data::{data::DataComparator, data_selection::data_selection, data_utils::{is_value_empty, value_to_string, values_are_equals}}, error::Error, graph::graph::Graph};
+use crate::domain::{data::{data::DataComparator, data_selection::data_selection, data_utils::{is_value_empty, value_to_string, values_are_equals}}, error::Error, expr::eval_expr::{eval_expr_str, value_is_truthy}, graph::graph::Graph};
 
 #[async_recursion]
 pub async fn compare_data(
@@ -114,6 +114,7 @@ pub async fn compare_data(
             )
         },
         DataComparator::True => Ok( true ),
+        DataComparator::Expr( e ) => value_is_truthy( &eval_expr_str( graph, e )? ),
         DataComparator::False => Ok( false )
     }
 }
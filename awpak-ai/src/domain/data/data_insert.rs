@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use awpak_utils::result::result::AwpakResult;
 use serde_json::{Map, Value};
 
-use crate::domain::{data::{data::{DataMerge, DataToContext}, data_utils::str_to_value}, error::Error, path::expand_path::expand_path};
+use crate::domain::{data::{data::{DataMerge, DataToContext}, data_schema::validate_schema, data_utils::str_to_value}, error::Error, path::expand_path::expand_path};
 
 
 pub fn str_to_context(
@@ -44,6 +44,13 @@ pub fn value_to_context(
         Err( e ) => return AwpakResult::new_err( ( context, Ok( Value::Null ) ), e )
     };
 
+    if let Some( schema ) = &conf.schema && 
+       ! ( conf.optional && value.is_null() ) && 
+       let Err( e ) = validate_schema( schema, &value )
+    {
+        return AwpakResult::new_err( ( context, Ok( Value::Null ) ), e.prepend_str( format!( "Output {}.\n", conf.path ) ) )
+    }
+
     match conf.merge
     {
         DataMerge::Insert => insert_allways( context, value, path.as_str() ),
@@ -448,7 +455,8 @@ mod tests
             path : "/root/inner/3".to_string(), 
             ty : DataType::String, 
             merge : DataMerge::Insert,
-            optional : false
+            optional : false,
+            schema : None
         };
 
         let context = value_to_context( context, value, conf );
@@ -518,7 +526,8 @@ mod tests
             path : "/root/inner/3".to_string(), 
             ty : DataType::String, 
             merge : DataMerge::Insert,
-            optional : false
+            optional : false,
+            schema : None
         };
 
         let context = value_to_context( context, value, conf );
@@ -607,7 +616,8 @@ mod tests
             path : "/root/inner/3".to_string(), 
             ty : DataType::String, 
             merge : DataMerge::Append,
-            optional : false
+            optional : false,
+            schema : None
         };
 
         let context = value_to_context( context, value, conf );
@@ -677,7 +687,8 @@ mod tests
             path : "/root/inner/3".to_string(), 
             ty : DataType::String, 
             merge : DataMerge::Append,
-            optional : false
+            optional : false,
+            schema : None
         };
 
         let context = value_to_context( context, value, conf );
@@ -1,7 +1,26 @@
 
-use serde_json::{Number, Value};
+use awpak_utils::string_utils::str_len;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::{format::{Item, StrftimeItems}, DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use md5::Md5;
+use regex::Regex;
+use serde_json::{Map, Number, Value};
+use sha2::{Digest, Sha256, Sha512};
 
-use crate::domain::{data::data_utils::value_to_string, error::Error};
+use crate::domain::{data::{data::HashAlgorithm, data_utils::value_to_string}, error::Error};
+
+pub fn value_len( value : Value ) -> Value
+{
+    match value
+    {
+        Value::Array( a ) => Value::from( a.len() ),
+        Value::Number( n ) => Value::Number( n ),
+        Value::Object( o ) => Value::from( o.len() ),
+        Value::Null => Value::from( 0 ),
+        Value::String( s ) => Value::from( str_len( s.as_str() ) ),
+        Value::Bool( b ) => if b { Value::from( 1_usize ) } else { Value::from( 0_usize ) }
+    }
+}
 
 pub fn string_split( v : Value, separator : impl AsRef<str> ) -> Value
 {
@@ -28,7 +47,7 @@ pub fn add_values( v1 : Value, v2 : Value ) -> Result<Value, Error>
     Ok( value_from_f64( n1 + n2 )? )
 }
 
-fn value_from_f64( number : f64 ) -> Result<Value, Error>
+pub fn value_from_f64( number : f64 ) -> Result<Value, Error>
 {
     if number == number.round()
     {
@@ -48,4 +67,261 @@ pub fn f64_from_value( value : Value ) -> Result<f64, Error>
         Value::String( s ) if s.trim() != "" => s.parse().map_err( | _ | Error::ParseData( format!( "{:?} invalid f64", s ) ) ),
         v => Err( Error::ParseData( format!( "Value is not a number. {:?}", v ) ) )
     }
-}
\ No newline at end of file
+}
+
+pub fn multiply_values( v1 : Value, v2 : Value ) -> Result<Value, Error>
+{
+    let n1 = f64_from_value( v1 )?;
+    let n2 = f64_from_value( v2 )?;
+
+    value_from_f64( n1 * n2 )
+}
+
+pub fn divide_values( v1 : Value, v2 : Value ) -> Result<Value, Error>
+{
+    let n1 = f64_from_value( v1 )?;
+    let n2 = not_zero( f64_from_value( v2 )? )?;
+
+    value_from_f64( n1 / n2 )
+}
+
+pub fn modulo_values( v1 : Value, v2 : Value ) -> Result<Value, Error>
+{
+    let n1 = f64_from_value( v1 )?;
+    let n2 = not_zero( f64_from_value( v2 )? )?;
+
+    value_from_f64( n1 % n2 )
+}
+
+fn not_zero( number : f64 ) -> Result<f64, Error>
+{
+    if number == 0.0 { return Err( Error::ParseData( "Division by zero".into() ) ) }
+
+    Ok( number )
+}
+
+pub fn map_string( v : Value, f : impl Fn( &str ) -> String ) -> Value
+{
+    Value::String( f( &value_to_string( &v ) ) )
+}
+
+pub fn replace_value( v : Value, search : &str, replace : &str, regex : bool ) -> Result<Value, Error>
+{
+    let str = value_to_string( &v );
+
+    if ! regex { return Ok( Value::String( str.replace( search, replace ) ) ) }
+
+    let regex = new_regex( search )?;
+
+    Ok( Value::String( regex.replace_all( &str, replace ).to_string() ) )
+}
+
+/// Returns the first capture group, or the whole match if the regex has no groups.
+/// With named groups returns an object. With `all` returns an array with every match.
+pub fn regex_capture( v : Value, regex : &str, all : bool ) -> Result<Value, Error>
+{
+    let str = value_to_string( &v );
+
+    let regex = new_regex( regex )?;
+
+    let names = regex.capture_names().flatten().collect::<Vec<_>>();
+
+    let capture = | c : regex::Captures | -> Value
+    {
+        if ! names.is_empty()
+        {
+            return Value::Object(
+                names.iter()
+                .map( | n | ( n.to_string(), c.name( n ).map( | m | Value::String( m.as_str().to_string() ) ).unwrap_or( Value::Null ) ) )
+                .collect()
+            )
+        }
+
+        c.get( 1 ).or( c.get( 0 ) ).map( | m | Value::String( m.as_str().to_string() ) ).unwrap_or( Value::Null )
+    };
+
+    if all { return Ok( Value::Array( regex.captures_iter( &str ).map( capture ).collect() ) ) }
+
+    Ok( regex.captures( &str ).map( capture ).unwrap_or( Value::Null ) )
+}
+
+fn new_regex( regex : &str ) -> Result<Regex, Error>
+{
+    Regex::new( regex ).map_err( | e | Error::ParseData( format!( "Invalid regex: {}. Error: {}", regex, e ) ) )
+}
+
+pub fn join_values( v : Value, sep : &str ) -> Result<Value, Error>
+{
+    match v
+    {
+        Value::Array( a ) => Ok( Value::String( a.iter().map( value_to_string ).collect::<Vec<_>>().join( sep ) ) ),
+        Value::Null => Ok( Value::String( "".into() ) ),
+        v => Err( Error::ParseData( format!( "Join. Value is not an array. {:?}", v ) ) )
+    }
+}
+
+/// Slices arrays and strings (by chars). Negative positions count from the end.
+pub fn slice_value( v : Value, start : Option<i64>, end : Option<i64> ) -> Result<Value, Error>
+{
+    let range = | len : usize |
+    {
+        let pos = | p : i64 | if p < 0 { ( len as i64 + p ).max( 0 ) as usize } else { ( p as usize ).min( len ) };
+
+        let start = start.map( pos ).unwrap_or( 0 );
+        let end = end.map( pos ).unwrap_or( len );
+
+        ( start, end.max( start ) )
+    };
+
+    match v
+    {
+        Value::Array( a ) =>
+        {
+            let ( start, end ) = range( a.len() );
+
+            Ok( Value::Array( a[ start..end ].to_vec() ) )
+        },
+        Value::String( s ) =>
+        {
+            let ( start, end ) = range( s.chars().count() );
+
+            Ok( Value::String( s.chars().skip( start ).take( end - start ).collect() ) )
+        },
+        Value::Null => Ok( Value::Null ),
+        v => Err( Error::ParseData( format!( "Slice. Value is not an array or a string. {:?}", v ) ) )
+    }
+}
+
+pub fn object_keys( v : Value ) -> Value
+{
+    match v
+    {
+        Value::Object( o ) => Value::Array( o.into_iter().map( | ( k, _ ) | Value::String( k ) ).collect() ),
+        Value::Array( a ) => Value::Array( ( 0..a.len() ).map( Value::from ).collect() ),
+        _ => Value::Array( vec![] )
+    }
+}
+
+pub fn object_values( v : Value ) -> Value
+{
+    match v
+    {
+        Value::Object( o ) => Value::Array( o.into_iter().map( | ( _, v ) | v ).collect() ),
+        Value::Array( a ) => Value::Array( a ),
+        _ => Value::Array( vec![] )
+    }
+}
+
+/// Shallow merge. Keys of later objects replace keys of earlier ones. `null` values are skipped.
+pub fn merge_objects( values : Vec<Value> ) -> Result<Value, Error>
+{
+    let mut ret = Map::new();
+
+    for v in values
+    {
+        match v
+        {
+            Value::Object( o ) => ret.extend( o ),
+            Value::Null => {},
+            v => return Err( Error::ParseData( format!( "Merge. Value is not an object. {:?}", v ) ) )
+        }
+    }
+
+    Ok( Value::Object( ret ) )
+}
+
+pub fn parse_json( v : Value ) -> Result<Value, Error>
+{
+    match v
+    {
+        Value::String( s ) => serde_json::from_str( &s ).map_err( | e | Error::ParseData( format!( "ParseJson. {}", e ) ) ),
+        v => Ok( v )
+    }
+}
+
+pub fn to_json( v : Value, pretty : bool ) -> Result<Value, Error>
+{
+    let str = if pretty { serde_json::to_string_pretty( &v ) } else { serde_json::to_string( &v ) };
+
+    Ok( Value::String( str.map_err( | e | Error::ParseData( format!( "ToJson. {}", e ) ) )? ) )
+}
+
+pub fn base64_encode( v : Value ) -> Value
+{
+    Value::String( BASE64_STANDARD.encode( value_to_string( &v ) ) )
+}
+
+pub fn base64_decode( v : Value ) -> Result<Value, Error>
+{
+    let bytes = BASE64_STANDARD.decode( value_to_string( &v ).trim() ).map_err( | e | Error::ParseData( format!( "Base64Decode. {}", e ) ) )?;
+
+    Ok( Value::String( String::from_utf8( bytes ).map_err( | e | Error::ParseData( format!( "Base64Decode. {}", e ) ) )? ) )
+}
+
+pub fn hash_value( v : Value, algorithm : &HashAlgorithm ) -> Value
+{
+    let str = value_to_string( &v );
+
+    Value::String(
+        match algorithm
+        {
+            HashAlgorithm::Sha256 => format!( "{:x}", Sha256::digest( str.as_bytes() ) ),
+            HashAlgorithm::Sha512 => format!( "{:x}", Sha512::digest( str.as_bytes() ) ),
+            HashAlgorithm::Md5 => format!( "{:x}", Md5::digest( str.as_bytes() ) )
+        }
+    )
+}
+
+/// Current UTC date. RFC 3339 if there is no `format`.
+pub fn now( format : Option<&str> ) -> Result<Value, Error>
+{
+    format_date_time( Utc::now(), format )
+}
+
+/// Dates can be RFC 3339 strings, strings with `input_format` or unix timestamps in seconds.
+pub fn format_date( v : Value, format : &str, input_format : Option<&str> ) -> Result<Value, Error>
+{
+    let date = match ( v, input_format )
+    {
+        ( Value::Number( n ), _ ) =>
+        {
+            let secs = n.as_f64().ok_or( Error::ParseData( format!( "FormatDate. Invalid timestamp {}", n ) ) )?;
+
+            DateTime::from_timestamp( secs as i64, 0 ).ok_or( Error::ParseData( format!( "FormatDate. Invalid timestamp {}", n ) ) )?
+        },
+        ( Value::String( s ), Some( f ) ) => parse_date_with_format( &s, f )?,
+        ( Value::String( s ), None ) => DateTime::parse_from_rfc3339( s.trim() )
+            .map_err( | e | Error::ParseData( format!( "FormatDate. Invalid date {}. {}", s, e ) ) )?
+            .with_timezone( &Utc ),
+        ( v, _ ) => return Err( Error::ParseData( format!( "FormatDate. Invalid date {:?}", v ) ) )
+    };
+
+    format_date_time( date, Some( format ) )
+}
+
+fn parse_date_with_format( date : &str, format : &str ) -> Result<DateTime<Utc>, Error>
+{
+    let date = date.trim();
+
+    if let Ok( d ) = DateTime::parse_from_str( date, format ) { return Ok( d.with_timezone( &Utc ) ) }
+
+    if let Ok( d ) = NaiveDateTime::parse_from_str( date, format ) { return Ok( d.and_utc() ) }
+
+    match NaiveDate::parse_from_str( date, format )
+    {
+        Ok( d ) => Ok( d.and_time( NaiveTime::MIN ).and_utc() ),
+        Err( e ) => Err( Error::ParseData( format!( "FormatDate. Invalid date {} for format {}. {}", date, format, e ) ) )
+    }
+}
+
+fn format_date_time( date : DateTime<Utc>, format : Option<&str> ) -> Result<Value, Error>
+{
+    let Some( format ) = format else { return Ok( Value::String( date.to_rfc3339() ) ) };
+
+    if StrftimeItems::new( format ).any( | i | matches!( i, Item::Error ) )
+    {
+        return Err( Error::ParseData( format!( "Invalid date format {}", format ) ) )
+    }
+
+    Ok( Value::String( date.format( format ).to_string() ) )
+}
@@ -0,0 +1,32 @@
+use serde_json::Value;
+
+use crate::domain::error::Error;
+
+
+/// Validates `value` against a JSON Schema. 
+/// 
+/// The error has one line per failed rule, with the JSON pointer of the value ( `/` is the root ).
+pub fn validate_schema( schema : &Value, value : &Value ) -> Result<(), Error>
+{
+    let validator = jsonschema::validator_for( schema ).map_err( | e | Error::Schema( format!( "Invalid schema. {}", e ) ) )?;
+
+    let errors = validator.iter_errors( value )
+    .map(
+        | e |
+        {
+            let path = e.instance_path.to_string();
+
+            format!( "{}: {}", if path.is_empty() { "/" } else { path.as_str() }, e )
+        }
+    )
+    .collect::<Vec<_>>();
+
+    if errors.is_empty() { return Ok( () ) }
+
+    Err( Error::Schema( errors.join( "\n" ) ) )
+}
+
+pub fn check_schema( schema : &Value ) -> Result<(), Error>
+{
+    jsonschema::validator_for( schema ).map( | _ | () ).map_err( | e | Error::Schema( format!( "Invalid schema. {}", e ) ) )
+}
@@ -1,10 +1,9 @@
 use std::collections::HashMap;
 
 use async_recursion::async_recursion;
-use awpak_utils::string_utils::str_len;
 use serde_json::Value;
 
-use crate::domain::{data::{data::{DataFrom, DataOperation, DataToString, FromContext, FromParsedInput}, data_history::data_from_agent_history, data_insert::merge_values, data_operations::{add_values, string_split, substract_values}, data_utils::{value_from_map, value_is_null, value_to_string}}, error::Error, graph::graph::Graph, path::expand_path::expand_path, store::store_query::store_query_from_graph_store};
+use crate::domain::{data::{data::{DataFrom, DataOperation, DataToString, FromContext, FromParsedInput}, data_history::data_from_agent_history, data_insert::merge_values, data_compare::compare_data, data_operations::{add_values, base64_decode, base64_encode, divide_values, format_date, hash_value, join_values, map_string, merge_objects, modulo_values, multiply_values, now, object_keys, object_values, parse_json, regex_capture, replace_value, slice_value, string_split, substract_values, to_json, value_len}, data_utils::{value_from_map, value_is_null, value_to_string}}, error::Error, expr::eval_expr::eval_expr_str, graph::graph::Graph, mcp::mcp_data::{data_from_mcp_prompt, data_from_mcp_resource}, path::expand_path::expand_path, store::store_query::store_query_from_graph_store, template::render_template::render_template};
 
 pub async fn data_to_string(
     graph : &Graph,
@@ -69,6 +68,10 @@ pub async fn data_selection(
         DataFrom::Operation( o ) => operation_data_from( graph, o ).await,
         DataFrom::AgentHistory( h ) => data_from_agent_history( graph, h ),
         DataFrom::Store( s ) => store_query_from_graph_store( graph, s ).await,
+        DataFrom::Expr( e ) => eval_expr_str( graph, e ),
+        DataFrom::Template( t ) => render_template( graph, t ),
+        DataFrom::McpResource( r ) => data_from_mcp_resource( graph, r ).await,
+        DataFrom::McpPrompt( p ) => data_from_mcp_prompt( graph, p ).await,
         DataFrom::Null => Ok( Value::Null )
     }
 }
@@ -84,15 +87,7 @@ async fn operation_data_from(
         {
             let value = data_selection( graph, f ).await?;
 
-            match value
-            {
-                Value::Array( a ) => Ok( Value::from( a.len() ) ),
-                Value::Number( n ) => Ok( Value::Number( n ) ),
-                Value::Object( o ) => Ok( Value::from( o.len() ) ),
-                Value::Null => Ok( Value::from( 0 ) ),
-                Value::String( s ) => Ok( Value::from( str_len( s.as_str() ) ) ),
-                Value::Bool( b ) => Ok( if b { Value::from( 1 as usize ) } else { Value::from( 0 as usize ) } )
-            }
+            Ok( value_len( value ) )
         },
         DataOperation::Substract { num_1, num_2 } =>
         {
@@ -108,15 +103,109 @@ async fn operation_data_from(
 
             add_values( v1, v2 )
         },
+        DataOperation::Multiply { num_1, num_2 } =>
+        {
+            let v1 = data_selection( graph, num_1 ).await?;
+            let v2 = data_selection( graph, num_2 ).await?;
+
+            multiply_values( v1, v2 )
+        },
+        DataOperation::Divide { num_1, num_2 } =>
+        {
+            let v1 = data_selection( graph, num_1 ).await?;
+            let v2 = data_selection( graph, num_2 ).await?;
+
+            divide_values( v1, v2 )
+        },
+        DataOperation::Modulo { num_1, num_2 } =>
+        {
+            let v1 = data_selection( graph, num_1 ).await?;
+            let v2 = data_selection( graph, num_2 ).await?;
+
+            modulo_values( v1, v2 )
+        },
         DataOperation::StringSplit { from, sep } =>
         {
             let value = data_selection( graph, from ).await?;
 
             Ok( string_split( value, sep ) )
+        },
+        DataOperation::Upper( f ) => Ok( map_string( data_selection( graph, f ).await?, | s | s.to_uppercase() ) ),
+        DataOperation::Lower( f ) => Ok( map_string( data_selection( graph, f ).await?, | s | s.to_lowercase() ) ),
+        DataOperation::Trim( f ) => Ok( map_string( data_selection( graph, f ).await?, | s | s.trim().to_string() ) ),
+        DataOperation::Replace { from, search, replace, regex } =>
+        {
+            replace_value( data_selection( graph, from ).await?, search, replace, *regex )
+        },
+        DataOperation::RegexCapture { from, regex, all } =>
+        {
+            regex_capture( data_selection( graph, from ).await?, regex, *all )
+        },
+        DataOperation::Join { from, sep } => join_values( data_selection( graph, from ).await?, sep ),
+        DataOperation::Slice { from, start, end } => slice_value( data_selection( graph, from ).await?, *start, *end ),
+        DataOperation::Filter { from, condition, item } =>
+        {
+            let mut ret = vec![];
+
+            let mut graph_item = graph.clone();
+
+            for value in array_from_value( data_selection( graph, from ).await?, "Filter" )?
+            {
+                graph_item.context.insert( item.clone(), value.clone() );
+
+                if compare_data( &graph_item, condition ).await? { ret.push( value ); }
+            }
+
+            Ok( Value::Array( ret ) )
+        },
+        DataOperation::Map { from, map, item } =>
+        {
+            let mut ret = vec![];
+
+            let mut graph_item = graph.clone();
+
+            for value in array_from_value( data_selection( graph, from ).await?, "Map" )?
+            {
+                graph_item.context.insert( item.clone(), value );
+
+                ret.push( data_selection( &graph_item, map ).await? );
+            }
+
+            Ok( Value::Array( ret ) )
+        },
+        DataOperation::Keys( f ) => Ok( object_keys( data_selection( graph, f ).await? ) ),
+        DataOperation::Values( f ) => Ok( object_values( data_selection( graph, f ).await? ) ),
+        DataOperation::Merge( from ) =>
+        {
+            let mut values = vec![];
+
+            for f in from { values.push( data_selection( graph, f ).await? ); }
+
+            merge_objects( values )
+        },
+        DataOperation::ParseJson( f ) => parse_json( data_selection( graph, f ).await? ),
+        DataOperation::ToJson { from, pretty } => to_json( data_selection( graph, from ).await?, *pretty ),
+        DataOperation::Base64Encode( f ) => Ok( base64_encode( data_selection( graph, f ).await? ) ),
+        DataOperation::Base64Decode( f ) => base64_decode( data_selection( graph, f ).await? ),
+        DataOperation::Hash { from, algorithm } => Ok( hash_value( data_selection( graph, from ).await?, algorithm ) ),
+        DataOperation::Now { format } => now( format.as_deref() ),
+        DataOperation::FormatDate { from, format, input_format } =>
+        {
+            format_date( data_selection( graph, from ).await?, format, input_format.as_deref() )
         }
     }
 }
 
+fn array_from_value( value : Value, operation : &str ) -> Result<Vec<Value>, Error>
+{
+    match value
+    {
+        Value::Array( a ) => Ok( a ),
+        Value::Null => Ok( vec![] ),
+        v => Err( Error::ParseData( format!( "{}. Value is not an array. {:?}", operation, v ) ) )
+    }
+}
+
 async fn concat_data_from(
     graph : &Graph,
     from : &Vec<DataFrom>
@@ -4,4 +4,5 @@ pub mod data_utils;
 pub mod data_insert;
 pub mod data_compare;
 pub mod data_operations;
-pub mod data_history;
\ No newline at end of file
+pub mod data_history;
+pub mod data_schema;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use std::fmt::{Display};
+use std::{fmt::Display, sync::Arc};
 
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -13,9 +13,40 @@ pub enum Error
     NodeExists( String ),
     File( String ),
     Store( String ),
+    Validation( String ),
+    LimitExceeded( String ),
+    Schema( String ),
+    HumanInput( String ),
+    Node( Box<NodeError> ),
+    Source( Box<Error>, SourceError ),
     Ignore
 }
 
+/// Error raised while executing a node. 
+/// 
+/// The wrapper is transparent for `Display`: the text is the same as the text of `cause`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeError
+{
+    pub graph_id : Option<String>,
+    pub node_id : String,
+    pub executor : String,
+    pub cause : Error
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceError( pub Arc<dyn std::error::Error + Send + Sync> );
+
+impl PartialEq for SourceError
+{
+    fn eq( &self, other : &Self ) -> bool
+    {
+        self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for SourceError {}
+
 impl Error
 {
     pub fn append_str( self, str : impl AsRef<str> ) -> Self
@@ -31,6 +62,12 @@ impl Error
             Error::NodeExists( s ) => Error::NodeExists( format!( "{}{}", s, str.as_ref() ) ),
             Error::File( s ) => Error::File( format!( "{}{}", s, str.as_ref() ) ),
             Error::Store( s ) => Error::Store( format!( "{}{}", s, str.as_ref() ) ),
+            Error::Validation( s ) => Error::Validation( format!( "{}{}", s, str.as_ref() ) ),
+            Error::LimitExceeded( s ) => Error::LimitExceeded( format!( "{}{}", s, str.as_ref() ) ),
+            Error::Schema( s ) => Error::Schema( format!( "{}{}", s, str.as_ref() ) ),
+            Error::HumanInput( s ) => Error::HumanInput( format!( "{}{}", s, str.as_ref() ) ),
+            Error::Node( mut n ) => { n.cause = n.cause.append_str( str ); Error::Node( n ) },
+            Error::Source( e, s ) => Error::Source( Box::new( e.append_str( str ) ), s ),
             Error::Ignore => Error::Ignore
         }
     }
@@ -48,11 +85,132 @@ impl Error
             Error::NodeExists( s ) => Error::NodeExists( format!( "{}{}", str.as_ref(), s ) ),
             Error::File( s ) => Error::File( format!( "{}{}", str.as_ref(), s ) ),
             Error::Store( s ) => Error::Store( format!( "{}{}", str.as_ref(), s ) ),
+            Error::Validation( s ) => Error::Validation( format!( "{}{}", str.as_ref(), s ) ),
+            Error::LimitExceeded( s ) => Error::LimitExceeded( format!( "{}{}", str.as_ref(), s ) ),
+            Error::Schema( s ) => Error::Schema( format!( "{}{}", str.as_ref(), s ) ),
+            Error::HumanInput( s ) => Error::HumanInput( format!( "{}{}", str.as_ref(), s ) ),
+            Error::Node( mut n ) => { n.cause = n.cause.prepend_str( str ); Error::Node( n ) },
+            Error::Source( e, s ) => Error::Source( Box::new( e.prepend_str( str ) ), s ),
             Error::Ignore => Error::Ignore
         }
     }
 }
 
+pub const ERROR_KINDS : [&str; 14] = [
+    "ParseData", "MCPTool", "Agent", "Command", "WebClient", "NodeNotFound", 
+    "NodeExists", "File", "Store", "Validation", "LimitExceeded", "Schema", "HumanInput", "Ignore"
+];
+
+impl Error
+{
+    pub fn with_source( self, source : impl std::error::Error + Send + Sync + 'static ) -> Self
+    {
+        Error::Source( Box::new( self ), SourceError( Arc::new( source ) ) )
+    }
+
+    pub fn in_node( self, graph_id : Option<&String>, node_id : impl Into<String>, executor : impl Into<String> ) -> Self
+    {
+        Error::Node( 
+            Box::new( 
+                NodeError 
+                { 
+                    graph_id : graph_id.cloned(), 
+                    node_id : node_id.into(), 
+                    executor : executor.into(), 
+                    cause : self 
+                } 
+            ) 
+        )
+    }
+
+    /// Outermost node error. For sub-graphs, the node of the parent graph.
+    pub fn node( &self ) -> Option<&NodeError>
+    {
+        match self
+        {
+            Error::Node( n ) => Some( n ),
+            Error::Source( e, _ ) => e.node(),
+            _ => None
+        }
+    }
+
+    /// Nodes from the outermost graph to the graph where the error was raised.
+    pub fn node_trail( &self ) -> Vec<&NodeError>
+    {
+        let mut ret = vec![];
+
+        let mut current = self;
+
+        loop
+        {
+            match current
+            {
+                Error::Node( n ) => { ret.push( n.as_ref() ); current = &n.cause },
+                Error::Source( e, _ ) => current = e,
+                _ => return ret
+            }
+        }
+    }
+
+    /// Error without node and source wrappers.
+    pub fn root( &self ) -> &Error
+    {
+        match self
+        {
+            Error::Node( n ) => n.cause.root(),
+            Error::Source( e, _ ) => e.root(),
+            _ => self
+        }
+    }
+
+    pub fn kind( &self ) -> &'static str
+    {
+        match self
+        {
+            Error::Node( n ) => n.cause.kind(),
+            Error::Source( e, _ ) => e.kind(),
+            Error::ParseData( _ ) => "ParseData",
+            Error::MCPTool( _ ) => "MCPTool",
+            Error::Agent( _ ) => "Agent",
+            Error::Command( _ ) => "Command",
+            Error::WebClient( _ ) => "WebClient",
+            Error::NodeNotFound( _ ) => "NodeNotFound",
+            Error::NodeExists( _ ) => "NodeExists",
+            Error::File( _ ) => "File",
+            Error::Store( _ ) => "Store",
+            Error::Validation( _ ) => "Validation",
+            Error::LimitExceeded( _ ) => "LimitExceeded",
+            Error::Schema( _ ) => "Schema",
+            Error::HumanInput( _ ) => "HumanInput",
+            Error::Ignore => "Ignore"
+        }
+    }
+
+    /// Error of `kind` with the text `text`. `None` if `kind` is not the kind of a text error
+    pub fn from_kind( kind : &str, text : impl Into<String> ) -> Option<Self>
+    {
+        let text = text.into();
+
+        match kind
+        {
+            "ParseData" => Some( Error::ParseData( text ) ),
+            "MCPTool" => Some( Error::MCPTool( text ) ),
+            "Agent" => Some( Error::Agent( text ) ),
+            "Command" => Some( Error::Command( text ) ),
+            "WebClient" => Some( Error::WebClient( text ) ),
+            "NodeNotFound" => Some( Error::NodeNotFound( text ) ),
+            "NodeExists" => Some( Error::NodeExists( text ) ),
+            "File" => Some( Error::File( text ) ),
+            "Store" => Some( Error::Store( text ) ),
+            "Validation" => Some( Error::Validation( text ) ),
+            "LimitExceeded" => Some( Error::LimitExceeded( text ) ),
+            "Schema" => Some( Error::Schema( text ) ),
+            "HumanInput" => Some( Error::HumanInput( text ) ),
+            _ => None
+        }
+    }
+}
+
 pub trait ChangeError
 {
     fn append_err( self, str : impl AsRef<str> ) -> Self;
@@ -93,6 +251,12 @@ fn error_to_string( err : &Error ) -> String
         Error::NodeExists( s ) => format!( "NodeExists: {}", s ),
         Error::File( s ) => format!( "File: {}", s ),
         Error::Store( s ) => format!( "Store: {}", s ),
+        Error::Validation( s ) => format!( "Validation: {}", s ),
+        Error::LimitExceeded( s ) => format!( "LimitExceeded: {}", s ),
+        Error::Schema( s ) => format!( "Schema: {}", s ),
+        Error::HumanInput( s ) => format!( "HumanInput: {}", s ),
+        Error::Node( n ) => error_to_string( &n.cause ),
+        Error::Source( e, _ ) => error_to_string( e ),
         Error::Ignore => "Ignore".into()
     }
 }
@@ -105,4 +269,56 @@ impl Display for Error
     }
 }
 
-impl std::error::Error for Error {}
\ No newline at end of file
+impl std::error::Error for Error 
+{
+    fn source( &self ) -> Option<&( dyn std::error::Error + 'static )>
+    {
+        match self
+        {
+            Error::Node( n ) => std::error::Error::source( &n.cause ),
+            Error::Source( _, s ) => Some( s.0.as_ref() ),
+            _ => None
+        }
+    }
+}
+
+impl Display for NodeError
+{
+    fn fmt( &self, f : &mut std::fmt::Formatter<'_> ) -> std::fmt::Result
+    {
+        write!( f, "{}", self.cause )
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_error_kinds()
+    {
+        let errors = vec![
+            Error::ParseData( "".into() ), Error::MCPTool( "".into() ), Error::Agent( "".into() ), Error::Command( "".into() ),
+            Error::WebClient( "".into() ), Error::NodeNotFound( "".into() ), Error::NodeExists( "".into() ), Error::File( "".into() ),
+            Error::Store( "".into() ), Error::Validation( "".into() ), Error::LimitExceeded( "".into() ), Error::Schema( "".into() ),
+            Error::HumanInput( "".into() ), Error::Ignore
+        ];
+
+        for e in &errors
+        {
+            // A new variant does not compile until it is added to `errors`
+            match e
+            {
+                Error::ParseData( _ ) | Error::MCPTool( _ ) | Error::Agent( _ ) | Error::Command( _ ) | Error::WebClient( _ ) |
+                Error::NodeNotFound( _ ) | Error::NodeExists( _ ) | Error::File( _ ) | Error::Store( _ ) | Error::Validation( _ ) |
+                Error::LimitExceeded( _ ) | Error::Schema( _ ) | Error::HumanInput( _ ) | Error::Ignore => {},
+                Error::Node( _ ) | Error::Source( _, _ ) => unreachable!()
+            }
+
+            assert!( ERROR_KINDS.contains( &e.kind() ), "{}", e.kind() );
+        }
+
+        assert_eq!( errors.len(), ERROR_KINDS.len() );
+    }
+}
\ No newline at end of file
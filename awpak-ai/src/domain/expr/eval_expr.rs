@@ -0,0 +1,361 @@
+use std::cmp::Ordering;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::domain::{data::{data::{FromAgentHistory, FromAgentHistoryContent}, data_history::data_from_agent_history, data_operations::{f64_from_value, join_values, object_keys, object_values, replace_value, string_split, value_from_f64, value_len}, data_utils::{is_value_empty, value_to_string, values_are_equals}}, error::Error, expr::{expr::{BinaryOp, Expr, ExprVar, UnaryOp}, parse_expr::parse_expr}, graph::graph::Graph};
+
+
+pub fn eval_expr_str( graph : &Graph, source : &str ) -> Result<Value, Error>
+{
+    let expr = parse_expr( source )?;
+
+    eval_expr( graph, &expr ).map_err( | e | e.prepend_str( format!( "Expression: {}\n", source ) ) )
+}
+
+pub fn eval_expr( graph : &Graph, expr : &Expr ) -> Result<Value, Error>
+{
+    match expr
+    {
+        Expr::Literal( v ) => Ok( v.clone() ),
+        Expr::Array( a ) => Ok( Value::Array( a.iter().map( | e | eval_expr( graph, e ) ).collect::<Result<Vec<_>, _>>()? ) ),
+        Expr::Var( v ) => Ok( eval_var( graph, *v ) ),
+        Expr::Member( e, name ) => Ok( member( eval_expr( graph, e )?, name ) ),
+        Expr::Index( e, i ) =>
+        {
+            let value = eval_expr( graph, e )?;
+            let index = eval_expr( graph, i )?;
+
+            index_value( value, index )
+        },
+        Expr::Unary( UnaryOp::Not, e ) => Ok( Value::Bool( ! value_is_truthy( &eval_expr( graph, e )? )? ) ),
+        Expr::Unary( UnaryOp::Neg, e ) => value_from_f64( - f64_from_value( eval_expr( graph, e )? )? ),
+        Expr::Binary( op, a, b ) => eval_binary( graph, *op, a, b ),
+        Expr::Cond { cond, then, otherwise } =>
+        {
+            if value_is_truthy( &eval_expr( graph, cond )? )? { eval_expr( graph, then ) } else { eval_expr( graph, otherwise ) }
+        },
+        Expr::Call( name, args ) if name == "history" =>
+        {
+            let id = value_to_string( &eval_expr( graph, args.first().unwrap_or( &Expr::Literal( Value::Null ) ) )? );
+
+            data_from_agent_history( graph, &FromAgentHistory { id, content : FromAgentHistoryContent::FullMessages } )
+        },
+        Expr::Call( name, args ) =>
+        {
+            let args = args.iter().map( | e | eval_expr( graph, e ) ).collect::<Result<Vec<_>, _>>()?;
+
+            call_function( name, args ).map_err( | e | e.prepend_str( format!( "Function {}: ", name ) ) )
+        }
+    }
+}
+
+/// `false`, `null`, `0`, empty strings, empty arrays and empty objects are false.
+pub fn value_is_truthy( value : &Value ) -> Result<bool, Error>
+{
+    match value
+    {
+        Value::Bool( b ) => Ok( *b ),
+        v => Ok( ! is_value_empty( v )? )
+    }
+}
+
+fn eval_var( graph : &Graph, var : ExprVar ) -> Value
+{
+    match var
+    {
+        ExprVar::Context => Value::Object( graph.context.iter().map( | ( k, v ) | ( k.clone(), v.clone() ) ).collect::<Map<_, _>>() ),
+        ExprVar::Input => graph.input.as_ref().map( | i | Value::String( i.trim().to_string() ) ).unwrap_or( Value::Null ),
+        ExprVar::ParsedInput => graph.parsed_input.clone()
+    }
+}
+
+fn member( value : Value, name : &str ) -> Value
+{
+    match value
+    {
+        Value::Object( mut o ) => o.remove( name ).unwrap_or( Value::Null ),
+        _ => Value::Null
+    }
+}
+
+fn index_value( value : Value, index : Value ) -> Result<Value, Error>
+{
+    match ( value, index )
+    {
+        ( Value::Object( o ), Value::String( s ) ) => Ok( member( Value::Object( o ), &s ) ),
+        ( Value::Array( mut a ), i ) =>
+        {
+            let i = f64_from_value( i )? as i64;
+
+            let i = if i < 0 { a.len() as i64 + i } else { i };
+
+            if i < 0 || i as usize >= a.len() { return Ok( Value::Null ) }
+
+            Ok( a.swap_remove( i as usize ) )
+        },
+        ( Value::Null, _ ) => Ok( Value::Null ),
+        ( v, i ) => Err( Error::ParseData( format!( "Invalid index {} for {}", i, v ) ) )
+    }
+}
+
+/// The right side of `&&` and `||` is only evaluated when needed
+fn eval_binary( graph : &Graph, op : BinaryOp, a : &Expr, b : &Expr ) -> Result<Value, Error>
+{
+    let a = eval_expr( graph, a )?;
+    let b = || eval_expr( graph, b );
+
+    match op
+    {
+        BinaryOp::And => Ok( Value::Bool( value_is_truthy( &a )? && value_is_truthy( &b()? )? ) ),
+        BinaryOp::Or => Ok( Value::Bool( value_is_truthy( &a )? || value_is_truthy( &b()? )? ) ),
+        BinaryOp::Add => add( a, b()? ),
+        BinaryOp::Sub => value_from_f64( f64_from_value( a )? - f64_from_value( b()? )? ),
+        BinaryOp::Mul => value_from_f64( f64_from_value( a )? * f64_from_value( b()? )? ),
+        BinaryOp::Div | BinaryOp::Rem =>
+        {
+            let n1 = f64_from_value( a )?;
+            let n2 = f64_from_value( b()? )?;
+
+            if n2 == 0.0 { return Err( Error::ParseData( "Division by zero".into() ) ) }
+
+            value_from_f64( if matches!( op, BinaryOp::Div ) { n1 / n2 } else { n1 % n2 } )
+        },
+        BinaryOp::Eq => Ok( Value::Bool( values_are_equals( &a, &b()? ) ) ),
+        BinaryOp::NotEq => Ok( Value::Bool( ! values_are_equals( &a, &b()? ) ) ),
+        BinaryOp::Gt => Ok( Value::Bool( compare_values( a, b()? )? == Ordering::Greater ) ),
+        BinaryOp::Gte => Ok( Value::Bool( compare_values( a, b()? )? != Ordering::Less ) ),
+        BinaryOp::Lt => Ok( Value::Bool( compare_values( a, b()? )? == Ordering::Less ) ),
+        BinaryOp::Lte => Ok( Value::Bool( compare_values( a, b()? )? != Ordering::Greater ) )
+    }
+}
+
+/// Numbers are added. If one of the values is a string they are concatenated as strings.
+/// Arrays are concatenated.
+fn add( a : Value, b : Value ) -> Result<Value, Error>
+{
+    match ( a, b )
+    {
+        ( Value::Array( mut a ), Value::Array( b ) ) =>
+        {
+            a.extend( b );
+
+            Ok( Value::Array( a ) )
+        },
+        ( a @ Value::String( _ ), b ) |
+        ( a, b @ Value::String( _ ) ) => Ok( Value::String( format!( "{}{}", value_to_string( &a ), value_to_string( &b ) ) ) ),
+        ( a, b ) => value_from_f64( f64_from_value( a )? + f64_from_value( b )? )
+    }
+}
+
+/// Two strings are compared alphabetically. Any other values are compared as numbers.
+fn compare_values( a : Value, b : Value ) -> Result<Ordering, Error>
+{
+    match ( a, b )
+    {
+        ( Value::String( a ), Value::String( b ) ) => Ok( a.cmp( &b ) ),
+        ( a, b ) =>
+        {
+            let n1 = f64_from_value( a )?;
+            let n2 = f64_from_value( b )?;
+
+            n1.partial_cmp( &n2 ).ok_or( Error::ParseData( format!( "{} and {} can not be compared", n1, n2 ) ) )
+        }
+    }
+}
+
+fn call_function( name : &str, args : Vec<Value> ) -> Result<Value, Error>
+{
+    match name
+    {
+        "min" | "max" =>
+        {
+            let numbers = variadic_args( args ).into_iter().map( f64_from_value ).collect::<Result<Vec<_>, _>>()?;
+
+            let number = numbers.into_iter().reduce( | a, b | if ( name == "min" ) == ( b < a ) { b } else { a } );
+
+            return match number
+            {
+                Some( n ) => value_from_f64( n ),
+                None => Ok( Value::Null )
+            }
+        },
+        "coalesce" => return Ok( variadic_args( args ).into_iter().find( | v | ! v.is_null() ).unwrap_or( Value::Null ) ),
+        _ => {}
+    }
+
+    let mut args = args.into_iter();
+
+    let mut arg = || args.next().unwrap_or( Value::Null );
+
+    match name
+    {
+        "len" => Ok( value_len( arg() ) ),
+        "lower" => Ok( Value::String( value_to_string( &arg() ).to_lowercase() ) ),
+        "upper" => Ok( Value::String( value_to_string( &arg() ).to_uppercase() ) ),
+        "trim" => Ok( Value::String( value_to_string( &arg() ).trim().to_string() ) ),
+        "contains" =>
+        {
+            let ( haystack, needle ) = ( arg(), arg() );
+
+            Ok( Value::Bool(
+                match haystack
+                {
+                    Value::Array( a ) => a.iter().any( | v | values_are_equals( v, &needle ) ),
+                    Value::Object( o ) => o.contains_key( &value_to_string( &needle ) ),
+                    v => value_to_string( &v ).contains( &value_to_string( &needle ) )
+                }
+            ) )
+        },
+        "starts_with" => Ok( Value::Bool( value_to_string( &arg() ).starts_with( &value_to_string( &arg() ) ) ) ),
+        "ends_with" => Ok( Value::Bool( value_to_string( &arg() ).ends_with( &value_to_string( &arg() ) ) ) ),
+        "replace" =>
+        {
+            let ( str, from, to ) = ( arg(), value_to_string( &arg() ), value_to_string( &arg() ) );
+
+            replace_value( str, &from, &to, false )
+        },
+        "split" =>
+        {
+            let ( str, sep ) = ( arg(), value_to_string( &arg() ) );
+
+            Ok( string_split( str, sep ) )
+        },
+        "join" =>
+        {
+            let ( values, sep ) = ( arg(), value_to_string( &arg() ) );
+
+            join_values( values, &sep )
+        },
+        "substring" =>
+        {
+            let str = value_to_string( &arg() );
+            let start = f64_from_value( arg() )? as usize;
+            let len = match arg() { Value::Null => usize::MAX, v => f64_from_value( v )? as usize };
+
+            Ok( Value::String( str.chars().skip( start ).take( len ).collect() ) )
+        },
+        "matches" =>
+        {
+            let ( str, regex ) = ( value_to_string( &arg() ), value_to_string( &arg() ) );
+
+            let regex = Regex::new( &regex ).map_err( | e | Error::ParseData( format!( "Invalid regex: {}. Error: {}", regex, e ) ) )?;
+
+            Ok( Value::Bool( regex.is_match( &str ) ) )
+        },
+        "string" => Ok( Value::String( value_to_string( &arg() ) ) ),
+        "number" => value_from_f64( f64_from_value( arg() )? ),
+        "bool" => Ok( Value::Bool( value_is_truthy( &arg() )? ) ),
+        "abs" => value_from_f64( f64_from_value( arg() )?.abs() ),
+        "round" => value_from_f64( f64_from_value( arg() )?.round() ),
+        "floor" => value_from_f64( f64_from_value( arg() )?.floor() ),
+        "ceil" => value_from_f64( f64_from_value( arg() )?.ceil() ),
+        "is_empty" => Ok( Value::Bool( is_value_empty( &arg() )? ) ),
+        "keys" => Ok( object_keys( arg() ) ),
+        "values" => Ok( object_values( arg() ) ),
+        "type_of" => Ok( Value::String(
+            match arg()
+            {
+                Value::Null => "null",
+                Value::Bool( _ ) => "bool",
+                Value::Number( _ ) => "number",
+                Value::String( _ ) => "string",
+                Value::Array( _ ) => "array",
+                Value::Object( _ ) => "object"
+            }.to_string()
+        ) ),
+        _ => Err( Error::ParseData( format!( "Unknown function {}", name ) ) )
+    }
+}
+
+/// A single array argument is used as the list of arguments.
+fn variadic_args( args : Vec<Value> ) -> Vec<Value>
+{
+    match args.as_slice()
+    {
+        [ Value::Array( a ) ] => a.clone(),
+        _ => args
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use serde_json::json;
+
+    use super::*;
+
+    fn graph() -> Graph
+    {
+        let mut graph = Graph::default();
+
+        graph.input = Some( " hello ".into() );
+        graph.parsed_input = json!( { "items" : [ 1, 2, 3 ], "user" : { "name" : "Ann" } } );
+        graph.context.insert( "count".into(), json!( 4 ) );
+        graph.context.insert( "text".into(), json!( "a,b,c" ) );
+
+        graph
+    }
+
+    fn eval( source : &str ) -> Result<Value, Error>
+    {
+        eval_expr_str( &graph(), source )
+    }
+
+    #[test]
+    fn test_eval_expr_arithmetic()
+    {
+        assert_eq!( eval( "1 + 2 * 3" ).unwrap(), json!( 7 ) );
+        assert_eq!( eval( "(1 + 2) * 3" ).unwrap(), json!( 9 ) );
+        assert_eq!( eval( "context.count / 8" ).unwrap(), json!( 0.5 ) );
+        assert_eq!( eval( "-context.count % 3" ).unwrap(), json!( -1 ) );
+        assert!( eval( "1 / 0" ).is_err() );
+    }
+
+    #[test]
+    fn test_eval_expr_paths()
+    {
+        assert_eq!( eval( "parsed_input.items[1]" ).unwrap(), json!( 2 ) );
+        assert_eq!( eval( "parsed_input.items[-1]" ).unwrap(), json!( 3 ) );
+        assert_eq!( eval( "parsed_input['user'].name" ).unwrap(), json!( "Ann" ) );
+        assert_eq!( eval( "parsed_input.missing.name" ).unwrap(), Value::Null );
+        assert_eq!( eval( "input" ).unwrap(), json!( "hello" ) );
+    }
+
+    #[test]
+    fn test_eval_expr_comparison_and_boolean()
+    {
+        assert_eq!( eval( "context.count >= 4 && !(context.count == 5)" ).unwrap(), json!( true ) );
+        assert_eq!( eval( "'abc' < 'abd' || false" ).unwrap(), json!( true ) );
+        assert_eq!( eval( "context.count != '4'" ).unwrap(), json!( false ) );
+        assert_eq!( eval( "null ? 1 : 2" ).unwrap(), json!( 2 ) );
+    }
+
+    #[test]
+    fn test_eval_expr_functions()
+    {
+        assert_eq!( eval( "split(context.text, ',')" ).unwrap(), json!( [ "a", "b", "c" ] ) );
+        assert_eq!( eval( "join(split(context.text, ','), '-')" ).unwrap(), json!( "a-b-c" ) );
+        assert_eq!( eval( "len(parsed_input.items) + len('añb')" ).unwrap(), json!( 6 ) );
+        assert_eq!( eval( "substring('hello', 1, 3)" ).unwrap(), json!( "ell" ) );
+        assert_eq!( eval( "matches(input, '^h.*o$')" ).unwrap(), json!( true ) );
+        assert_eq!( eval( "min(parsed_input.items)" ).unwrap(), json!( 1 ) );
+        assert_eq!( eval( "max(3, 9, 2)" ).unwrap(), json!( 9 ) );
+        assert_eq!( eval( "coalesce(context.missing, 'default')" ).unwrap(), json!( "default" ) );
+        assert_eq!( eval( "'n: ' + number('2.50')" ).unwrap(), json!( "n: 2.5" ) );
+    }
+
+    #[test]
+    fn test_parse_expr_errors()
+    {
+        assert!( parse_expr( "1 +" ).is_err() );
+        assert!( parse_expr( "foo" ).is_err() );
+        assert!( parse_expr( "exec('rm')" ).is_err() );
+        assert!( parse_expr( "'unterminated" ).is_err() );
+        assert!( parse_expr( &"(".repeat( 100 ) ).is_err() );
+        assert!( parse_expr( &format!( "context{}", ".a".repeat( 100 ) ) ).is_err() );
+        assert!( parse_expr( &format!( "context{}", "[0]".repeat( 100 ) ) ).is_err() );
+        assert!( parse_expr( &format!( "{}1{}", "abs(".repeat( 40 ), ")".repeat( 40 ) ) ).is_err() );
+        assert!( parse_expr( &format!( "context{}", ".a".repeat( 10 ) ) ).is_ok() );
+    }
+}
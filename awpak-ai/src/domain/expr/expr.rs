@@ -0,0 +1,57 @@
+use serde_json::Value;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr
+{
+    Literal( Value ),
+    Array( Vec<Expr> ),
+    Var( ExprVar ),
+    Member( Box<Expr>, String ),
+    Index( Box<Expr>, Box<Expr> ),
+    Unary( UnaryOp, Box<Expr> ),
+    Binary( BinaryOp, Box<Expr>, Box<Expr> ),
+    Cond { cond : Box<Expr>, then : Box<Expr>, otherwise : Box<Expr> },
+    Call( String, Vec<Expr> )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExprVar
+{
+    Context,
+    Input,
+    ParsedInput
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp
+{
+    Not,
+    Neg
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp
+{
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+
+    And,
+    Or
+}
+
+pub const EXPR_FUNCTIONS : [ &str; 27 ] = [
+    "len", "lower", "upper", "trim", "contains", "starts_with", "ends_with", "replace", 
+    "split", "join", "substring", "matches", "string", "number", "abs", "round", "floor", 
+    "ceil", "min", "max", "is_empty", "coalesce", "keys", "values", "bool", "type_of", "history"
+];
@@ -0,0 +1,3 @@
+pub mod expr;
+pub mod parse_expr;
+pub mod eval_expr;
\ No newline at end of file
@@ -0,0 +1,373 @@
+use serde_json::{Number, Value};
+
+use crate::domain::{error::Error, expr::expr::{BinaryOp, Expr, ExprVar, UnaryOp, EXPR_FUNCTIONS}};
+
+
+const MAX_DEPTH : usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token
+{
+    Number( Number ),
+    Str( String ),
+    Ident( String ),
+    Op( &'static str )
+}
+
+const OPERATORS : [ &str; 22 ] = [
+    "==", "!=", "<=", ">=", "&&", "||",
+    "+", "-", "*", "/", "%", "<", ">", "!", "(", ")", "[", "]", ",", ".", "?", ":"
+];
+
+pub fn parse_expr( source : &str ) -> Result<Expr, Error>
+{
+    let tokens = tokenize( source ).map_err( | e | e.prepend_str( format!( "Invalid expression: {}\n", source ) ) )?;
+
+    let mut parser = ExprParser { tokens, pos : 0, depth : 0 };
+
+    let expr = parser.expr().map_err( | e | e.prepend_str( format!( "Invalid expression: {}\n", source ) ) )?;
+
+    match parser.peek()
+    {
+        None => Ok( expr ),
+        Some( t ) => Err( Error::ParseData( format!( "Invalid expression: {}\nUnexpected token {:?}", source, t ) ) )
+    }
+}
+
+fn tokenize( source : &str ) -> Result<Vec<Token>, Error>
+{
+    let chars = source.chars().collect::<Vec<_>>();
+
+    let mut tokens = vec![];
+
+    let mut i = 0;
+
+    while i < chars.len()
+    {
+        let c = chars[ i ];
+
+        if c.is_whitespace() { i += 1; continue; }
+
+        if c.is_ascii_digit()
+        {
+            let start = i;
+
+            while i < chars.len() && ( chars[ i ].is_ascii_digit() || chars[ i ] == '.' )
+            {
+                if chars[ i ] == '.' && ! chars.get( i + 1 ).is_some_and( | c | c.is_ascii_digit() ) { break }
+
+                i += 1;
+            }
+
+            let str = chars[ start..i ].iter().collect::<String>();
+
+            let number = serde_json::from_str::<Number>( &str ).map_err( | e | Error::ParseData( format!( "Invalid number {}. {}", str, e ) ) )?;
+
+            tokens.push( Token::Number( number ) );
+
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_'
+        {
+            let start = i;
+
+            while i < chars.len() && ( chars[ i ].is_alphanumeric() || chars[ i ] == '_' ) { i += 1; }
+
+            tokens.push( Token::Ident( chars[ start..i ].iter().collect() ) );
+
+            continue;
+        }
+
+        if c == '"' || c == '\''
+        {
+            let ( str, next ) = string_literal( &chars, i )?;
+
+            tokens.push( Token::Str( str ) );
+
+            i = next;
+
+            continue;
+        }
+
+        let op = OPERATORS.iter().find( | o | o.chars().enumerate().all( | ( j, oc ) | chars.get( i + j ) == Some( &oc ) ) );
+
+        match op
+        {
+            Some( o ) =>
+            {
+                tokens.push( Token::Op( o ) );
+
+                i += o.len();
+            },
+            None => return Err( Error::ParseData( format!( "Unexpected character {} at position {}", c, i ) ) )
+        }
+    }
+
+    Ok( tokens )
+}
+
+fn string_literal( chars : &[char], start : usize ) -> Result<( String, usize ), Error>
+{
+    let quote = chars[ start ];
+
+    let mut ret = String::new();
+
+    let mut i = start + 1;
+
+    while i < chars.len()
+    {
+        match chars[ i ]
+        {
+            c if c == quote => return Ok( ( ret, i + 1 ) ),
+            '\\' =>
+            {
+                let escaped = chars.get( i + 1 ).ok_or( Error::ParseData( "Unterminated string".into() ) )?;
+
+                ret.push( match escaped { 'n' => '\n', 't' => '\t', 'r' => '\r', c => *c } );
+
+                i += 2;
+            },
+            c =>
+            {
+                ret.push( c );
+
+                i += 1;
+            }
+        }
+    }
+
+    Err( Error::ParseData( format!( "Unterminated string at position {}", start ) ) )
+}
+
+struct ExprParser
+{
+    tokens : Vec<Token>,
+    pos : usize,
+    depth : usize
+}
+
+impl ExprParser
+{
+    fn peek( &self ) -> Option<&Token>
+    {
+        self.tokens.get( self.pos )
+    }
+
+    fn next( &mut self ) -> Option<Token>
+    {
+        let token = self.tokens.get( self.pos ).cloned();
+
+        self.pos += 1;
+
+        token
+    }
+
+    fn eat( &mut self, op : &str ) -> bool
+    {
+        if matches!( self.peek(), Some( Token::Op( o ) ) if *o == op )
+        {
+            self.pos += 1;
+
+            return true
+        }
+
+        false
+    }
+
+    fn expect( &mut self, op : &str ) -> Result<(), Error>
+    {
+        if self.eat( op ) { return Ok( () ) }
+
+        Err( Error::ParseData( format!( "Expected {} found {:?}", op, self.peek() ) ) )
+    }
+
+    fn enter( &mut self ) -> Result<(), Error>
+    {
+        self.depth += 1;
+
+        if self.depth > MAX_DEPTH { return Err( Error::ParseData( format!( "Max depth {} exceeded", MAX_DEPTH ) ) ) }
+
+        Ok( () )
+    }
+
+    fn expr( &mut self ) -> Result<Expr, Error>
+    {
+        self.enter()?;
+
+        let cond = self.binary( 0 )?;
+
+        let ret = if self.eat( "?" )
+        {
+            let then = self.expr()?;
+
+            self.expect( ":" )?;
+
+            let otherwise = self.expr()?;
+
+            Expr::Cond { cond : Box::new( cond ), then : Box::new( then ), otherwise : Box::new( otherwise ) }
+        }
+        else
+        {
+            cond
+        };
+
+        self.depth -= 1;
+
+        Ok( ret )
+    }
+
+    fn binary( &mut self, level : usize ) -> Result<Expr, Error>
+    {
+        if level >= BINARY_LEVELS.len() { return self.unary() }
+
+        let mut left = self.binary( level + 1 )?;
+
+        'outer: loop
+        {
+            for ( op, binary_op ) in BINARY_LEVELS[ level ]
+            {
+                if self.eat( op )
+                {
+                    let right = self.binary( level + 1 )?;
+
+                    left = Expr::Binary( *binary_op, Box::new( left ), Box::new( right ) );
+
+                    continue 'outer;
+                }
+            }
+
+            return Ok( left )
+        }
+    }
+
+    fn unary( &mut self ) -> Result<Expr, Error>
+    {
+        let op = if self.eat( "!" ) { Some( UnaryOp::Not ) } else if self.eat( "-" ) { Some( UnaryOp::Neg ) } else { None };
+
+        match op
+        {
+            Some( op ) =>
+            {
+                self.enter()?;
+
+                let expr = self.unary()?;
+
+                self.depth -= 1;
+
+                Ok( Expr::Unary( op, Box::new( expr ) ) )
+            },
+            None => self.postfix()
+        }
+    }
+
+    /// Each field and index nests the expression, so it counts as a level of depth
+    fn postfix( &mut self ) -> Result<Expr, Error>
+    {
+        let depth = self.depth;
+
+        let mut expr = self.primary()?;
+
+        loop
+        {
+            if self.eat( "." )
+            {
+                self.enter()?;
+
+                match self.next()
+                {
+                    Some( Token::Ident( i ) ) => expr = Expr::Member( Box::new( expr ), i ),
+                    t => return Err( Error::ParseData( format!( "Expected field name after . found {:?}", t ) ) )
+                }
+            }
+            else if self.eat( "[" )
+            {
+                self.enter()?;
+
+                let index = self.expr()?;
+
+                self.expect( "]" )?;
+
+                expr = Expr::Index( Box::new( expr ), Box::new( index ) );
+            }
+            else
+            {
+                self.depth = depth;
+
+                return Ok( expr )
+            }
+        }
+    }
+
+    fn primary( &mut self ) -> Result<Expr, Error>
+    {
+        match self.next()
+        {
+            Some( Token::Number( n ) ) => Ok( Expr::Literal( Value::Number( n ) ) ),
+            Some( Token::Str( s ) ) => Ok( Expr::Literal( Value::String( s ) ) ),
+            Some( Token::Op( "(" ) ) =>
+            {
+                let expr = self.expr()?;
+
+                self.expect( ")" )?;
+
+                Ok( expr )
+            },
+            Some( Token::Op( "[" ) ) => Ok( Expr::Array( self.list( "]" )? ) ),
+            Some( Token::Ident( i ) ) => self.ident( i ),
+            t => Err( Error::ParseData( format!( "Unexpected token {:?}", t ) ) )
+        }
+    }
+
+    fn ident( &mut self, ident : String ) -> Result<Expr, Error>
+    {
+        if self.eat( "(" )
+        {
+            if ! EXPR_FUNCTIONS.contains( &ident.as_str() ) { return Err( Error::ParseData( format!( "Unknown function {}", ident ) ) ) }
+
+            self.enter()?;
+
+            let args = self.list( ")" )?;
+
+            self.depth -= 1;
+
+            return Ok( Expr::Call( ident, args ) )
+        }
+
+        match ident.as_str()
+        {
+            "true" => Ok( Expr::Literal( Value::Bool( true ) ) ),
+            "false" => Ok( Expr::Literal( Value::Bool( false ) ) ),
+            "null" => Ok( Expr::Literal( Value::Null ) ),
+            "context" => Ok( Expr::Var( ExprVar::Context ) ),
+            "input" => Ok( Expr::Var( ExprVar::Input ) ),
+            "parsed_input" => Ok( Expr::Var( ExprVar::ParsedInput ) ),
+            _ => Err( Error::ParseData( format!( "Unknown identifier {}", ident ) ) )
+        }
+    }
+
+    fn list( &mut self, end : &str ) -> Result<Vec<Expr>, Error>
+    {
+        let mut ret = vec![];
+
+        if self.eat( end ) { return Ok( ret ) }
+
+        loop
+        {
+            ret.push( self.expr()? );
+
+            if self.eat( end ) { return Ok( ret ) }
+
+            self.expect( "," )?;
+        }
+    }
+}
+
+const BINARY_LEVELS : [ &[ ( &str, BinaryOp ) ]; 6 ] = [
+    &[ ( "||", BinaryOp::Or ) ],
+    &[ ( "&&", BinaryOp::And ) ],
+    &[ ( "==", BinaryOp::Eq ), ( "!=", BinaryOp::NotEq ) ],
+    &[ ( "<=", BinaryOp::Lte ), ( ">=", BinaryOp::Gte ), ( "<", BinaryOp::Lt ), ( ">", BinaryOp::Gt ) ],
+    &[ ( "+", BinaryOp::Add ), ( "-", BinaryOp::Sub ) ],
+    &[ ( "*", BinaryOp::Mul ), ( "/", BinaryOp::Div ), ( "%", BinaryOp::Rem ) ]
+];
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{data::data::DataFrom, graph::graph_node::GraphNode, parallel::parallel::ParallelExecutor};
+
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForEach
+{
+    pub from : DataFrom,
+    pub executor : Box<ParallelExecutor>,
+
+    #[serde(default = "for_each_default_item")]
+    pub item : String,
+    #[serde(default = "for_each_default_index")]
+    pub index : String,
+
+    #[serde(default)]
+    pub concurrency : Option<usize>
+}
+
+fn for_each_default_item() -> String
+{
+    "item".into()
+}
+
+fn for_each_default_index() -> String
+{
+    "index".into()
+}
+
+/// ForEach executor with the sub-graph already built.
+#[derive(Clone)]
+pub struct ForEachNode
+{
+    pub for_each : ForEach,
+    pub graph : Option<GraphNode>
+}
@@ -0,0 +1 @@
+pub mod for_each;
\ No newline at end of file
@@ -1,13 +1,13 @@
-use std::{collections::HashMap, fs::File, io::BufReader};
+use std::{collections::HashMap, fs::File, io::BufReader, sync::Arc, time::Duration};
 
 use async_recursion::async_recursion;
 use awpak_utils::file_utils::path_for_file;
 
-use crate::domain::{error::Error, graph::{build_graph_node::graph_node_executor_from_config, graph::{Graph, GraphConfig}, node::{Node, NodeConfig, NodeExecutor, NodeExecutorConfig}}, store::{store::{Store, StoreConfig}, store_from_config::store_from_config}};
+use crate::domain::{cassette::cassette::Cassette, checkpoint::checkpoint::DirCheckpointStore, error::Error, graph::{build_graph_node::{agent_node_executor_from_config, for_each_node_executor_from_config, graph_node_executor_from_config, parallel_node_executor_from_config}, graph::{Graph, GraphConfig}, node::{Node, NodeConfig, NodeExecutor, NodeExecutorConfig}, validate_graph::check_graph}, store::{store::{Store, StoreConfig}, store_from_config::store_from_config}};
 
 pub async fn build_graph_from_str( str : impl AsRef<str> ) -> Result<Graph, Error>
 {
-    let config : GraphConfig = serde_json::from_str( str.as_ref() ).map_err( | e | Error::ParseData( e.to_string() ) )?;
+    let config = graph_config_from_str( str )?;
 
     build_graph( config ).await
 }
@@ -15,33 +15,61 @@ pub async fn build_graph_from_str( str : impl AsRef<str> ) -> Result<Graph, Erro
 #[async_recursion]
 pub async fn build_graph_from_path( path : &str ) -> Result<Graph, Error>
 {
-    let path = path_for_file( path ).map_err( | e | Error::File( e.to_string() ) )?;
+    let config = graph_config_from_path( path )?;
 
-    let file = File::open( path ).map_err( | e | Error::File( e.to_string() ) )?;
-    let reader = BufReader::new( file );
+    build_graph( config ).await
+}
+
+pub fn graph_config_from_str( str : impl AsRef<str> ) -> Result<GraphConfig, Error>
+{
+    serde_json::from_str( str.as_ref() ).map_err( | e | Error::ParseData( e.to_string() ).with_source( e ) )
+}
 
-    let config : GraphConfig = serde_json::from_reader(reader ).map_err( | e | Error::File( e.to_string() ) )?;
+pub fn graph_config_from_path( path : &str ) -> Result<GraphConfig, Error>
+{
+    let path = path_for_file( path ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )?;
 
-    build_graph( config ).await
+    let file = File::open( path ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )?;
+    let reader = BufReader::new( file );
+
+    serde_json::from_reader(reader ).map_err( | e | Error::File( e.to_string() ).with_source( e ) )
 }
 
 pub async fn build_graph(
     config : GraphConfig
 ) -> Result<Graph, Error>
 {
+    if config.strict { check_graph( &config )?; }
+
     let ( first, nodes ) = build_nodes( config.first, config.nodes ).await?;
 
     let stores = init_stores( config.stores ).await?;
-    Ok(
-        Graph::new(
-            stores,
-            config.input_type,
-            config.context,
-            first,
-            nodes,
-            config.preserve_context
-        )
-    )
+
+    let mut graph = Graph::new(
+        stores,
+        config.input_type,
+        config.context,
+        first,
+        nodes,
+        config.preserve_context
+    );
+
+    graph.max_steps = config.max_steps;
+    graph.max_duration = config.max_duration.map( Duration::from_millis );
+    graph.max_tokens_budget = config.max_tokens_budget;
+    graph.input_schema = config.input_schema;
+
+    if let Some( d ) = config.checkpoint_dir
+    {
+        graph.checkpoint = Some( Arc::new( DirCheckpointStore::new( d ) ) );
+    }
+
+    if let Some( c ) = &config.cassette
+    {
+        graph.cassette = Some( Arc::new( Cassette::from_config( c )? ) );
+    }
+
+    Ok( graph )
 }
 
 async fn init_stores( config : Vec<StoreConfig> ) -> Result<HashMap<String, Store>, Error>
@@ -66,7 +94,11 @@ async fn node_from_config( config : NodeConfig ) -> Result<Node, Error>
             id : config.id,
             executor,
             destination : config.destination,
-            output : config.output
+            output : config.output,
+            max_visits : config.max_visits,
+            retry : config.retry,
+            on_error : config.on_error,
+            fan_out : config.fan_out
         }
     )
 }
@@ -75,13 +107,16 @@ async fn node_executor_from_config( config : NodeExecutorConfig ) -> Result<Node
 {
     match config
     {
-        NodeExecutorConfig::Agent( a ) => Ok( NodeExecutor::Agent( a ) ),
+        NodeExecutorConfig::Agent( a ) => agent_node_executor_from_config( a ).await,
         NodeExecutorConfig::AgentHistoryMut( a ) => Ok( NodeExecutor::AgentHistoryMut( a ) ),
         NodeExecutorConfig::Command( c ) => Ok( NodeExecutor::Command( c ) ),
         NodeExecutorConfig::ContextMut( c ) => Ok( NodeExecutor::ContextMut( c ) ),
         NodeExecutorConfig::Graph( g ) => graph_node_executor_from_config( g ).await,
-        NodeExecutorConfig::Parallel( p ) => Ok( NodeExecutor::Parallel( p ) ),
-        NodeExecutorConfig::WebClient( w ) => Ok( NodeExecutor::WebClient( w ) )
+        NodeExecutorConfig::Parallel( p ) => parallel_node_executor_from_config( p ).await,
+        NodeExecutorConfig::WebClient( w ) => Ok( NodeExecutor::WebClient( w ) ),
+        NodeExecutorConfig::Join( j ) => Ok( NodeExecutor::Join( j ) ),
+        NodeExecutorConfig::ForEach( f ) => for_each_node_executor_from_config( f ).await,
+        NodeExecutorConfig::HumanInput( h ) => Ok( NodeExecutor::HumanInput( h ) )
     }
 }
 
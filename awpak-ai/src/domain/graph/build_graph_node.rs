@@ -1,20 +1,97 @@
-use crate::domain::{error::Error, graph::{build_graph::build_graph_from_path, graph_node::GraphNode, node::{GraphNodeConfig, NodeExecutor}}};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::domain::{agent::{agent::AIAgent, agent_tool::{AgentToolExecutor, AgentToolGraph}}, error::Error, for_each::for_each::{ForEach, ForEachNode}, graph::{build_graph::build_graph_from_path, graph_node::GraphNode, node::{GraphNodeConfig, NodeExecutor}}, parallel::parallel::{Parallel, ParallelExecutor, ParallelNode}};
 
 
 pub async fn graph_node_executor_from_config( 
     config : GraphNodeConfig
 ) -> Result<NodeExecutor, Error>
+{
+    Ok( NodeExecutor::Graph( graph_node_from_config( config ).await? ) )
+}
+
+pub async fn agent_node_executor_from_config(
+    agent : AIAgent
+) -> Result<NodeExecutor, Error>
+{
+    Ok( NodeExecutor::Agent( agent_from_config( agent ).await? ) )
+}
+
+pub async fn parallel_node_executor_from_config(
+    mut parallel : Parallel
+) -> Result<NodeExecutor, Error>
+{
+    let mut graphs = HashMap::new();
+
+    for ( idx, executor ) in parallel.executors.iter_mut().enumerate()
+    {
+        match executor
+        {
+            ParallelExecutor::Graph { ty : _, executor, condition : _ } =>
+            {
+                graphs.insert( idx, graph_node_from_config( executor.clone() ).await? );
+            },
+            ParallelExecutor::Agent { ty : _, executor, condition : _ } =>
+            {
+                *executor = agent_from_config( std::mem::take( executor ) ).await?;
+            },
+            _ => {}
+        }
+    }
+
+    Ok( NodeExecutor::Parallel( ParallelNode { parallel, graphs } ) )
+}
+
+pub async fn for_each_node_executor_from_config(
+    mut for_each : ForEach
+) -> Result<NodeExecutor, Error>
+{
+    let graph = match for_each.executor.as_mut()
+    {
+        ParallelExecutor::Graph { ty : _, executor, condition : _ } => Some( graph_node_from_config( executor.clone() ).await? ),
+        ParallelExecutor::Agent { ty : _, executor, condition : _ } =>
+        {
+            *executor = agent_from_config( std::mem::take( executor ) ).await?;
+
+            None
+        },
+        _ => None
+    };
+
+    Ok( NodeExecutor::ForEach( ForEachNode { for_each, graph } ) )
+}
+
+async fn graph_node_from_config(
+    config : GraphNodeConfig
+) -> Result<GraphNode, Error>
 {
     let graph = build_graph_from_path( &config.path ).await?;
 
     Ok(
-        NodeExecutor::Graph(
-            GraphNode
-            {
-                graph,
-                input : config.input,
-                output : config.output
-            }
-        )
+        GraphNode
+        {
+            graph,
+            input : config.input,
+            output : config.output
+        }
     )
-}
\ No newline at end of file
+}
+
+/// Builds the sub-graphs of the `Graph` tools of the agent
+pub async fn agent_from_config(
+    mut agent : AIAgent
+) -> Result<AIAgent, Error>
+{
+    for tool in agent.tools.iter_mut()
+    {
+        if let AgentToolExecutor::Graph( c ) = &tool.executor
+        {
+            let graph = graph_node_from_config( c.clone() ).await
+            .map_err( | e | e.prepend_str( format!( "Tool {}.\n", tool.name ) ) )?;
+
+            tool.graph = Some( AgentToolGraph( Arc::new( graph ) ) );
+        }
+    }
+
+    Ok( agent )
+}
@@ -0,0 +1,50 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DiagnosticLevel
+{
+    Error,
+    Warning
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Diagnostic
+{
+    pub level : DiagnosticLevel,
+    #[serde(default)]
+    pub node : Option<String>,
+    pub message : String
+}
+
+impl Diagnostic
+{
+    pub fn error( node : Option<&str>, message : impl Into<String> ) -> Self
+    {
+        Self { level : DiagnosticLevel::Error, node : node.map( | n | n.to_string() ), message : message.into() }
+    }
+
+    pub fn warning( node : Option<&str>, message : impl Into<String> ) -> Self
+    {
+        Self { level : DiagnosticLevel::Warning, node : node.map( | n | n.to_string() ), message : message.into() }
+    }
+
+    pub fn is_error( &self ) -> bool
+    {
+        self.level == DiagnosticLevel::Error
+    }
+}
+
+impl Display for Diagnostic
+{
+    fn fmt( &self, f : &mut std::fmt::Formatter<'_> ) -> std::fmt::Result
+    {
+        match &self.node
+        {
+            Some( n ) => write!( f, "{:?}. Node {}: {}", self.level, n, self.message ),
+            None => write!( f, "{:?}: {}", self.level, self.message )
+        }
+    }
+}
@@ -1,9 +1,9 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::domain::{data::data::DataType, graph::node::{Node, NodeConfig}, store::store::{Store, StoreConfig}};
+use crate::domain::{agent::agent_mock::MockCalls, cassette::cassette::{Cassette, CassetteConfig}, checkpoint::checkpoint::CheckpointStore, data::data::DataType, graph::node::{Node, NodeConfig}, human_input::human_input::HumanInputHandler, mcp::mcp_pool::MCPPool, store::store::{Store, StoreConfig}, usage::usage::UsageCounter};
 
 #[derive(Default, Clone)]
 pub struct Graph
@@ -23,6 +23,30 @@ pub struct Graph
     
     pub final_output : Option<Result<String, String>>,
 
+    pub checkpoint : Option<Arc<dyn CheckpointStore>>,
+
+    pub max_steps : Option<usize>,
+    pub max_duration : Option<Duration>,
+
+    pub input_schema : Option<Value>,
+
+    pub mcp_pool : Arc<MCPPool>,
+
+    /// Answers the `HumanInput` nodes. Sub-graphs without handler use the one of the parent graph
+    pub human_input : Option<Arc<dyn HumanInputHandler>>,
+
+    pub usage : Arc<UsageCounter>,
+    pub max_tokens_budget : Option<u64>,
+
+    /// Node being executed. Agent token usage is added to it
+    pub current_node : Option<String>,
+
+    /// Records or replays agent, web client and command calls. Sub-graphs without cassette use the one of the parent graph
+    pub cassette : Option<Arc<Cassette>>,
+
+    /// Call index of each `Mock` provider
+    pub mock_calls : Arc<MockCalls>,
+
     __clean_context : bool,
     __initial_context : HashMap<String, Value>
 }
@@ -64,6 +88,26 @@ impl Graph
 
             final_output : None, 
 
+            checkpoint : None,
+
+            max_steps : None,
+            max_duration : None,
+
+            input_schema : None,
+
+            mcp_pool : Arc::default(),
+
+            human_input : None,
+
+            usage : Arc::default(),
+            max_tokens_budget : None,
+
+            current_node : None,
+
+            cassette : None,
+
+            mock_calls : Arc::default(),
+
             __clean_context: ! preserve_context, 
             __initial_context : initial_context
         }
@@ -95,5 +139,23 @@ pub struct GraphConfig
     #[serde(default)]
     pub input_type : Option<DataType>,
     #[serde(default)]
-    pub preserve_context : bool
+    pub preserve_context : bool,
+    #[serde(default)]
+    pub strict : bool,
+    #[serde(default)]
+    pub checkpoint_dir : Option<String>,
+    #[serde(default)]
+    pub max_steps : Option<usize>,
+    /// Milliseconds
+    #[serde(default)]
+    pub max_duration : Option<u64>,
+    /// JSON Schema for the input. Checked against the parsed input, or against the input string if there is no `input_type`
+    #[serde(default)]
+    pub input_schema : Option<Value>,
+    /// Max total tokens of the agents in a run, sub-graphs included
+    #[serde(default)]
+    pub max_tokens_budget : Option<u64>,
+    /// File where agent, web client and command calls are recorded or replayed
+    #[serde(default)]
+    pub cassette : Option<CassetteConfig>
 }
\ No newline at end of file
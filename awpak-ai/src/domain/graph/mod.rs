@@ -2,4 +2,6 @@ pub mod graph;
 pub mod node;
 pub mod build_graph;
 pub mod build_graph_node;
-pub mod graph_node;
\ No newline at end of file
+pub mod graph_node;
+pub mod diagnostic;
+pub mod validate_graph;
\ No newline at end of file
@@ -1,7 +1,9 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::domain::{agent::agent::AIAgent, agent_history_mut::agent_history_mut::AgentHistoryMut, command::command::Command, context_mut::context_mut::ContextMut, data::data::{DataComparator, DataFrom, DataToContext, DataToString}, graph::graph_node::{GraphNode, GraphNodeOutput}, parallel::parallel::Parallel, web_client::web_client::WebClient};
+use crate::domain::{agent::agent::AIAgent, agent_history_mut::agent_history_mut::AgentHistoryMut, command::command::Command, error::Error, context_mut::context_mut::ContextMut, data::data::{DataComparator, DataFrom, DataToContext, DataToString}, for_each::for_each::{ForEach, ForEachNode}, graph::graph_node::{GraphNode, GraphNodeOutput}, human_input::human_input::HumanInput, join::join::Join, parallel::parallel::{Parallel, ParallelNode}, web_client::web_client::WebClient};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NodeConfig
@@ -11,7 +13,15 @@ pub struct NodeConfig
     #[serde(default)]
     pub output : Option<DataToContext>,
     #[serde(default)]
-    pub destination : Vec<NodeDestination>
+    pub destination : Vec<NodeDestination>,
+    #[serde(default)]
+    pub max_visits : Option<usize>,
+    #[serde(default)]
+    pub retry : Option<NodeRetry>,
+    #[serde(default)]
+    pub on_error : Option<NodeOnError>,
+    #[serde(default)]
+    pub fan_out : bool
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -23,7 +33,10 @@ pub enum NodeExecutorConfig
     ContextMut( Vec<ContextMut> ),
     WebClient( WebClient ),
     AgentHistoryMut( Vec<AgentHistoryMut> ),
-    Parallel( Parallel )
+    Parallel( Parallel ),
+    Join( Join ),
+    ForEach( ForEach ),
+    HumanInput( HumanInput )
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,7 +56,11 @@ pub struct Node
     pub id : String,
     pub executor : NodeExecutor,
     pub output : Option<DataToContext>,
-    pub destination : Vec<NodeDestination>
+    pub destination : Vec<NodeDestination>,
+    pub max_visits : Option<usize>,
+    pub retry : Option<NodeRetry>,
+    pub on_error : Option<NodeOnError>,
+    pub fan_out : bool
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -53,6 +70,75 @@ pub struct NodeDestination
     pub condition : DataComparator
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NodeRetry
+{
+    pub attempts : usize,
+    #[serde(default)]
+    pub backoff : RetryBackoff,
+    #[serde(default)]
+    pub errors : Vec<String>,
+    #[serde(default)]
+    pub condition : Option<DataComparator>
+}
+
+impl NodeRetry
+{
+    pub fn retry_on( &self, err : &Error, attempt : usize ) -> bool
+    {
+        attempt < self.attempts &&
+        ( self.errors.is_empty() || self.errors.iter().any( | e | e == err.kind() ) )
+    }
+
+    pub fn delay( &self, attempt : usize ) -> Duration
+    {
+        let millis = match &self.backoff
+        {
+            RetryBackoff::Fixed( m ) => *m,
+            RetryBackoff::Exponential { initial, max } =>
+            {
+                let exp = initial.saturating_mul( 2u64.saturating_pow( attempt.saturating_sub( 1 ) as u32 ) );
+
+                match max
+                {
+                    Some( m ) => exp.min( *m ),
+                    None => exp
+                }
+            }
+        };
+
+        Duration::from_millis( millis )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum RetryBackoff
+{
+    Fixed( u64 ),
+    Exponential
+    {
+        initial : u64,
+        #[serde(default)]
+        max : Option<u64>
+    }
+}
+
+impl Default for RetryBackoff
+{
+    fn default() -> Self
+    {
+        RetryBackoff::Fixed( 0 )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NodeOnError
+{
+    pub next : NodeNext,
+    #[serde(default)]
+    pub output : Option<DataToContext>
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum NodeNext
 {
@@ -70,11 +156,31 @@ pub enum NodeExecutor
     ContextMut( Vec<ContextMut> ),
     WebClient( WebClient ),
     AgentHistoryMut( Vec<AgentHistoryMut> ),
-    Parallel( Parallel )
+    Parallel( ParallelNode ),
+    Join( Join ),
+    ForEach( ForEachNode ),
+    HumanInput( HumanInput )
 }
 
 impl NodeExecutor
 {
+    pub fn kind( &self ) -> &'static str
+    {
+        match self
+        {
+            NodeExecutor::Agent( _ ) => "Agent",
+            NodeExecutor::Command( _ ) => "Command",
+            NodeExecutor::Graph( _ ) => "Graph",
+            NodeExecutor::ContextMut( _ ) => "ContextMut",
+            NodeExecutor::WebClient( _ ) => "WebClient",
+            NodeExecutor::AgentHistoryMut( _ ) => "AgentHistoryMut",
+            NodeExecutor::Parallel( _ ) => "Parallel",
+            NodeExecutor::Join( _ ) => "Join",
+            NodeExecutor::ForEach( _ ) => "ForEach",
+            NodeExecutor::HumanInput( _ ) => "HumanInput"
+        }
+    }
+
     pub fn context_mut( &self ) -> Option<&Vec<ContextMut>>
     {
         match self
@@ -104,6 +210,24 @@ impl NodeExecutor
         }
     }
 
+    pub fn own_parallel( self ) -> ( Self, Option<ParallelNode> )
+    {
+        match self
+        {
+            NodeExecutor::Parallel( p ) => ( NodeExecutor::ContextMut( vec![] ), Some( p ) ),
+            _ => ( self, None )
+        }
+    }
+
+    pub fn own_for_each( self ) -> ( Self, Option<ForEachNode> )
+    {
+        match self
+        {
+            NodeExecutor::ForEach( f ) => ( NodeExecutor::ContextMut( vec![] ), Some( f ) ),
+            _ => ( self, None )
+        }
+    }
+
     pub fn agent( &self ) -> Option<&AIAgent>
     {
         match self
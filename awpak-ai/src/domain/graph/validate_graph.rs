@@ -0,0 +1,713 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::domain::{agent::{agent::{AIAgent, AIAgentFallback, AIAgentProviderConfig, OllamaConfig}, agent_tool::AgentToolExecutor}, command::command::Command, data::{data::{DataComparator, DataFrom, DataOperation, DataToContext, DataToString}, data_schema::check_schema}, error::{Error, ERROR_KINDS}, expr::parse_expr::parse_expr, mcp::mcp::{MCPTransport, NodeMCPServer}, graph::{diagnostic::Diagnostic, graph::GraphConfig, node::{NodeConfig, NodeExecutorConfig, NodeNext}}, parallel::parallel::ParallelExecutor, template::parse_template::parse_template, web_client::web_client::{WebClient, WebClientBody}};
+
+
+pub fn validate_graph( config : &GraphConfig ) -> Vec<Diagnostic>
+{
+    let nodes = std::iter::once( &config.first ).chain( config.nodes.iter() ).collect::<Vec<_>>();
+
+    let mut refs = GraphRefs::default();
+
+    nodes.iter().for_each( | n | collect_node( &mut refs, n ) );
+
+    let mut ret = vec![];
+
+    ret.append( &mut check_duplicated_nodes( &nodes ) );
+    ret.append( &mut check_destinations( &nodes ) );
+    ret.append( &mut check_retry( &nodes ) );
+    ret.append( &mut check_unreachable_nodes( &config.first.id, &nodes ) );
+    ret.append( &mut check_agent_references( &nodes, &refs ) );
+    ret.append( &mut check_fallbacks( &refs ) );
+    ret.append( &mut check_mcp_servers( &refs ) );
+    ret.append( &mut check_store_references( config, &refs ) );
+    ret.append( &mut check_regex( &refs ) );
+    ret.append( &mut check_expressions( &refs ) );
+    ret.append( &mut check_schemas( config, &refs ) );
+    ret.append( &mut check_context_paths( config, &refs ) );
+
+    ret
+}
+
+pub fn check_graph( config : &GraphConfig ) -> Result<(), Error>
+{
+    let errors = validate_graph( config ).into_iter()
+    .filter( | d | d.is_error() )
+    .map( | d | d.to_string() )
+    .collect::<Vec<_>>();
+
+    if errors.is_empty() { return Ok( () ) }
+
+    Err( Error::Validation( errors.join( "\n" ) ) )
+}
+
+#[derive(Default)]
+struct GraphRefs<'a>
+{
+    from : Vec<( &'a str, &'a DataFrom )>,
+    comparators : Vec<( &'a str, &'a DataComparator )>,
+    to_context : Vec<( &'a str, &'a DataToContext )>,
+    agent_history : Vec<( &'a str, &'a str )>,
+    agent_schemas : Vec<( &'a str, &'a Value )>,
+    fallbacks : Vec<( &'a str, &'a AIAgentFallback )>,
+    parallel_agents : Vec<( &'a str, &'a AIAgent )>,
+    mcp_servers : Vec<( &'a str, &'a NodeMCPServer )>,
+    mock_patterns : Vec<( &'a str, &'a String )>,
+    written : Vec<&'a str>
+}
+
+fn check_duplicated_nodes( nodes : &[&NodeConfig] ) -> Vec<Diagnostic>
+{
+    let mut ids = HashSet::new();
+
+    nodes.iter()
+    .filter( | n | ! ids.insert( n.id.as_str() ) )
+    .map( | n | Diagnostic::error( Some( &n.id ), "Duplicated node id" ) )
+    .collect()
+}
+
+fn check_destinations( nodes : &[&NodeConfig] ) -> Vec<Diagnostic>
+{
+    let ids = nodes.iter().map( | n | n.id.as_str() ).collect::<HashSet<_>>();
+
+    let mut ret = vec![];
+
+    for node in nodes
+    {
+        if node.destination.is_empty()
+        {
+            ret.push( Diagnostic::error( Some( &node.id ), "Node has no destinations and no exit" ) );
+        }
+
+        for ( idx, d ) in node.destination.iter().enumerate()
+        {
+            if let NodeNext::Node( n ) = &d.next && ! ids.contains( n.as_str() )
+            {
+                ret.push( Diagnostic::error( Some( &node.id ), format!( "Destination {} points to unknown node {}", idx, n ) ) );
+            }
+        }
+
+        if let Some( o ) = &node.on_error && let NodeNext::Node( n ) = &o.next && ! ids.contains( n.as_str() )
+        {
+            ret.push( Diagnostic::error( Some( &node.id ), format!( "OnError points to unknown node {}", n ) ) );
+        }
+
+        if let NodeExecutorConfig::Join( j ) = &node.executor
+        {
+            for b in j.branches.iter().filter( | b | ! ids.contains( b.as_str() ) )
+            {
+                ret.push( Diagnostic::error( Some( &node.id ), format!( "Join branch {} is not a node", b ) ) );
+            }
+        }
+    }
+
+    ret
+}
+
+fn check_retry( nodes : &[&NodeConfig] ) -> Vec<Diagnostic>
+{
+    let mut ret = vec![];
+
+    for node in nodes
+    {
+        let Some( retry ) = &node.retry else { continue };
+
+        if retry.attempts < 2
+        {
+            ret.push( Diagnostic::warning( Some( &node.id ), format!( "Retry with {} attempts never retries", retry.attempts ) ) );
+        }
+
+        for e in retry.errors.iter().filter( | e | ! ERROR_KINDS.contains( &e.as_str() ) )
+        {
+            ret.push( Diagnostic::error( Some( &node.id ), format!( "Retry error {} is not a known error kind", e ) ) );
+        }
+    }
+
+    ret
+}
+
+fn check_unreachable_nodes( first : &str, nodes : &[&NodeConfig] ) -> Vec<Diagnostic>
+{
+    let by_id = nodes.iter().map( | n | ( n.id.as_str(), *n ) ).collect::<HashMap<_, _>>();
+
+    let mut visited = HashSet::from( [ first ] );
+    let mut pending = VecDeque::from( [ first ] );
+
+    while let Some( id ) = pending.pop_front()
+    {
+        let Some( node ) = by_id.get( id ) else { continue };
+
+        let nexts = node.destination.iter().map( | d | &d.next ).chain( node.on_error.iter().map( | o | &o.next ) );
+
+        for next in nexts
+        {
+            if let NodeNext::Node( n ) = next && visited.insert( n.as_str() )
+            {
+                pending.push_back( n.as_str() );
+            }
+        }
+    }
+
+    nodes.iter()
+    .filter( | n | ! visited.contains( n.id.as_str() ) )
+    .map( | n | Diagnostic::warning( Some( &n.id ), "Node is unreachable from the first node" ) )
+    .collect()
+}
+
+fn check_agent_references( nodes : &[&NodeConfig], refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    let agents = nodes.iter()
+    .filter( | n | matches!( n.executor, NodeExecutorConfig::Agent( _ ) ) )
+    .map( | n | n.id.as_str() )
+    .collect::<HashSet<_>>();
+
+    refs.agent_history.iter()
+    .filter( | ( _, id ) | ! agents.contains( id ) )
+    .map( | ( node, id ) | Diagnostic::error( Some( node ), format!( "Agent history reference {} is not an Agent node", id ) ) )
+    .collect()
+}
+
+fn check_fallbacks( refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    let mut ret = vec![];
+
+    for ( node, fallback ) in &refs.fallbacks
+    {
+        if fallback.providers.is_empty()
+        {
+            ret.push( Diagnostic::error( Some( node ), "Fallback without providers" ) );
+        }
+
+        fallback.providers.iter().enumerate()
+        .filter( | ( _, p ) | matches!( p.provider, AIAgentProviderConfig::Fallback( _ ) ) )
+        .for_each( | ( i, _ ) | ret.push( Diagnostic::error( Some( node ), format!( "Fallback provider {} is a Fallback", i ) ) ) );
+    }
+
+    refs.parallel_agents.iter()
+    .filter( | ( _, a ) | matches!( &a.provider, AIAgentProviderConfig::Fallback( AIAgentFallback { output : Some( _ ), .. } ) ) )
+    .for_each( | ( node, _ ) | ret.push( Diagnostic::error( Some( node ), "Fallback output is not supported in Parallel executors" ) ) );
+
+    ret
+}
+
+fn check_mcp_servers( refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    refs.mcp_servers.iter()
+    .filter( | ( _, s ) | matches!( s.transport, MCPTransport::ChildProcess ) && s.command.trim().is_empty() )
+    .map( | ( node, _ ) | Diagnostic::error( Some( node ), "MCP server with ChildProcess transport and empty command" ) )
+    .collect()
+}
+
+fn check_store_references( config : &GraphConfig, refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    let stores = config.stores.iter().map( | s | s.id.as_str() ).collect::<HashSet<_>>();
+
+    refs.from.iter()
+    .filter_map(
+        | ( node, from ) |
+        {
+            match from
+            {
+                DataFrom::Store( s ) if ! stores.contains( s.id.as_str() ) =>
+                {
+                    Some( Diagnostic::error( Some( node ), format!( "Store {} is not declared", s.id ) ) )
+                },
+                _ => None
+            }
+        }
+    )
+    .collect()
+}
+
+fn check_regex( refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    let comparators = refs.comparators.iter()
+    .filter_map( | ( node, comparator ) | match comparator { DataComparator::Regex { regex, .. } => Some( ( node, regex ) ), _ => None } );
+
+    let operations = refs.from.iter()
+    .filter_map(
+        | ( node, from ) |
+        {
+            match from
+            {
+                DataFrom::Operation( o ) => match o.as_ref()
+                {
+                    DataOperation::Replace { search, regex : true, .. } => Some( ( node, search ) ),
+                    DataOperation::RegexCapture { regex, .. } => Some( ( node, regex ) ),
+                    _ => None
+                },
+                _ => None
+            }
+        }
+    );
+
+    let mock_patterns = refs.mock_patterns.iter().map( | ( node, pattern ) | ( node, *pattern ) );
+
+    comparators.chain( operations ).chain( mock_patterns )
+    .filter_map(
+        | ( node, regex ) |
+        {
+            match Regex::new( regex )
+            {
+                Ok( _ ) => None,
+                Err( e ) => Some( Diagnostic::error( Some( node ), format!( "Invalid regex: {}. Error: {}", regex, e ) ) )
+            }
+        }
+    )
+    .collect()
+}
+
+fn check_expressions( refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    let from = refs.from.iter().filter_map( | ( n, f ) | match f { DataFrom::Expr( e ) => Some( ( n, e ) ), _ => None } );
+
+    let comparators = refs.comparators.iter().filter_map( | ( n, c ) | match c { DataComparator::Expr( e ) => Some( ( n, e ) ), _ => None } );
+
+    let mut ret = from.chain( comparators )
+    .filter_map( | ( node, expr ) | parse_expr( expr ).err().map( | e | Diagnostic::error( Some( node ), e.to_string() ) ) )
+    .collect::<Vec<_>>();
+
+    for ( node, from ) in &refs.from
+    {
+        let DataFrom::Template( t ) = from else { continue };
+
+        let partials = t.partials.iter().map( | ( n, p ) | ( Some( n ), p ) );
+
+        for ( name, template ) in std::iter::once( ( None, &t.template ) ).chain( partials )
+        {
+            if let Err( e ) = parse_template( template )
+            {
+                let e = match name { Some( n ) => e.prepend_str( format!( "Partial {}. ", n ) ), None => e };
+
+                ret.push( Diagnostic::error( Some( node ), e.to_string() ) );
+            }
+        }
+    }
+
+    ret
+}
+
+fn check_schemas( config : &GraphConfig, refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    let input = config.input_schema.as_ref()
+    .and_then( | s | check_schema( s ).err() )
+    .map( | e | Diagnostic::error( None, format!( "Input schema. {}", e ) ) );
+
+    let outputs = refs.to_context.iter()
+    .filter_map( | ( node, to ) | to.schema.as_ref().and_then( | s | check_schema( s ).err() ).map( | e | ( node, to, e ) ) )
+    .map( | ( node, to, e ) | Diagnostic::error( Some( node ), format!( "Output {}. {}", to.path, e ) ) );
+
+    let agents = refs.agent_schemas.iter()
+    .filter_map( | ( node, s ) | check_schema( s ).err().map( | e | Diagnostic::error( Some( node ), format!( "Agent schema. {}", e ) ) ) );
+
+    input.into_iter().chain( outputs ).chain( agents ).collect()
+}
+
+fn check_context_paths( config : &GraphConfig, refs : &GraphRefs ) -> Vec<Diagnostic>
+{
+    let mut written = config.context.keys().map( | k | k.as_str() ).collect::<HashSet<_>>();
+
+    written.extend( refs.written.iter().filter_map( | p | context_root( p ) ) );
+
+    for ( _, to ) in &refs.to_context
+    {
+        match context_root( &to.path )
+        {
+            Some( r ) => { written.insert( r ); },
+            // A dynamic destination could write any key, so reads can not be checked
+            None => return vec![]
+        }
+    }
+
+    let mut reads : Vec<( &str, &str )> = vec![];
+
+    for ( node, from ) in &refs.from
+    {
+        match from
+        {
+            DataFrom::Context( c ) =>
+            {
+                if let Some( r ) = context_root( &c.path ) { reads.push( ( node, r ) ); }
+
+                expanded_roots( &c.path ).into_iter().for_each( | r | reads.push( ( node, r ) ) );
+            },
+            DataFrom::ParsedInput( p ) =>
+            {
+                if let Some( path ) = &p.path
+                {
+                    expanded_roots( path ).into_iter().for_each( | r | reads.push( ( node, r ) ) );
+                }
+            },
+            _ => {}
+        }
+    }
+
+    for ( node, to ) in &refs.to_context
+    {
+        expanded_roots( &to.path ).into_iter().for_each( | r | reads.push( ( node, r ) ) );
+    }
+
+    let mut reported = HashSet::new();
+
+    reads.into_iter()
+    .filter( | ( _, r ) | ! written.contains( r ) )
+    .filter( | ( n, r ) | reported.insert( ( *n, *r ) ) )
+    .map( | ( n, r ) | Diagnostic::warning( Some( n ), format!( "Context path {} is read but never written", r ) ) )
+    .collect()
+}
+
+fn context_root( path : &str ) -> Option<&str>
+{
+    let root = path.trim().trim_start_matches( "/" ).split( "/" ).next().unwrap_or( "" );
+
+    if root.is_empty() || root.contains( "{" ) || root.contains( "}" ) { return None }
+
+    Some( root )
+}
+
+fn expanded_roots( path : &str ) -> Vec<&str>
+{
+    let mut ret = vec![];
+
+    let mut start = None;
+
+    for ( i, c ) in path.char_indices()
+    {
+        match c
+        {
+            '{' => start = Some( i + 1 ),
+            '}' =>
+            {
+                if let Some( s ) = start.take() && let Some( r ) = context_root( &path[ s..i ] )
+                {
+                    ret.push( r );
+                }
+            },
+            _ => {}
+        }
+    }
+
+    ret
+}
+
+fn collect_node<'a>( refs : &mut GraphRefs<'a>, node : &'a NodeConfig )
+{
+    let id = node.id.as_str();
+
+    collect_executor( refs, id, &node.executor );
+
+    if let Some( o ) = &node.output { refs.to_context.push( ( id, o ) ); }
+
+    if let Some( c ) = node.retry.as_ref().and_then( | r | r.condition.as_ref() ) { collect_comparator( refs, id, c ); }
+
+    if let Some( o ) = &node.on_error
+    {
+        if let Some( t ) = &o.output { refs.to_context.push( ( id, t ) ); }
+
+        if let NodeNext::ExitOk( d ) | NodeNext::ExitErr( d ) = &o.next { collect_data_to_string( refs, id, d ); }
+    }
+
+    for d in &node.destination
+    {
+        collect_comparator( refs, id, &d.condition );
+
+        match &d.next
+        {
+            NodeNext::ExitOk( o ) | NodeNext::ExitErr( o ) => collect_data_to_string( refs, id, o ),
+            NodeNext::Node( _ ) => {}
+        }
+    }
+}
+
+fn collect_executor<'a>( refs : &mut GraphRefs<'a>, id : &'a str, executor : &'a NodeExecutorConfig )
+{
+    match executor
+    {
+        NodeExecutorConfig::Agent( a ) => collect_agent( refs, id, a ),
+        NodeExecutorConfig::Command( c ) => collect_command( refs, id, c ),
+        NodeExecutorConfig::Graph( g ) => collect_data_to_string( refs, id, &g.input ),
+        NodeExecutorConfig::ContextMut( v ) =>
+        {
+            for c in v
+            {
+                collect_from( refs, id, &c.from );
+                collect_comparator( refs, id, &c.condition );
+
+                refs.to_context.push( ( id, &c.to ) );
+            }
+        },
+        NodeExecutorConfig::WebClient( w ) => collect_web_client( refs, id, w ),
+        NodeExecutorConfig::AgentHistoryMut( v ) =>
+        {
+            for h in v
+            {
+                collect_from( refs, id, &h.from );
+                collect_comparator( refs, id, &h.condition );
+
+                refs.agent_history.push( ( id, &h.id ) );
+            }
+        },
+        NodeExecutorConfig::Join( _ ) => {},
+        NodeExecutorConfig::Parallel( p ) => p.executors.iter().for_each( | e | collect_parallel_executor( refs, id, e ) ),
+        NodeExecutorConfig::ForEach( f ) =>
+        {
+            collect_from( refs, id, &f.from );
+            collect_parallel_executor( refs, id, &f.executor );
+
+            refs.written.push( &f.item );
+            refs.written.push( &f.index );
+        },
+        NodeExecutorConfig::HumanInput( h ) =>
+        {
+            collect_data_to_string( refs, id, &h.question );
+
+            h.choices.iter().for_each( | c | collect_from( refs, id, c ) );
+        }
+    }
+}
+
+fn collect_parallel_executor<'a>( refs : &mut GraphRefs<'a>, id : &'a str, executor : &'a ParallelExecutor )
+{
+    collect_comparator( refs, id, executor.condition() );
+
+    match executor
+    {
+        ParallelExecutor::Command { ty : _, executor, condition : _ } => collect_command( refs, id, executor ),
+        ParallelExecutor::WebClient { ty : _, executor, condition : _ } => collect_web_client( refs, id, executor ),
+        ParallelExecutor::Agent { ty : _, executor, condition : _ } =>
+        {
+            refs.parallel_agents.push( ( id, executor ) );
+
+            collect_agent( refs, id, executor )
+        },
+        ParallelExecutor::Graph { ty : _, executor, condition : _ } => collect_data_to_string( refs, id, &executor.input )
+    }
+}
+
+fn collect_provider<'a>( refs : &mut GraphRefs<'a>, id : &'a str, provider : &'a AIAgentProviderConfig )
+{
+    collect_from( refs, id, provider.model() );
+
+    match provider
+    {
+        AIAgentProviderConfig::Ollama( OllamaConfig { base_url : Some( u ), .. } ) => collect_from( refs, id, u ),
+        AIAgentProviderConfig::OpenAICompatible( c ) =>
+        {
+            collect_from( refs, id, &c.base_url );
+
+            c.headers.iter().for_each( | h | { collect_from( refs, id, &h.name ); collect_from( refs, id, &h.value ); } );
+        },
+        AIAgentProviderConfig::Fallback( f ) =>
+        {
+            refs.fallbacks.push( ( id, f ) );
+
+            f.providers.iter().for_each( | p | collect_provider( refs, id, &p.provider ) );
+
+            if let Some( o ) = &f.output { refs.written.push( o ) }
+        },
+        AIAgentProviderConfig::Mock( m ) => m.responses.iter().filter_map( | r | r.pattern.as_ref() ).for_each( | p | refs.mock_patterns.push( ( id, p ) ) ),
+        _ => {}
+    }
+}
+
+fn collect_agent<'a>( refs : &mut GraphRefs<'a>, id : &'a str, agent : &'a AIAgent )
+{
+    collect_provider( refs, id, &agent.provider );
+
+    let p = &agent.parameters;
+
+    [ &p.temperature, &p.top_p, &p.max_tokens, &p.stop, &p.seed, &p.additional_params ].into_iter()
+    .flatten()
+    .for_each( | f | collect_from( refs, id, f ) );
+
+    collect_data_to_string( refs, id, &agent.system_prompt );
+    collect_data_to_string( refs, id, &agent.prompt );
+
+    agent.servers.iter().for_each( | s | collect_mcp_server( refs, id, s ) );
+
+    if let Some( s ) = &agent.output_schema { refs.agent_schemas.push( ( id, s ) ) }
+
+    for tool in &agent.tools
+    {
+        refs.agent_schemas.push( ( id, &tool.parameters ) );
+        refs.written.push( &tool.args );
+
+        match &tool.executor
+        {
+            AgentToolExecutor::Command( c ) => collect_command( refs, id, c ),
+            AgentToolExecutor::WebClient( c ) => collect_web_client( refs, id, c ),
+            AgentToolExecutor::Graph( g ) => collect_data_to_string( refs, id, &g.input )
+        }
+    }
+}
+
+fn collect_mcp_server<'a>( refs : &mut GraphRefs<'a>, id : &'a str, server : &'a NodeMCPServer )
+{
+    refs.mcp_servers.push( ( id, server ) );
+
+    server.arguments.iter().for_each( | a | collect_from( refs, id, a ) );
+
+    if let MCPTransport::Sse( t ) | MCPTransport::StreamableHttp( t ) = &server.transport
+    {
+        collect_from( refs, id, &t.url );
+
+        t.headers.iter().for_each( | h | { collect_from( refs, id, &h.name ); collect_from( refs, id, &h.value ); } );
+
+        if let Some( a ) = &t.auth_token { collect_from( refs, id, a ) }
+    }
+}
+
+fn collect_command<'a>( refs : &mut GraphRefs<'a>, id : &'a str, command : &'a Command )
+{
+    collect_from( refs, id, &command.command );
+
+    command.args.iter().for_each( | a | collect_from( refs, id, a ) );
+}
+
+fn collect_web_client<'a>( refs : &mut GraphRefs<'a>, id : &'a str, client : &'a WebClient )
+{
+    collect_from( refs, id, &client.url );
+
+    for nv in client.headers.iter().chain( client.query_params.iter() )
+    {
+        collect_from( refs, id, &nv.name );
+        collect_from( refs, id, &nv.value );
+    }
+
+    match &client.body
+    {
+        Some( WebClientBody::Json( j ) ) => collect_from( refs, id, j ),
+        Some( WebClientBody::Form( f ) ) =>
+        {
+            for nv in f
+            {
+                collect_from( refs, id, &nv.name );
+                collect_from( refs, id, &nv.value );
+            }
+        },
+        None => {}
+    }
+}
+
+fn collect_data_to_string<'a>( refs : &mut GraphRefs<'a>, id : &'a str, data : &'a [DataToString] )
+{
+    data.iter().for_each( | d | collect_from( refs, id, &d.from ) );
+}
+
+fn collect_from<'a>( refs : &mut GraphRefs<'a>, id : &'a str, from : &'a DataFrom )
+{
+    refs.from.push( ( id, from ) );
+
+    match from
+    {
+        DataFrom::Concat( v ) => v.iter().for_each( | f | collect_from( refs, id, f ) ),
+        DataFrom::Operation( o ) => collect_operation( refs, id, o ),
+        DataFrom::AgentHistory( h ) => refs.agent_history.push( ( id, &h.id ) ),
+        DataFrom::Store( s ) => collect_from( refs, id, &s.query ),
+        DataFrom::McpResource( r ) =>
+        {
+            collect_mcp_server( refs, id, &r.server );
+            collect_from( refs, id, &r.uri );
+        },
+        DataFrom::McpPrompt( p ) =>
+        {
+            collect_mcp_server( refs, id, &p.server );
+            p.args.values().for_each( | a | collect_from( refs, id, a ) );
+        },
+        DataFrom::Context( _ ) |
+        DataFrom::ParsedInput( _ ) |
+        DataFrom::Input { .. } |
+        DataFrom::Static( _ ) |
+        DataFrom::Expr( _ ) |
+        DataFrom::Template( _ ) |
+        DataFrom::Null => {}
+    }
+}
+
+fn collect_operation<'a>( refs : &mut GraphRefs<'a>, id : &'a str, operation : &'a DataOperation )
+{
+    match operation
+    {
+        DataOperation::Len( f ) => collect_from( refs, id, f ),
+        DataOperation::Substract { num_1, num_2 } |
+        DataOperation::Add { num_1, num_2 } =>
+        {
+            collect_from( refs, id, num_1 );
+            collect_from( refs, id, num_2 );
+        },
+        DataOperation::Multiply { num_1, num_2 } |
+        DataOperation::Divide { num_1, num_2 } |
+        DataOperation::Modulo { num_1, num_2 } =>
+        {
+            collect_from( refs, id, num_1 );
+            collect_from( refs, id, num_2 );
+        },
+        DataOperation::StringSplit { from, .. } |
+        DataOperation::Replace { from, .. } |
+        DataOperation::RegexCapture { from, .. } |
+        DataOperation::Join { from, .. } |
+        DataOperation::Slice { from, .. } |
+        DataOperation::ToJson { from, .. } |
+        DataOperation::Hash { from, .. } |
+        DataOperation::FormatDate { from, .. } |
+        DataOperation::Upper( from ) |
+        DataOperation::Lower( from ) |
+        DataOperation::Trim( from ) |
+        DataOperation::Keys( from ) |
+        DataOperation::Values( from ) |
+        DataOperation::ParseJson( from ) |
+        DataOperation::Base64Encode( from ) |
+        DataOperation::Base64Decode( from ) => collect_from( refs, id, from ),
+        DataOperation::Filter { from, condition, item } =>
+        {
+            collect_from( refs, id, from );
+            collect_comparator( refs, id, condition );
+
+            refs.written.push( item );
+        },
+        DataOperation::Map { from, map, item } =>
+        {
+            collect_from( refs, id, from );
+            collect_from( refs, id, map );
+
+            refs.written.push( item );
+        },
+        DataOperation::Merge( from ) => from.iter().for_each( | f | collect_from( refs, id, f ) ),
+        DataOperation::Now { .. } => {}
+    }
+}
+
+fn collect_comparator<'a>( refs : &mut GraphRefs<'a>, id : &'a str, comparator : &'a DataComparator )
+{
+    refs.comparators.push( ( id, comparator ) );
+
+    match comparator
+    {
+        DataComparator::Eq { from_1, from_2 } |
+        DataComparator::NotEq { from_1, from_2 } |
+        DataComparator::Gt { from_1, from_2 } |
+        DataComparator::Lt { from_1, from_2 } =>
+        {
+            collect_from( refs, id, from_1 );
+            collect_from( refs, id, from_2 );
+        },
+        DataComparator::Regex { regex : _, from } |
+        DataComparator::Empty( from ) |
+        DataComparator::NotEmpty( from ) => collect_from( refs, id, from ),
+        DataComparator::And { comp_1, comp_2 } |
+        DataComparator::Or { comp_1, comp_2 } |
+        DataComparator::Xor { comp_1, comp_2 } |
+        DataComparator::Nand { comp_1, comp_2 } =>
+        {
+            collect_comparator( refs, id, comp_1 );
+            collect_comparator( refs, id, comp_2 );
+        },
+        DataComparator::Not( c ) => collect_comparator( refs, id, c ),
+        DataComparator::Expr( _ ) |
+        DataComparator::True |
+        DataComparator::False => {}
+    }
+}
@@ -0,0 +1,60 @@
+use serde_json::Value;
+use tracing::info;
+
+use crate::domain::{data::{data_selection::{data_selection, data_to_string}, data_utils::value_to_string}, error::{ChangeError, Error}, graph::graph::Graph, human_input::human_input::{HumanInput, HumanInputRequest}, tracing::filter_layer::{HUMAN_INPUT_ANSWER, HUMAN_INPUT_QUESTION}, utils::string_utils::option_string_to_str};
+
+
+pub async fn execute_human_input(
+    graph : &Graph,
+    node_id : &str,
+    human_input : &HumanInput
+) -> Result<String, Error>
+{
+    let handler = graph.human_input.as_ref()
+    .ok_or( Error::HumanInput( "Graph without human input handler".into() ) )?;
+
+    let question = data_to_string( graph, human_input.question.clone() ).await;
+
+    let choices = choices( graph, human_input ).await?;
+
+    info!(
+        target:HUMAN_INPUT_QUESTION,
+        id=option_string_to_str( graph.id.as_ref() ),
+        text=format!( "{}\nChoices: {:?}", question, choices )
+    );
+
+    let request = HumanInputRequest
+    {
+        graph_id : graph.id.clone(),
+        node_id : Some( node_id.to_string() ),
+        question,
+        choices
+    };
+
+    let answer = handler.ask( request.clone() ).await?;
+
+    info!( target:HUMAN_INPUT_ANSWER, id=option_string_to_str( graph.id.as_ref() ), text=answer );
+
+    if ! request.choices.is_empty() && ! request.choices.contains( &answer )
+    {
+        return Err( Error::HumanInput( format!( "Answer {} is not one of {:?}", answer, request.choices ) ) )
+    }
+
+    Ok( answer )
+}
+
+async fn choices( graph : &Graph, human_input : &HumanInput ) -> Result<Vec<String>, Error>
+{
+    let mut ret = vec![];
+
+    for c in &human_input.choices
+    {
+        match data_selection( graph, c ).await.prepend_err( "HumanInput choices.\n" )?
+        {
+            Value::Array( a ) => ret.extend( a.iter().map( value_to_string ) ),
+            v => ret.push( value_to_string( &v ) )
+        }
+    }
+
+    Ok( ret )
+}
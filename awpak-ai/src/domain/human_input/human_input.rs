@@ -0,0 +1,93 @@
+use std::{future::Future, pin::Pin};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::domain::{data::data::{DataFrom, DataToString}, error::Error};
+
+
+/// Suspends the graph until the question is answered. The answer is the output of the node
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HumanInput
+{
+    pub question : Vec<DataToString>,
+    /// Allowed answers. Values that are arrays add each item as a choice
+    #[serde(default)]
+    pub choices : Vec<DataFrom>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HumanInputRequest
+{
+    pub graph_id : Option<String>,
+    /// `None` for the approval of MCP tool calls
+    pub node_id : Option<String>,
+    pub question : String,
+    pub choices : Vec<String>
+}
+
+pub type HumanInputFuture<'a> = Pin<Box<dyn Future<Output = Result<String, Error>> + Send + 'a>>;
+
+/// Prompt side of the `HumanInput` nodes. Set it in `Graph::human_input`
+pub trait HumanInputHandler : Send + Sync
+{
+    fn ask( &self, request : HumanInputRequest ) -> HumanInputFuture<'_>;
+}
+
+impl<F, Fut> HumanInputHandler for F
+where
+    F : Fn( HumanInputRequest ) -> Fut + Send + Sync,
+    Fut : Future<Output = Result<String, Error>> + Send + 'static
+{
+    fn ask( &self, request : HumanInputRequest ) -> HumanInputFuture<'_>
+    {
+        Box::pin( self( request ) )
+    }
+}
+
+/// Question received from a `human_input_channel`
+pub struct HumanInputQuestion
+{
+    pub request : HumanInputRequest,
+    answer : oneshot::Sender<String>
+}
+
+impl HumanInputQuestion
+{
+    pub fn answer( self, answer : impl Into<String> )
+    {
+        let _ = self.answer.send( answer.into() );
+    }
+}
+
+pub struct ChannelHumanInput
+{
+    sender : mpsc::Sender<HumanInputQuestion>
+}
+
+impl HumanInputHandler for ChannelHumanInput
+{
+    fn ask( &self, request : HumanInputRequest ) -> HumanInputFuture<'_>
+    {
+        Box::pin(
+            async move
+            {
+                let ( answer, receiver ) = oneshot::channel();
+
+                self.sender.send( HumanInputQuestion { request, answer } ).await
+                .map_err( | _ | Error::HumanInput( "Channel closed".into() ) )?;
+
+                receiver.await.map_err( | _ | Error::HumanInput( "Question dropped without answer".into() ) )
+            }
+        )
+    }
+}
+
+/// Handler that sends each question to the returned receiver.
+/// The graph waits until `HumanInputQuestion::answer` is called
+pub fn human_input_channel( buffer : usize ) -> ( ChannelHumanInput, mpsc::Receiver<HumanInputQuestion> )
+{
+    let ( sender, receiver ) = mpsc::channel( buffer );
+
+    ( ChannelHumanInput { sender }, receiver )
+}
@@ -0,0 +1,2 @@
+pub mod human_input;
+pub mod execute_human_input;
\ No newline at end of file
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::data::data::DataMerge;
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Join
+{
+    #[serde(default)]
+    pub branches : Vec<String>,
+    #[serde(default)]
+    pub merge : Vec<JoinMerge>,
+    #[serde(default)]
+    pub default_merge : DataMerge
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JoinMerge
+{
+    pub key : String,
+    #[serde(default)]
+    pub merge : DataMerge
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use awpak_utils::result::result::AwpakResult;
+use serde_json::Value;
+
+use crate::domain::{data::{data::{DataMerge, DataToContext, DataType}, data_insert::value_to_context}, error::Error, join::join::Join};
+
+
+/// Merges into `context` the keys that each branch added or changed.
+/// 
+/// `context` is the context before the fan-out. Branches are merged in order.
+pub fn join_contexts(
+    mut context : HashMap<String, Value>,
+    branches : Vec<( String, HashMap<String, Value> )>,
+    join : &Join
+) -> AwpakResult<HashMap<String, Value>, Error>
+{
+    let base = context.clone();
+
+    for ( name, branch ) in branches
+    {
+        if ! join.branches.is_empty() && ! join.branches.contains( &name ) { continue }
+
+        let mut changed = branch.into_iter()
+        .filter( | ( k, v ) | base.get( k ) != Some( v ) )
+        .collect::<Vec<_>>();
+
+        changed.sort_by( | ( k1, _ ), ( k2, _ ) | k1.cmp( k2 ) );
+
+        for ( key, value ) in changed
+        {
+            let conf = DataToContext 
+            { 
+                path : key.clone(), 
+                ty : DataType::default(), 
+                merge : join_merge( join, &key ), 
+                optional : false,
+                schema : None
+            };
+
+            context = match value_to_context( context, value, &conf ).collect()
+            {
+                ( ( c, _ ), None ) => c,
+                ( ( c, _ ), Some( e ) ) =>
+                {
+                    let msg = format!( "Join branch {}. Key: {}\n", name, key );
+
+                    return AwpakResult::new_err( c, e.prepend_str( msg ) )
+                }
+            };
+        }
+    }
+
+    AwpakResult::new( context )
+}
+
+fn join_merge( join : &Join, key : &str ) -> DataMerge
+{
+    join.merge.iter()
+    .find( | m | m.key == key )
+    .map( | m | m.merge.clone() )
+    .unwrap_or( join.default_merge.clone() )
+}
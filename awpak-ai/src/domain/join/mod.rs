@@ -0,0 +1,2 @@
+pub mod join;
+pub mod join_contexts;
\ No newline at end of file
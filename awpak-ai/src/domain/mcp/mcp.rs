@@ -2,15 +2,117 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::domain::data::data::DataFrom;
+use crate::domain::{data::data::DataFrom, web_client::web_client::WebClientNameValue};
 
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NodeMCPServer
 {
+    #[serde(default)]
+    pub transport : MCPTransport,
+    /// Only used by `MCPTransport::ChildProcess`
+    #[serde(default)]
     pub command : String,
     #[serde(default)]
     pub arguments : Vec<DataFrom>,
     #[serde(default)]
-    pub env : HashMap<String, String>
+    pub env : HashMap<String, String>,
+    /// Which tools the agent can call. Only used by `Agent` servers
+    #[serde(default)]
+    pub policy : MCPToolPolicy
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub enum MCPTransport
+{
+    /// Spawns `command` with `arguments` and talks to it over stdio
+    #[default]
+    ChildProcess,
+    Sse( MCPHttpTransport ),
+    StreamableHttp( MCPHttpTransport )
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MCPHttpTransport
+{
+    pub url : DataFrom,
+    #[serde(default)]
+    pub headers : Vec<WebClientNameValue>,
+    /// Sent as `Authorization: Bearer <token>`
+    #[serde(default)]
+    pub auth_token : Option<DataFrom>
+}
+
+/// Tool name patterns. `*` matches any text
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MCPToolPolicy
+{
+    /// If not empty, tools that don't match are denied
+    #[serde(default)]
+    pub allow : Vec<String>,
+    #[serde(default)]
+    pub deny : Vec<String>,
+    /// Calls are approved with the graph human input handler. Denied if the graph has none
+    #[serde(default)]
+    pub ask : Vec<String>
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MCPToolDecision
+{
+    Allow,
+    Deny,
+    Ask
+}
+
+impl MCPToolPolicy
+{
+    /// `deny` is checked first, then `allow` and then `ask`
+    pub fn decision( &self, tool : &str ) -> MCPToolDecision
+    {
+        let matches = | patterns : &Vec<String> | patterns.iter().any( | p | pattern_matches( p, tool ) );
+
+        if matches( &self.deny ) || ( ! self.allow.is_empty() && ! matches( &self.allow ) )
+        {
+            return MCPToolDecision::Deny
+        }
+
+        if matches( &self.ask ) { MCPToolDecision::Ask } else { MCPToolDecision::Allow }
+    }
+}
+
+fn pattern_matches( pattern : &str, name : &str ) -> bool
+{
+    match pattern.split_once( '*' )
+    {
+        None => pattern == name,
+        Some( ( prefix, rest ) ) =>
+        {
+            name.starts_with( prefix ) &&
+            ( prefix.len()..=name.len() ).any( | i | name.is_char_boundary( i ) && pattern_matches( rest, &name[ i.. ] ) )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_tool_policy_decision()
+    {
+        let policy = MCPToolPolicy
+        {
+            allow : vec![ "read_*".into(), "list_*".into(), "delete_*".into() ],
+            deny : vec![ "*_secret*".into() ],
+            ask : vec![ "delete_*".into() ]
+        };
+
+        assert_eq!( policy.decision( "read_file" ), MCPToolDecision::Allow );
+        assert_eq!( policy.decision( "read_secret_file" ), MCPToolDecision::Deny );
+        assert_eq!( policy.decision( "write_file" ), MCPToolDecision::Deny );
+        assert_eq!( policy.decision( "delete_file" ), MCPToolDecision::Ask );
+        assert_eq!( MCPToolPolicy::default().decision( "write_file" ), MCPToolDecision::Allow );
+    }
 }
@@ -0,0 +1,69 @@
+use rmcp::model::{GetPromptRequestParam, JsonObject, PromptMessageContent, ReadResourceRequestParam, ResourceContents};
+use serde_json::Value;
+
+use crate::domain::{data::{data::{FromMcpPrompt, FromMcpResource}, data_selection::data_selection, data_utils::value_to_string}, error::{ChangeError, Error}, graph::graph::Graph, mcp::mcp_functions::client_from_server};
+
+
+/// Contents of a resource. Text contents are joined with a new line. Blobs are returned in base64
+pub async fn data_from_mcp_resource(
+    graph : &Graph,
+    from : &FromMcpResource
+) -> Result<Value, Error>
+{
+    let uri = value_to_string( &data_selection( graph, &from.uri ).await.prepend_err( "McpResource uri.\n" )? );
+
+    let client = client_from_server( graph, &from.server ).await?;
+
+    let result = client.read_resource( ReadResourceRequestParam { uri : uri.clone() } )
+    .await.map_err( | e | Error::MCPTool( format!( "Resource {}. {}", uri, e ) ).with_source( e ) )?;
+
+    let contents = result.contents.into_iter()
+    .map(
+        | c | match c
+        {
+            ResourceContents::TextResourceContents { text, .. } => text,
+            ResourceContents::BlobResourceContents { blob, .. } => blob
+        }
+    )
+    .collect::<Vec<_>>();
+
+    Ok( Value::String( contents.join( "\n" ) ) )
+}
+
+/// Text of the prompt messages, separated by a blank line. Images are skipped
+pub async fn data_from_mcp_prompt(
+    graph : &Graph,
+    from : &FromMcpPrompt
+) -> Result<Value, Error>
+{
+    let mut arguments = JsonObject::new();
+
+    for ( name, value ) in &from.args
+    {
+        let value = data_selection( graph, value ).await.prepend_err( format!( "McpPrompt arg {}.\n", name ) )?;
+
+        arguments.insert( name.clone(), Value::String( value_to_string( &value ) ) );
+    }
+
+    let client = client_from_server( graph, &from.server ).await?;
+
+    let result = client.get_prompt( GetPromptRequestParam { name : from.name.clone(), arguments : Some( arguments ) } )
+    .await.map_err( | e | Error::MCPTool( format!( "Prompt {}. {}", from.name, e ) ).with_source( e ) )?;
+
+    let messages = result.messages.into_iter()
+    .filter_map(
+        | m | match m.content
+        {
+            PromptMessageContent::Text { text } => Some( text ),
+            PromptMessageContent::Resource { resource } => match resource.raw.resource
+            {
+                ResourceContents::TextResourceContents { text, .. } => Some( text ),
+                ResourceContents::BlobResourceContents { .. } => None
+            },
+            PromptMessageContent::Image { .. } => None
+        }
+    )
+    .collect::<Vec<_>>();
+
+    Ok( Value::String( messages.join( "\n\n" ) ) )
+}
@@ -1,18 +1,28 @@
 use rig::{agent::AgentBuilder, completion::CompletionModel};
-use rmcp::{transport::{ConfigureCommandExt, TokioChildProcess}, ServiceExt};
+use reqwest::header::AUTHORIZATION;
+use rmcp::{model::Tool, transport::{sse_client::SseClientConfig, streamable_http_client::StreamableHttpClientTransportConfig, ConfigureCommandExt, IntoTransport, SseClientTransport, StreamableHttpClientTransport, TokioChildProcess}, ServiceExt};
 use tokio::process::Command;
 
-use crate::domain::{command::command_input::command_args, error::Error, graph::graph::Graph};
+use crate::domain::{command::command_input::command_args, data::{data_selection::data_selection, data_utils::value_to_string}, error::{ChangeError, Error}, graph::graph::Graph, mcp::mcp_pool::MCPClient, web_client::http_client::{http_client, resolve_headers}};
 
-use super::mcp::NodeMCPServer;
+use super::{mcp::{MCPHttpTransport, MCPTransport, NodeMCPServer}, mcp_policy::add_mcp_tool_to_agent};
+
+/// Connection of a server with its `DataFrom` already resolved. Its `Debug` text is the key in the graph `MCPPool`
+#[derive(Debug)]
+enum MCPConnection
+{
+    ChildProcess { command : String, arguments : Vec<String> },
+    Sse { url : String, headers : Vec<( String, String )> },
+    StreamableHttp { url : String, headers : Vec<( String, String )> }
+}
 
 pub async fn add_mcp_clients_to_agent<M: CompletionModel>( 
     graph : &Graph,
     mut agent : AgentBuilder<M>,
     servers : &Vec<NodeMCPServer>
-) -> Result<( AgentBuilder<M>, Vec<rmcp::service::RunningService<rmcp::RoleClient, ()>> ), Error>
+) -> Result<( AgentBuilder<M>, Vec<MCPClient> ), Error>
 {
-    let mut clients : Vec<rmcp::service::RunningService<rmcp::RoleClient, ()>> = vec![];
+    let mut clients : Vec<MCPClient> = vec![];
 
     for server in servers
     {
@@ -30,54 +40,456 @@ async fn add_server_to_agent<T: CompletionModel>(
     graph : &Graph,
     agent_builder : AgentBuilder<T>, 
     server : &NodeMCPServer 
-) -> Result<( AgentBuilder<T>, rmcp::service::RunningService<rmcp::RoleClient, ()> ), Error>
+) -> Result<( AgentBuilder<T>, MCPClient ), Error>
 {
-    let client: rmcp::service::RunningService<rmcp::RoleClient, ()> = client_from_server( graph, server ).await?;
-
-    let tools = client.list_all_tools().await.map_err( | e | Error::MCPTool( e.to_string() ) )?;
+    let ( client, tools ) = client_and_tools( graph, server ).await?;
     
     let agent_builder = tools
     .into_iter()
     .fold(
         agent_builder, 
-        | builder, tool |
-        {
-            builder.rmcp_tool( tool, client.clone() )
-        }
+        | builder, tool | add_mcp_tool_to_agent( graph, builder, &client, tool, &server.policy )
     );
 
     Ok( ( agent_builder, client ) )
 }
 
-async fn client_from_server( 
+/// Listing the tools is also the health check of a pooled client. If it fails, the client is connected again once
+async fn client_and_tools(
+    graph : &Graph,
+    server : &NodeMCPServer
+) -> Result<( MCPClient, Vec<Tool> ), Error>
+{
+    let connection = server_connection( graph, server ).await?;
+
+    let key = format!( "{:?}", connection );
+
+    let client = graph.mcp_pool.get_or_connect( &key, || connect( &connection ) ).await?;
+
+    if let Ok( tools ) = client.list_all_tools().await { return Ok( ( client, tools ) ) }
+
+    graph.mcp_pool.remove( &key ).await;
+
+    let client = graph.mcp_pool.get_or_connect( &key, || connect( &connection ) ).await?;
+
+    let tools = client.list_all_tools().await.map_err( | e | Error::MCPTool( e.to_string() ).with_source( e ) )?;
+
+    Ok( ( client, tools ) )
+}
+
+/// Client of the server from the graph `MCPPool`. It is connected on first use
+pub async fn client_from_server( 
     graph : &Graph,
     server : &NodeMCPServer 
-) -> Result<rmcp::service::RunningService<rmcp::RoleClient, ()>, Error>
-{    
-    let arguments = command_args( graph, &server.arguments ).await?;
+) -> Result<MCPClient, Error>
+{
+    let connection = server_connection( graph, server ).await?;
+
+    graph.mcp_pool.get_or_connect( &format!( "{:?}", connection ), || connect( &connection ) ).await
+}
+
+async fn server_connection(
+    graph : &Graph,
+    server : &NodeMCPServer
+) -> Result<MCPConnection, Error>
+{
+    Ok(
+        match &server.transport
+        {
+            MCPTransport::ChildProcess => MCPConnection::ChildProcess 
+            { 
+                command : server.command.clone(), 
+                arguments : command_args( graph, &server.arguments ).await? 
+            },
+            MCPTransport::Sse( t ) => MCPConnection::Sse 
+            { 
+                url : transport_url( graph, t ).await?, 
+                headers : transport_headers( graph, t ).await? 
+            },
+            MCPTransport::StreamableHttp( t ) => MCPConnection::StreamableHttp 
+            { 
+                url : transport_url( graph, t ).await?, 
+                headers : transport_headers( graph, t ).await? 
+            }
+        }
+    )
+}
+
+async fn connect( connection : &MCPConnection ) -> Result<rmcp::service::RunningService<rmcp::RoleClient, ()>, Error>
+{
+    match connection
+    {
+        MCPConnection::ChildProcess { command, arguments } =>
+        {
+            serve_client( tokio_child_process( command, arguments )? ).await
+        },
+        MCPConnection::Sse { url, headers } =>
+        {
+            let config = SseClientConfig { sse_endpoint : url.as_str().into(), ..Default::default() };
+
+            let transport = SseClientTransport::start_with_client( http_client( headers, Error::MCPTool )?, config )
+            .await.map_err( | e | Error::MCPTool( e.to_string() ).with_source( e ) )?;
+
+            serve_client( transport ).await
+        },
+        MCPConnection::StreamableHttp { url, headers } =>
+        {
+            let config = StreamableHttpClientTransportConfig::with_uri( url.as_str() );
+
+            serve_client( StreamableHttpClientTransport::with_client( http_client( headers, Error::MCPTool )?, config ) ).await
+        }
+    }
+}
+
+async fn serve_client<T, E, A>( transport : T ) -> Result<rmcp::service::RunningService<rmcp::RoleClient, ()>, Error>
+where
+    T : IntoTransport<rmcp::RoleClient, E, A>,
+    E : std::error::Error + Send + Sync + 'static
+{
+    ().serve( transport ).await.map_err( | e | Error::MCPTool( e.to_string() ).with_source( e ) )
+}
+
+async fn transport_url( graph : &Graph, transport : &MCPHttpTransport ) -> Result<String, Error>
+{
+    let url = value_to_string( &data_selection( graph, &transport.url ).await.prepend_err( "MCP url.\n" )? );
+
+    if url.trim() == "" { return Err( Error::MCPTool( "Empty MCP url".into() ) ) }
+
+    Ok( url )
+}
 
-    let child_process = tokio_child_process( server, arguments )?;
+async fn transport_headers( graph : &Graph, transport : &MCPHttpTransport ) -> Result<Vec<( String, String )>, Error>
+{
+    let mut ret = resolve_headers( graph, &transport.headers ).await?;
 
-    let client = ()
-    .serve( child_process )
-    .await.map_err( | e | Error::MCPTool( e.to_string() ) )?;
+    if let Some( token ) = &transport.auth_token
+    {
+        let token = value_to_string( &data_selection( graph, token ).await.prepend_err( "MCP auth_token.\n" )? );
 
-    Ok( client )
+        ret.push( ( AUTHORIZATION.to_string(), format!( "Bearer {}", token ) ) );
+    }
+
+    Ok( ret )
 }
 
-fn tokio_child_process( server : &NodeMCPServer, arguments : Vec<String> ) -> Result<TokioChildProcess, Error>
+fn tokio_child_process( command : &str, arguments : &[String] ) -> Result<TokioChildProcess, Error>
 {
-    TokioChildProcess::new( mcp_command( server, arguments ) ).map_err( | e | Error::MCPTool( e.to_string() ) )
+    TokioChildProcess::new( mcp_command( command, arguments ) ).map_err( | e | Error::MCPTool( e.to_string() ).with_source( e ) )
 }
 
-fn mcp_command( server : &NodeMCPServer, arguments : Vec<String> ) -> Command
+fn mcp_command( command : &str, arguments : &[String] ) -> Command
 {
     Command::new(
-        &server.command
+        command
     )
     .configure(
         |cmd| {
             arguments.iter().for_each( | a | { cmd.arg( a ); } );
         }
     )
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use axum::{extract::Request, http::StatusCode, middleware::{from_fn, Next}, response::Response};
+    use rmcp::{handler::server::{router::tool::ToolRouter, tool::Parameters}, model::{ErrorData, GetPromptRequestParam, GetPromptResult, PromptMessage, PromptMessageRole, ReadResourceRequestParam, ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo}, schemars, service::RequestContext, RoleServer, tool, tool_handler, tool_router, transport::{sse_server::{SseServer, SseServerConfig}, streamable_http_server::{session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService}}, ServerHandler};
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio_util::sync::CancellationToken;
+
+    use rig::tool::rmcp::McpTool;
+
+    use crate::domain::{data::data::DataFrom, human_input::human_input::HumanInputRequest, mcp::{mcp::MCPToolPolicy, mcp_policy::call_policy_tool}};
+
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    struct SumRequest
+    {
+        a : i32,
+        b : i32
+    }
+
+    #[derive(Clone)]
+    struct Calculator
+    {
+        tool_router : ToolRouter<Self>
+    }
+
+    #[tool_router]
+    impl Calculator
+    {
+        fn new() -> Self
+        {
+            Self { tool_router : Self::tool_router() }
+        }
+
+        #[tool(description = "Sum two numbers")]
+        fn sum( &self, Parameters( SumRequest { a, b } ) : Parameters<SumRequest> ) -> String
+        {
+            ( a + b ).to_string()
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Calculator
+    {
+        fn get_info( &self ) -> ServerInfo
+        {
+            ServerInfo 
+            { 
+                capabilities : ServerCapabilities::builder().enable_tools().enable_resources().enable_prompts().build(), 
+                ..Default::default() 
+            }
+        }
+
+        async fn read_resource( &self, request : ReadResourceRequestParam, _ : RequestContext<RoleServer> ) -> Result<ReadResourceResult, ErrorData>
+        {
+            match request.uri.as_str()
+            {
+                "docs://readme" => Ok( ReadResourceResult { contents : vec![ ResourceContents::text( "Calculator docs", request.uri ) ] } ),
+                _ => Err( ErrorData::resource_not_found( request.uri, None ) )
+            }
+        }
+
+        async fn get_prompt( &self, request : GetPromptRequestParam, _ : RequestContext<RoleServer> ) -> Result<GetPromptResult, ErrorData>
+        {
+            let n = request.arguments.as_ref().and_then( | a | a.get( "n" ) ).and_then( | n | n.as_str() ).unwrap_or( "" ).to_string();
+
+            Ok(
+                GetPromptResult
+                {
+                    description : None,
+                    messages : vec![ 
+                        PromptMessage::new_text( PromptMessageRole::User, format!( "Sum {} and {}", n, n ) ),
+                        PromptMessage::new_text( PromptMessageRole::User, "Use the sum tool" )
+                    ]
+                }
+            )
+        }
+    }
+
+    async fn check_token( request : Request, next : Next ) -> Result<Response, StatusCode>
+    {
+        match request.headers().get( "authorization" ).and_then( | h | h.to_str().ok() )
+        {
+            Some( "Bearer secret" ) => Ok( next.run( request ).await ),
+            _ => Err( StatusCode::UNAUTHORIZED )
+        }
+    }
+
+    async fn serve( router : axum::Router, ct : CancellationToken ) -> String
+    {
+        let listener = tokio::net::TcpListener::bind( "127.0.0.1:0" ).await.unwrap();
+
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn( async move
+        {
+            let _ = axum::serve( listener, router ).with_graceful_shutdown( ct.cancelled_owned() ).await;
+        } );
+
+        format!( "http://{}", addr )
+    }
+
+    fn server( transport : &str, url : String, token : &str ) -> NodeMCPServer
+    {
+        serde_json::from_value( json!(
+            {
+                "transport" : {
+                    transport : {
+                        "url" : { "Static" : url },
+                        "headers" : [ { "name" : { "Static" : "x-client" }, "value" : { "Static" : "awpak" } } ],
+                        "auth_token" : { "Static" : token }
+                    }
+                }
+            }
+        ) ).unwrap()
+    }
+
+    async fn tool_names( server : &NodeMCPServer ) -> Result<Vec<String>, Error>
+    {
+        let client = client_from_server( &Graph::default(), server ).await?;
+
+        let tools = client.list_all_tools().await.map_err( | e | Error::MCPTool( e.to_string() ) )?;
+
+        Ok( tools.into_iter().map( | t | t.name.to_string() ).collect() )
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_transport()
+    {
+        let ct = CancellationToken::new();
+
+        let service : StreamableHttpService<Calculator, LocalSessionManager> = StreamableHttpService::new(
+            || Ok( Calculator::new() ),
+            Default::default(),
+            StreamableHttpServerConfig::default()
+        );
+
+        let url = serve( axum::Router::new().nest_service( "/mcp", service ).layer( from_fn( check_token ) ), ct.clone() ).await;
+
+        let names = tool_names( &server( "StreamableHttp", format!( "{}/mcp", url ), "secret" ) ).await;
+
+        assert_eq!( names.unwrap(), vec![ "sum".to_string() ] );
+
+        assert_eq!( tool_names( &server( "StreamableHttp", format!( "{}/mcp", url ), "wrong" ) ).await.unwrap_err().kind(), "MCPTool" );
+
+        ct.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_pooled_clients()
+    {
+        let ct = CancellationToken::new();
+
+        let service : StreamableHttpService<Calculator, LocalSessionManager> = StreamableHttpService::new(
+            || Ok( Calculator::new() ),
+            Default::default(),
+            StreamableHttpServerConfig::default()
+        );
+
+        let url = serve( axum::Router::new().nest_service( "/mcp", service ).layer( from_fn( check_token ) ), ct.clone() ).await;
+
+        let server = server( "StreamableHttp", format!( "{}/mcp", url ), "secret" );
+
+        let graph = Graph::default();
+
+        let first = client_from_server( &graph, &server ).await.unwrap();
+
+        let ( second, tools ) = client_and_tools( &graph.clone(), &server ).await.unwrap();
+
+        assert!( Arc::ptr_eq( &first, &second ) );
+        assert_eq!( tools.len(), 1 );
+        assert_eq!( graph.mcp_pool.len().await, 1 );
+
+        let other = client_from_server( &Graph::default(), &server ).await.unwrap();
+
+        assert!( ! Arc::ptr_eq( &first, &other ) );
+
+        let graph = Graph::default();
+
+        let ( a, b ) = tokio::join!( client_from_server( &graph, &server ), client_from_server( &graph, &server ) );
+
+        assert!( Arc::ptr_eq( &a.unwrap(), &b.unwrap() ) );
+        assert_eq!( graph.mcp_pool.len().await, 1 );
+
+        ct.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_mcp_resource_and_prompt()
+    {
+        let ct = CancellationToken::new();
+
+        let service : StreamableHttpService<Calculator, LocalSessionManager> = StreamableHttpService::new(
+            || Ok( Calculator::new() ),
+            Default::default(),
+            StreamableHttpServerConfig::default()
+        );
+
+        let url = serve( axum::Router::new().nest_service( "/mcp", service ).layer( from_fn( check_token ) ), ct.clone() ).await;
+
+        let server = serde_json::to_value( server( "StreamableHttp", format!( "{}/mcp", url ), "secret" ) ).unwrap();
+
+        let mut graph = Graph::default();
+
+        graph.context.insert( "n".into(), json!( 4 ) );
+
+        let from : DataFrom = serde_json::from_value( 
+            json!( { "McpResource" : { "server" : server, "uri" : { "Static" : "docs://readme" } } } ) 
+        ).unwrap();
+
+        assert_eq!( data_selection( &graph, &from ).await.unwrap(), json!( "Calculator docs" ) );
+
+        let from : DataFrom = serde_json::from_value( 
+            json!( { "McpResource" : { "server" : server, "uri" : { "Static" : "docs://missing" } } } ) 
+        ).unwrap();
+
+        assert_eq!( data_selection( &graph, &from ).await.unwrap_err().kind(), "MCPTool" );
+
+        let from : DataFrom = serde_json::from_value( 
+            json!( { "McpPrompt" : { "server" : server, "name" : "sum", "args" : { "n" : { "Context" : { "path" : "n" } } } } } ) 
+        ).unwrap();
+
+        assert_eq!( data_selection( &graph, &from ).await.unwrap(), json!( "Sum 4 and 4\n\nUse the sum tool" ) );
+
+        assert_eq!( graph.mcp_pool.len().await, 1 );
+
+        ct.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_tool_policy()
+    {
+        let ct = CancellationToken::new();
+
+        let service : StreamableHttpService<Calculator, LocalSessionManager> = StreamableHttpService::new(
+            || Ok( Calculator::new() ),
+            Default::default(),
+            StreamableHttpServerConfig::default()
+        );
+
+        let url = serve( axum::Router::new().nest_service( "/mcp", service ).layer( from_fn( check_token ) ), ct.clone() ).await;
+
+        let mut graph = Graph::default();
+
+        let ( client, mut tools ) = client_and_tools( &graph, &server( "StreamableHttp", format!( "{}/mcp", url ), "secret" ) ).await.unwrap();
+
+        let tool = Arc::new( McpTool::from_mcp_server( tools.remove( 0 ), client.peer().clone() ) );
+
+        let call = | graph : &Graph, policy : MCPToolPolicy | call_policy_tool( 
+            graph.clone(), "sum".into(), tool.clone(), policy, json!( { "a" : 2, "b" : 3 } ) 
+        );
+
+        let policy = | allow : &[&str], deny : &[&str], ask : &[&str] | MCPToolPolicy
+        {
+            allow : allow.iter().map( | s | s.to_string() ).collect(),
+            deny : deny.iter().map( | s | s.to_string() ).collect(),
+            ask : ask.iter().map( | s | s.to_string() ).collect()
+        };
+
+        assert_eq!( call( &graph, policy( &[ "s*" ], &[], &[] ) ).await.unwrap(), "5" );
+        assert!( call( &graph, policy( &[], &[ "sum" ], &[] ) ).await.unwrap().starts_with( "Error: " ) );
+        assert!( call( &graph, policy( &[], &[], &[ "*" ] ) ).await.unwrap().starts_with( "Error: " ) );
+
+        graph.human_input = Some( Arc::new( | r : HumanInputRequest | async move
+        {
+            Ok( if r.question.contains( "\"a\":2" ) { "yes".to_string() } else { "no".to_string() } )
+        } ) );
+
+        assert_eq!( call( &graph, policy( &[], &[], &[ "*" ] ) ).await.unwrap(), "5" );
+
+        ct.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_sse_transport()
+    {
+        let ct = CancellationToken::new();
+
+        let ( sse_server, router ) = SseServer::new(
+            SseServerConfig
+            {
+                bind : "127.0.0.1:0".parse().unwrap(),
+                sse_path : "/sse".into(),
+                post_path : "/message".into(),
+                ct : ct.clone(),
+                sse_keep_alive : None
+            }
+        );
+
+        sse_server.with_service( Calculator::new );
+
+        let url = serve( router.layer( from_fn( check_token ) ), ct.clone() ).await;
+
+        let names = tool_names( &server( "Sse", format!( "{}/sse", url ), "secret" ) ).await;
+
+        assert_eq!( names.unwrap(), vec![ "sum".to_string() ] );
+
+        assert_eq!( tool_names( &server( "Sse", format!( "{}/sse", url ), "wrong" ) ).await.unwrap_err().kind(), "MCPTool" );
+
+        ct.cancel();
+    }
+}
@@ -0,0 +1,117 @@
+use std::{future::Future, sync::Arc};
+
+use rig::{agent::AgentBuilder, completion::{CompletionModel, ToolDefinition}, tool::{rmcp::McpTool, Tool, ToolDyn}};
+use serde_json::Value;
+use tracing::info;
+
+use crate::domain::{error::Error, graph::graph::Graph, human_input::human_input::HumanInputRequest, mcp::{mcp::{MCPToolDecision, MCPToolPolicy}, mcp_pool::MCPClient}, tracing::filter_layer::AGENT_TOOL_CALL, utils::string_utils::option_string_to_str};
+
+
+/// Adds a server tool. Each call is checked with the policy of the server and traced with its decision
+pub fn add_mcp_tool_to_agent<M: CompletionModel>(
+    graph : &Graph,
+    agent : AgentBuilder<M>,
+    client : &MCPClient,
+    tool : rmcp::model::Tool,
+    policy : &MCPToolPolicy
+) -> AgentBuilder<M>
+{
+    agent.tool(
+        PolicyTool
+        {
+            graph : graph.clone(),
+            name : tool.name.to_string(),
+            tool : Arc::new( McpTool::from_mcp_server( tool, client.peer().clone() ) ),
+            policy : policy.clone()
+        }
+    )
+}
+
+struct PolicyTool
+{
+    graph : Graph,
+    name : String,
+    tool : Arc<McpTool>,
+    policy : MCPToolPolicy
+}
+
+impl Tool for PolicyTool
+{
+    const NAME : &'static str = "mcp_policy_tool";
+
+    type Error = Error;
+    type Args = Value;
+    type Output = String;
+
+    fn name( &self ) -> String
+    {
+        self.name.clone()
+    }
+
+    async fn definition( &self, prompt : String ) -> ToolDefinition
+    {
+        self.tool.definition( prompt ).await
+    }
+
+    fn call( &self, args : Self::Args ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send + Sync
+    {
+        // rig needs a Send + Sync future, but the futures of the human input handler
+        // and of `McpTool::call` are only Send. The JoinHandle of a task is Sync
+        let task = tokio::spawn(
+            call_policy_tool( self.graph.clone(), self.name.clone(), self.tool.clone(), self.policy.clone(), args )
+        );
+
+        async move
+        {
+            task.await.map_err( | e | Error::MCPTool( e.to_string() ).with_source( e ) )?
+        }
+    }
+}
+
+/// Denied calls are returned to the agent as the tool result
+pub( super ) async fn call_policy_tool(
+    graph : Graph,
+    name : String,
+    tool : Arc<McpTool>,
+    policy : MCPToolPolicy,
+    args : Value
+) -> Result<String, Error>
+{
+    let decision = match policy.decision( &name )
+    {
+        MCPToolDecision::Ask => ask_approval( &graph, &name, &args ).await,
+        d => d
+    };
+
+    info!(
+        target:AGENT_TOOL_CALL,
+        id=option_string_to_str( graph.id.as_ref() ),
+        text=format!( "\nToolCall policy:\n- Function name: {}\n- Args: {}\n- Decision: {:?}\n", name, args, decision )
+    );
+
+    if decision != MCPToolDecision::Allow
+    {
+        return Ok( format!( "Error: Call to tool {} denied by policy", name ) )
+    }
+
+    tool.call( args.to_string() ).await.map_err( | e | Error::MCPTool( e.to_string() ).with_source( e ) )
+}
+
+async fn ask_approval( graph : &Graph, name : &str, args : &Value ) -> MCPToolDecision
+{
+    let Some( handler ) = graph.human_input.as_ref() else { return MCPToolDecision::Deny };
+
+    let request = HumanInputRequest
+    {
+        graph_id : graph.id.clone(),
+        node_id : None,
+        question : format!( "Allow call to tool {} with args {}?", name, args ),
+        choices : vec![ "yes".into(), "no".into() ]
+    };
+
+    match handler.ask( request ).await
+    {
+        Ok( a ) if a == "yes" => MCPToolDecision::Allow,
+        _ => MCPToolDecision::Deny
+    }
+}
@@ -0,0 +1,65 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use rmcp::{service::RunningService, RoleClient};
+use tokio::sync::Mutex;
+
+use crate::domain::error::Error;
+
+
+pub type MCPClient = Arc<RunningService<RoleClient, ()>>;
+
+/// MCP clients of a graph, shared by its nodes and agent turns.
+///
+/// Clients are started on first use and closed when the last clone of the graph is dropped.
+#[derive(Default)]
+pub struct MCPPool
+{
+    clients : Mutex<HashMap<String, MCPClient>>
+}
+
+impl MCPPool
+{
+    /// Returns the client for `key`. A new client is connected if there is none or its transport is closed
+    pub async fn get_or_connect<F, Fut>( &self, key : &str, connect : F ) -> Result<MCPClient, Error>
+    where
+        F : FnOnce() -> Fut,
+        Fut : Future<Output = Result<RunningService<RoleClient, ()>, Error>>
+    {
+        if let Some( c ) = self.open_client( key ).await { return Ok( c ) }
+
+        // Not locked while connecting, so a slow server does not block the others
+        let client = Arc::new( connect().await? );
+
+        let mut clients = self.clients.lock().await;
+
+        // Another task may have connected the same server meanwhile. Its client is kept
+        if let Some( c ) = clients.get( key ) && ! c.is_transport_closed()
+        {
+            return Ok( c.clone() )
+        }
+
+        clients.insert( key.to_string(), client.clone() );
+
+        Ok( client )
+    }
+
+    async fn open_client( &self, key : &str ) -> Option<MCPClient>
+    {
+        self.clients.lock().await.get( key ).filter( | c | ! c.is_transport_closed() ).cloned()
+    }
+
+    pub async fn remove( &self, key : &str )
+    {
+        self.clients.lock().await.remove( key );
+    }
+
+    pub async fn len( &self ) -> usize
+    {
+        self.clients.lock().await.len()
+    }
+
+    pub async fn is_empty( &self ) -> bool
+    {
+        self.clients.lock().await.is_empty()
+    }
+}
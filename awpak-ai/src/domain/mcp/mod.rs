@@ -1,2 +1,5 @@
 pub mod mcp;
-pub mod mcp_functions;
\ No newline at end of file
+pub mod mcp_functions;
+pub mod mcp_pool;
+pub mod mcp_data;
+pub mod mcp_policy;
\ No newline at end of file
@@ -12,4 +12,12 @@ pub mod signals;
 pub mod web_client;
 pub mod agent_history_mut;
 pub mod parallel;
-pub mod store;
\ No newline at end of file
+pub mod store;
+pub mod checkpoint;
+pub mod join;
+pub mod for_each;
+pub mod expr;
+pub mod template;
+pub mod human_input;
+pub mod usage;
+pub mod cassette;
@@ -1,79 +1,55 @@
 use serde_json::Value;
-use tokio::task::JoinSet;
 
-use crate::domain::{command::execute_command::execute_command, data::{data::DataType, data_compare::compare_data, data_utils::str_to_value}, error::Error, graph::graph::Graph, parallel::parallel::{Parallel, ParallelExecutor}, web_client::execute_web_client::execute_web_client};
+use crate::domain::{agent::execute_agent::execute_agent, command::execute_command::execute_command, data::{data::DataType, data_utils::str_to_value}, error::Error, graph::graph::Graph, parallel::parallel::ParallelExecutor, web_client::execute_web_client::execute_web_client};
 
-pub async fn execute_parallel(
+/// Executes a `Command`, `WebClient` or `Agent` item of a parallel node. 
+/// 
+/// Agents with `save_history` return the executor with the updated history.
+pub async fn execute_parallel_executor(
     graph : &Graph,
-    parallel : &Parallel
-) -> Result<Vec<Value>, Error>
+    mut item : ParallelExecutor
+) -> ( ParallelExecutor, Result<Value, Error> )
 {
-    let mut tasks = JoinSet::new();
-
-    for ( idx, executor ) in parallel.executors.iter().enumerate()
-    {
-        tasks.spawn(
-            execute_item( graph.clone(), executor.clone(), idx )
-        );
-    }
-
-    let mut results = tasks.join_all().await;
-
-    results.sort_by( | ( _, t1 ), ( _, t2 ) | t1.cmp( t2 ) );
-
-    results.into_iter().try_fold(
-        vec![], 
-        | mut a, r |
-        {
-            a.push( r.0? );
-
-            Ok( a )
-        }
-    )
-}
-
-async fn execute_item(
-    graph : Graph,
-    item : ParallelExecutor,
-    idx : usize
-) -> ( Result<Value, Error>, usize )
-{
-    match compare_data( 
-            &graph,
-            item.condition() 
-    ).await
-    {
-        Ok( r ) if r => {},
-        Ok( _ ) => return ( Ok( Value::Null ), idx ),
-        Err( e ) => return ( Err( e ), idx )
-    };
-
-    let result = match item
+    let result = match &mut item
     {
         ParallelExecutor::Command { ty, executor, condition : _ } =>
         {
             result_str_to_value(
-                execute_command( &graph, &executor ).await,
+                execute_command( graph, executor ).await,
                 ty
             )
         },
         ParallelExecutor::WebClient { ty, executor, condition : _ } =>
         {
             result_str_to_value(
-                execute_web_client( &graph, &executor ).await,
+                execute_web_client( graph, executor ).await,
                 ty
             )
-        }
+        },
+        ParallelExecutor::Agent { ty, executor, condition : _ } =>
+        {
+            match execute_agent( graph, executor ).await
+            {
+                Ok( ( s, h, _ ) ) =>
+                {
+                    if executor.save_history { executor.history = h; }
+
+                    result_str_to_value( Ok( s ), ty )
+                },
+                Err( e ) => Err( e )
+            }
+        },
+        ParallelExecutor::Graph { .. } => Err( Error::NodeNotFound( "ParallelExecutor::Graph is not built".into() ) )
     };
 
-    ( result, idx )
+    ( item, result )
 }
 
-fn result_str_to_value( result : Result<String, Error>, ty : DataType ) -> Result<Value, Error>
+fn result_str_to_value( result : Result<String, Error>, ty : &DataType ) -> Result<Value, Error>
 {
     match result
     {
-        Ok( r ) => str_to_value( &r, &ty, true ),
+        Ok( r ) => str_to_value( &r, ty, true ),
         Err( e ) => Err( e )    
     }
-}
\ No newline at end of file
+}
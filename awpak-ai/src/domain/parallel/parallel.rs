@@ -1,18 +1,24 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-use crate::domain::{command::command::Command, data::data::{DataComparator, DataType}, web_client::web_client::WebClient};
+use crate::domain::{agent::agent::AIAgent, command::command::Command, data::data::{DataComparator, DataType}, graph::{graph_node::GraphNode, node::GraphNodeConfig}, web_client::web_client::WebClient};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Parallel
 {
-    pub executors : Vec<ParallelExecutor>
+    pub executors : Vec<ParallelExecutor>,
+    #[serde(default)]
+    pub concurrency : Option<usize>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum ParallelExecutor
 {
     Command { #[serde(default)] ty : DataType, executor : Command, condition : DataComparator },
-    WebClient { #[serde(default)] ty : DataType, executor : WebClient, condition : DataComparator }
+    WebClient { #[serde(default)] ty : DataType, executor : WebClient, condition : DataComparator },
+    Agent { #[serde(default)] ty : DataType, executor : AIAgent, condition : DataComparator },
+    Graph { #[serde(default)] ty : DataType, executor : GraphNodeConfig, condition : DataComparator }
 }
 
 impl ParallelExecutor
@@ -22,7 +28,9 @@ impl ParallelExecutor
         match self
         {
             ParallelExecutor::Command { ty : _, executor : _, condition } |
-            ParallelExecutor::WebClient { ty : _, executor : _, condition } =>
+            ParallelExecutor::WebClient { ty : _, executor : _, condition } |
+            ParallelExecutor::Agent { ty : _, executor : _, condition } |
+            ParallelExecutor::Graph { ty : _, executor : _, condition } =>
             {
                 condition
             }
@@ -34,10 +42,22 @@ impl ParallelExecutor
         match self
         {
             ParallelExecutor::Command { ty, executor : _, condition : _ } |
-            ParallelExecutor::WebClient { ty, executor : _, condition : _ } =>
+            ParallelExecutor::WebClient { ty, executor : _, condition : _ } |
+            ParallelExecutor::Agent { ty, executor : _, condition : _ } |
+            ParallelExecutor::Graph { ty, executor : _, condition : _ } =>
             {
                 ty.clone()
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Parallel executor with the sub-graphs already built. 
+/// 
+/// `graphs` is indexed by the position of the `ParallelExecutor::Graph` in `parallel.executors`.
+#[derive(Clone)]
+pub struct ParallelNode
+{
+    pub parallel : Parallel,
+    pub graphs : HashMap<usize, GraphNode>
+}
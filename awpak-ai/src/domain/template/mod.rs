@@ -0,0 +1,3 @@
+pub mod template;
+pub mod parse_template;
+pub mod render_template;
\ No newline at end of file
@@ -0,0 +1,188 @@
+use crate::domain::{error::Error, expr::{expr::Expr, parse_expr::parse_expr}, template::template::TemplateNode};
+
+
+const MAX_DEPTH : usize = 32;
+
+enum BlockKind
+{
+    Root,
+    If( Expr ),
+    Each { items : Expr, item : String, index : String }
+}
+
+struct TemplateBlock
+{
+    kind : BlockKind,
+    nodes : Vec<TemplateNode>,
+    otherwise : Vec<TemplateNode>,
+    in_else : bool
+}
+
+impl TemplateBlock
+{
+    fn new( kind : BlockKind ) -> Self
+    {
+        Self { kind, nodes : vec![], otherwise : vec![], in_else : false }
+    }
+
+    fn push( &mut self, node : TemplateNode )
+    {
+        if self.in_else { self.otherwise.push( node ) } else { self.nodes.push( node ) }
+    }
+}
+
+pub fn parse_template( source : &str ) -> Result<Vec<TemplateNode>, Error>
+{
+    let mut stack = vec![ TemplateBlock::new( BlockKind::Root ) ];
+
+    let mut rest = source;
+
+    while let Some( start ) = rest.find( "{{" )
+    {
+        push_text( &mut stack, &rest[ ..start ] );
+
+        let after = &rest[ start + 2.. ];
+
+        let end = after.find( "}}" ).ok_or( Error::ParseData( format!( "Template: unclosed tag {{{{{}", after ) ) )?;
+
+        parse_tag( &mut stack, after[ ..end ].trim() ).map_err( | e | e.prepend_str( "Template: " ) )?;
+
+        rest = &after[ end + 2.. ];
+    }
+
+    push_text( &mut stack, rest );
+
+    if stack.len() > 1 { return Err( Error::ParseData( "Template: unclosed block. Expected {{/if}} or {{/each}}".into() ) ) }
+
+    Ok( stack.pop().map( | b | b.nodes ).unwrap_or_default() )
+}
+
+fn push_text( stack : &mut [TemplateBlock], text : &str )
+{
+    if text.is_empty() { return }
+
+    if let Some( b ) = stack.last_mut() { b.push( TemplateNode::Text( text.to_string() ) ) }
+}
+
+fn parse_tag( stack : &mut Vec<TemplateBlock>, tag : &str ) -> Result<(), Error>
+{
+    if tag.starts_with( '!' ) { return Ok( () ) }
+
+    if let Some( name ) = tag.strip_prefix( '>' )
+    {
+        push_node( stack, TemplateNode::Partial( name.trim().to_string() ) );
+
+        return Ok( () )
+    }
+
+    if let Some( cond ) = tag.strip_prefix( "#if " )
+    {
+        return open_block( stack, BlockKind::If( parse_expr( cond )? ) )
+    }
+
+    if let Some( each ) = tag.strip_prefix( "#each " )
+    {
+        let ( items, names ) = match each.rsplit_once( " as " )
+        {
+            Some( ( items, names ) ) => ( items, Some( names ) ),
+            None => ( each, None )
+        };
+
+        let mut names = names.unwrap_or( "" ).split( ',' ).map( | n | n.trim() ).filter( | n | ! n.is_empty() );
+
+        let item = names.next().unwrap_or( "item" ).to_string();
+        let index = names.next().unwrap_or( "index" ).to_string();
+
+        return open_block( stack, BlockKind::Each { items : parse_expr( items )?, item, index } )
+    }
+
+    match tag
+    {
+        "else" => match stack.last_mut()
+        {
+            Some( b ) if ! matches!( b.kind, BlockKind::Root ) && ! b.in_else =>
+            {
+                b.in_else = true;
+
+                Ok( () )
+            },
+            _ => Err( Error::ParseData( "Unexpected {{else}}".into() ) )
+        },
+        "/if" | "/each" => close_block( stack, tag ),
+        _ =>
+        {
+            push_node( stack, TemplateNode::Value( parse_expr( tag )? ) );
+
+            Ok( () )
+        }
+    }
+}
+
+fn push_node( stack : &mut [TemplateBlock], node : TemplateNode )
+{
+    if let Some( b ) = stack.last_mut() { b.push( node ) }
+}
+
+fn open_block( stack : &mut Vec<TemplateBlock>, kind : BlockKind ) -> Result<(), Error>
+{
+    if stack.len() > MAX_DEPTH { return Err( Error::ParseData( format!( "Max depth {} exceeded", MAX_DEPTH ) ) ) }
+
+    stack.push( TemplateBlock::new( kind ) );
+
+    Ok( () )
+}
+
+fn close_block( stack : &mut Vec<TemplateBlock>, tag : &str ) -> Result<(), Error>
+{
+    let block = match stack.pop()
+    {
+        Some( b ) if ! matches!( b.kind, BlockKind::Root ) => b,
+        _ => return Err( Error::ParseData( format!( "Unexpected {{{{{}}}}}", tag ) ) )
+    };
+
+    let node = match ( block.kind, tag )
+    {
+        ( BlockKind::If( cond ), "/if" ) => TemplateNode::If { cond, then : block.nodes, otherwise : block.otherwise },
+        ( BlockKind::Each { items, item, index }, "/each" ) =>
+        {
+            TemplateNode::Each { items, item, index, body : block.nodes, otherwise : block.otherwise }
+        },
+        _ => return Err( Error::ParseData( format!( "Unexpected {{{{{}}}}}", tag ) ) )
+    };
+
+    push_node( stack, node );
+
+    Ok( () )
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_parse_template_blocks()
+    {
+        let nodes = parse_template( "a {{#if true}}b{{else}}c{{/if}} {{#each [1, 2] as n}}{{ context.n }}{{/each}}" );
+
+        assert!( nodes.is_ok() );
+
+        let nodes = nodes.unwrap();
+
+        assert_eq!( nodes.len(), 4 );
+
+        assert!( matches!( &nodes[ 1 ], TemplateNode::If { then, otherwise, .. } if then.len() == 1 && otherwise.len() == 1 ) );
+        assert!( matches!( &nodes[ 3 ], TemplateNode::Each { item, index, .. } if item == "n" && index == "index" ) );
+    }
+
+    #[test]
+    fn test_parse_template_errors()
+    {
+        assert!( parse_template( "{{#if true}}a" ).is_err() );
+        assert!( parse_template( "{{#if true}}a{{/each}}" ).is_err() );
+        assert!( parse_template( "a{{/if}}" ).is_err() );
+        assert!( parse_template( "{{else}}" ).is_err() );
+        assert!( parse_template( "{{ 1 + }}" ).is_err() );
+        assert!( parse_template( "{{ context.a" ).is_err() );
+    }
+}
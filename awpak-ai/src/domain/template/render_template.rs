@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::domain::{data::{data::FromTemplate, data_utils::value_to_string}, error::Error, expr::eval_expr::{eval_expr, value_is_truthy}, graph::graph::Graph, template::{parse_template::parse_template, template::TemplateNode}};
+
+
+const MAX_PARTIAL_DEPTH : usize = 16;
+
+pub fn render_template( graph : &Graph, template : &FromTemplate ) -> Result<Value, Error>
+{
+    let nodes = parse_template( &template.template )?;
+
+    let mut ret = String::new();
+
+    render_nodes( graph, &nodes, &template.partials, 0, &mut ret )?;
+
+    Ok( Value::String( ret ) )
+}
+
+fn render_nodes(
+    graph : &Graph,
+    nodes : &[TemplateNode],
+    partials : &HashMap<String, String>,
+    depth : usize,
+    out : &mut String
+) -> Result<(), Error>
+{
+    for node in nodes
+    {
+        match node
+        {
+            TemplateNode::Text( t ) => out.push_str( t ),
+            TemplateNode::Value( e ) => out.push_str( &value_to_string( &eval_expr( graph, e )? ) ),
+            TemplateNode::If { cond, then, otherwise } =>
+            {
+                let nodes = if value_is_truthy( &eval_expr( graph, cond )? )? { then } else { otherwise };
+
+                render_nodes( graph, nodes, partials, depth, out )?;
+            },
+            TemplateNode::Each { items, item, index, body, otherwise } =>
+            {
+                let items = match eval_expr( graph, items )?
+                {
+                    Value::Array( a ) => a,
+                    Value::Null => vec![],
+                    v => return Err( Error::ParseData( format!( "Template: #each value is not an array. {:?}", v ) ) )
+                };
+
+                if items.is_empty()
+                {
+                    render_nodes( graph, otherwise, partials, depth, out )?;
+
+                    continue;
+                }
+
+                let mut graph_item = graph.clone();
+
+                for ( i, value ) in items.into_iter().enumerate()
+                {
+                    graph_item.context.insert( item.clone(), value );
+                    graph_item.context.insert( index.clone(), Value::from( i ) );
+
+                    render_nodes( &graph_item, body, partials, depth, out )?;
+                }
+            },
+            TemplateNode::Partial( name ) =>
+            {
+                if depth >= MAX_PARTIAL_DEPTH
+                {
+                    return Err( Error::ParseData( format!( "Template: max partial depth {} exceeded", MAX_PARTIAL_DEPTH ) ) )
+                }
+
+                let partial = partials.get( name ).ok_or( Error::ParseData( format!( "Template: partial {} not found", name ) ) )?;
+
+                let nodes = parse_template( partial ).map_err( | e | e.prepend_str( format!( "Partial {}. ", name ) ) )?;
+
+                render_nodes( graph, &nodes, partials, depth + 1, out )?;
+            }
+        }
+    }
+
+    Ok( () )
+}
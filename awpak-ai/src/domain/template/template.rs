@@ -0,0 +1,19 @@
+use crate::domain::expr::expr::Expr;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateNode
+{
+    Text( String ),
+    Value( Expr ),
+    If { cond : Expr, then : Vec<TemplateNode>, otherwise : Vec<TemplateNode> },
+    Each 
+    { 
+        items : Expr, 
+        item : String, 
+        index : String, 
+        body : Vec<TemplateNode>, 
+        otherwise : Vec<TemplateNode> 
+    },
+    Partial( String )
+}
@@ -19,6 +19,10 @@ pub const AGENT_STREAM : &'static str = "agent_stream";
 pub const AGENT_SYNC : &'static str = "agent_sync";
 pub const AGENT_TOOL_CALL : &'static str = "agent_tool_call";
 pub const AGENT_TOOL_RESULT : &'static str = "agent_tool_result";
+pub const AGENT_USAGE : &'static str = "agent_usage";
+pub const AGENT_FALLBACK : &'static str = "agent_fallback";
+
+pub const CASSETTE_ERROR : &'static str = "cassette_error";
 
 pub const COMMAND_AND_ARGS : &'static str = "command_and_args";
 pub const COMMAND_RESULT : &'static str = "command_result";
@@ -31,9 +35,14 @@ pub const WEB_CLIENT_RESPONSE : &'static str = "web_client_response";
 pub const WEB_CLIENT_RESPONSE_HEADERS : &'static str = "web_client_response_headers";
 pub const WEB_CLIENT_RESPONSE_BODY : &'static str = "web_client_response_body";
 
+pub const HUMAN_INPUT_QUESTION : &'static str = "human_input_question";
+pub const HUMAN_INPUT_ANSWER : &'static str = "human_input_answer";
+
 pub const NODE_DESTINATION : &'static str = "node_destination";
 pub const NODE_EXECUTION : &'static str = "node_execution";
 pub const NODE_OUTPUT : &'static str = "node_output";
+pub const NODE_RETRY : &'static str = "node_retry";
+pub const NODE_ERROR : &'static str = "node_error";
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum AwpakAITarget
@@ -47,6 +56,9 @@ pub enum AwpakAITarget
     AgentSync,
     AgentToolCall,
     AgentToolResult,
+    AgentUsage,
+    AgentFallback,
+    CassetteError,
     CommandAndArgs,
     CommandResult,
     WebClientRequest,
@@ -56,9 +68,13 @@ pub enum AwpakAITarget
     WebClientResponse,
     WebClientResponseHeaders,
     WebClientResponseBody,
+    HumanInputQuestion,
+    HumanInputAnswer,
     NodeDestination,
     NodeExecution,
-    NodeOutput
+    NodeOutput,
+    NodeRetry,
+    NodeError
 }
 
 impl AwpakAITarget
@@ -76,6 +92,9 @@ impl AwpakAITarget
             AwpakAITarget::AgentSync => AGENT_SYNC,
             AwpakAITarget::AgentToolCall => AGENT_TOOL_CALL,
             AwpakAITarget::AgentToolResult => AGENT_TOOL_RESULT,
+            AwpakAITarget::AgentUsage => AGENT_USAGE,
+            AwpakAITarget::AgentFallback => AGENT_FALLBACK,
+            AwpakAITarget::CassetteError => CASSETTE_ERROR,
             AwpakAITarget::CommandAndArgs => COMMAND_AND_ARGS,
             AwpakAITarget::CommandResult => COMMAND_RESULT,
             AwpakAITarget::WebClientRequest => WEB_CLIENT_REQUEST,
@@ -85,9 +104,13 @@ impl AwpakAITarget
             AwpakAITarget::WebClientResponse => WEB_CLIENT_RESPONSE,
             AwpakAITarget::WebClientResponseHeaders => WEB_CLIENT_RESPONSE_HEADERS,
             AwpakAITarget::WebClientResponseBody => WEB_CLIENT_RESPONSE_BODY,
+            AwpakAITarget::HumanInputQuestion => HUMAN_INPUT_QUESTION,
+            AwpakAITarget::HumanInputAnswer => HUMAN_INPUT_ANSWER,
             AwpakAITarget::NodeDestination => NODE_DESTINATION,
             AwpakAITarget::NodeExecution => NODE_EXECUTION,
-            AwpakAITarget::NodeOutput => NODE_OUTPUT
+            AwpakAITarget::NodeOutput => NODE_OUTPUT,
+            AwpakAITarget::NodeRetry => NODE_RETRY,
+            AwpakAITarget::NodeError => NODE_ERROR
         }
     }
 
@@ -105,6 +128,9 @@ impl AwpakAITarget
                 AGENT_SYNC => AwpakAITarget::AgentSync,
                 AGENT_TOOL_CALL => AwpakAITarget::AgentToolCall,
                 AGENT_TOOL_RESULT => AwpakAITarget::AgentToolResult,
+                AGENT_USAGE => AwpakAITarget::AgentUsage,
+                AGENT_FALLBACK => AwpakAITarget::AgentFallback,
+                CASSETTE_ERROR => AwpakAITarget::CassetteError,
                 COMMAND_AND_ARGS => AwpakAITarget::CommandAndArgs,
                 COMMAND_RESULT => AwpakAITarget::CommandResult,
                 WEB_CLIENT_REQUEST => AwpakAITarget::WebClientRequest,
@@ -114,9 +140,13 @@ impl AwpakAITarget
                 WEB_CLIENT_RESPONSE => AwpakAITarget::WebClientResponse,
                 WEB_CLIENT_RESPONSE_HEADERS => AwpakAITarget::WebClientResponseHeaders,
                 WEB_CLIENT_RESPONSE_BODY => AwpakAITarget::WebClientResponseBody,
+                HUMAN_INPUT_QUESTION => AwpakAITarget::HumanInputQuestion,
+                HUMAN_INPUT_ANSWER => AwpakAITarget::HumanInputAnswer,
                 NODE_DESTINATION => AwpakAITarget::NodeDestination,
                 NODE_EXECUTION => AwpakAITarget::NodeExecution,
                 NODE_OUTPUT => AwpakAITarget::NodeOutput,
+                NODE_RETRY => AwpakAITarget::NodeRetry,
+                NODE_ERROR => AwpakAITarget::NodeError,
                 _ => return None
             }
         )
@@ -0,0 +1 @@
+pub mod usage;
\ No newline at end of file
@@ -0,0 +1,149 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage
+{
+    pub input_tokens : u64,
+    pub output_tokens : u64,
+    pub total_tokens : u64
+}
+
+impl TokenUsage
+{
+    pub fn new( input_tokens : u64, output_tokens : u64, total_tokens : u64 ) -> Self
+    {
+        Self
+        {
+            input_tokens,
+            output_tokens,
+            // Some providers only report input and output
+            total_tokens : if total_tokens == 0 { input_tokens + output_tokens } else { total_tokens }
+        }
+    }
+
+    pub fn add( &mut self, other : &TokenUsage )
+    {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+impl From<rig::completion::Usage> for TokenUsage
+{
+    fn from( usage : rig::completion::Usage ) -> Self
+    {
+        TokenUsage::new( usage.input_tokens, usage.output_tokens, usage.total_tokens )
+    }
+}
+
+/// Usage of the final response of a stream. Each provider has its own format
+pub fn usage_from_stream_response( response : &Value ) -> TokenUsage
+{
+    let usage = response.get( "usage" ).or( response.get( "usage_metadata" ) ).unwrap_or( response );
+
+    let field = | names : &[&str] | names.iter().find_map( | n | usage.get( n ).and_then( | v | v.as_u64() ) ).unwrap_or( 0 );
+
+    let input = field( &[ "input_tokens", "prompt_tokens", "prompt_eval_count" ] );
+    let total = field( &[ "total_tokens", "totalTokenCount" ] );
+    let output = match field( &[ "output_tokens", "completion_tokens", "eval_count" ] )
+    {
+        0 => total.saturating_sub( input ),
+        o => o
+    };
+
+    TokenUsage::new( input, output, total )
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct GraphUsage
+{
+    /// Current run. Reset when the graph starts
+    pub run : TokenUsage,
+    /// Every run of the graph, e.g. a chat session
+    pub total : TokenUsage,
+    /// Current run, by node id. Usage of sub-graphs is added to the node that runs them
+    pub nodes : HashMap<String, TokenUsage>
+}
+
+/// Token usage of a graph, shared by its clones (parallel items, fan out branches and agent tools)
+#[derive(Debug, Default)]
+pub struct UsageCounter
+{
+    usage : Mutex<GraphUsage>
+}
+
+impl UsageCounter
+{
+    pub fn add( &self, node_id : Option<&str>, usage : &TokenUsage )
+    {
+        let mut current = self.usage.lock().unwrap();
+
+        current.run.add( usage );
+        current.total.add( usage );
+
+        if let Some( id ) = node_id
+        {
+            current.nodes.entry( id.to_string() ).or_default().add( usage );
+        }
+    }
+
+    pub fn start_run( &self )
+    {
+        let mut current = self.usage.lock().unwrap();
+
+        current.run = TokenUsage::default();
+        current.nodes.clear();
+    }
+
+    /// Continues the run of a checkpoint
+    pub fn restore( &self, usage : GraphUsage )
+    {
+        *self.usage.lock().unwrap() = usage;
+    }
+
+    pub fn get( &self ) -> GraphUsage
+    {
+        self.usage.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_usage_from_stream_response()
+    {
+        assert_eq!( usage_from_stream_response( &json!( { "usage" : { "input_tokens" : 10, "output_tokens" : 5, "total_tokens" : 15 } } ) ), TokenUsage::new( 10, 5, 15 ) );
+        assert_eq!( usage_from_stream_response( &json!( { "usage" : { "prompt_tokens" : 10, "total_tokens" : 12 } } ) ), TokenUsage::new( 10, 2, 12 ) );
+        assert_eq!( usage_from_stream_response( &json!( { "usage" : { "input_tokens" : null, "output_tokens" : 7 } } ) ), TokenUsage::new( 0, 7, 7 ) );
+        assert_eq!( usage_from_stream_response( &json!( { "usage_metadata" : { "totalTokenCount" : 9 } } ) ), TokenUsage::new( 0, 9, 9 ) );
+        assert_eq!( usage_from_stream_response( &json!( { "done_reason" : "stop", "prompt_eval_count" : 3, "eval_count" : 4 } ) ), TokenUsage::new( 3, 4, 7 ) );
+    }
+
+    #[test]
+    fn test_usage_counter()
+    {
+        let counter = UsageCounter::default();
+
+        counter.add( Some( "a" ), &TokenUsage::new( 1, 2, 3 ) );
+        counter.add( Some( "a" ), &TokenUsage::new( 1, 1, 2 ) );
+        counter.start_run();
+        counter.add( Some( "b" ), &TokenUsage::new( 4, 0, 4 ) );
+
+        let usage = counter.get();
+
+        assert_eq!( usage.run, TokenUsage::new( 4, 0, 4 ) );
+        assert_eq!( usage.total, TokenUsage::new( 6, 3, 9 ) );
+        assert_eq!( usage.nodes.get( "a" ), None );
+        assert_eq!( usage.nodes.get( "b" ), Some( &TokenUsage::new( 4, 0, 4 ) ) );
+    }
+}
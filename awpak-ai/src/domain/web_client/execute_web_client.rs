@@ -4,7 +4,7 @@ use awpak_web_client::{request::{AwpakBody, AwpakFormField, AwpakHeader, AwpakQu
 use tokio::time::sleep;
 use tracing::info;
 
-use crate::domain::{data::{data_selection::data_selection, data_utils::value_to_string}, error::Error, graph::graph::Graph, signals::cancel_graph::is_graph_cancelled, tracing::filter_layer::{WEB_CLIENT_REQUEST, WEB_CLIENT_REQUEST_BODY, WEB_CLIENT_REQUEST_HEADERS, WEB_CLIENT_REQUEST_QUERY_PARAMS, WEB_CLIENT_RESPONSE, WEB_CLIENT_RESPONSE_BODY, WEB_CLIENT_RESPONSE_HEADERS}, utils::string_utils::{option_string_to_str, prefix_str_suffix}, web_client::web_client::{WebClient, WebClientBody, WebClientNameValue, WebClientOutput}};
+use crate::domain::{cassette::cassette::{with_cassette, CassetteKind}, data::{data_selection::data_selection, data_utils::value_to_string}, error::Error, graph::graph::Graph, signals::cancel_graph::is_graph_cancelled, tracing::filter_layer::{WEB_CLIENT_REQUEST, WEB_CLIENT_REQUEST_BODY, WEB_CLIENT_REQUEST_HEADERS, WEB_CLIENT_REQUEST_QUERY_PARAMS, WEB_CLIENT_RESPONSE, WEB_CLIENT_RESPONSE_BODY, WEB_CLIENT_RESPONSE_HEADERS}, utils::string_utils::{option_string_to_str, prefix_str_suffix}, web_client::web_client::{WebClient, WebClientBody, WebClientNameValue, WebClientOutput}};
 
 
 pub async fn execute_web_client(
@@ -16,7 +16,12 @@ pub async fn execute_web_client(
 
     let request = request( graph, client ).await?;
 
-    let response = execute_send_request( id, request ).await?;
+    let response = with_cassette(
+        graph.cassette.as_ref(),
+        CassetteKind::WebClient,
+        || serde_json::to_value( &request ).map_err( | e | Error::ParseData( format!( "Cassette request. {}", e ) ).with_source( e ) ),
+        || execute_send_request( id, request.clone() )
+    ).await?;
 
     output(
         id, 
@@ -38,7 +43,7 @@ async fn execute_send_request(
             {
                 v = send_request( request ) =>
                 {
-                    v.map_err( | e | Error::WebClient( e.to_string() ) )
+                    v.map_err( | e | Error::WebClient( e.to_string() ).with_source( e ) )
                 },
                 _ = async {
                     loop
@@ -50,7 +55,7 @@ async fn execute_send_request(
                 } => Err( Error::WebClient( "Request cancelled".into() ) )
             }
         },
-        _ => send_request( request ).await.map_err( | e | Error::WebClient( e.to_string() ) )
+        _ => send_request( request ).await.map_err( | e | Error::WebClient( e.to_string() ).with_source( e ) )
     }
 }
 
@@ -135,7 +140,7 @@ fn item_output(
                 prefix_str_suffix( 
                     prefix.as_ref(), 
                     suffix.as_ref(), 
-                    &serde_json::to_string( response ).map_err( | e | Error::ParseData( e.to_string() ) )?
+                    &serde_json::to_string( response ).map_err( | e | Error::ParseData( e.to_string() ).with_source( e ) )?
                 ) 
             )
         },
@@ -0,0 +1,41 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::domain::{data::{data_selection::data_selection, data_utils::value_to_string}, error::Error, graph::graph::Graph, web_client::web_client::WebClientNameValue};
+
+
+/// Name and value of each header, with their `DataFrom` resolved
+pub async fn resolve_headers( graph : &Graph, headers : &[WebClientNameValue] ) -> Result<Vec<( String, String )>, Error>
+{
+    let mut ret = vec![];
+
+    for h in headers
+    {
+        ret.push( 
+            ( 
+                value_to_string( &data_selection( graph, &h.name ).await? ), 
+                value_to_string( &data_selection( graph, &h.value ).await? ) 
+            ) 
+        );
+    }
+
+    Ok( ret )
+}
+
+/// HTTP client that sends the headers in every request. `error` is the variant of the returned errors, e.g. `Error::Agent`
+pub fn http_client( headers : &[( String, String )], error : fn( String ) -> Error ) -> Result<reqwest::Client, Error>
+{
+    let mut map = HeaderMap::new();
+
+    for ( name, value ) in headers
+    {
+        map.insert(
+            HeaderName::from_bytes( name.as_bytes() ).map_err( | e | error( format!( "Header {}. {}", name, e ) ).with_source( e ) )?,
+            HeaderValue::from_str( value ).map_err( | e | error( format!( "Header value. {}", e ) ).with_source( e ) )?
+        );
+    }
+
+    reqwest::Client::builder()
+    .default_headers( map )
+    .build()
+    .map_err( | e | error( e.to_string() ).with_source( e ) )
+}
@@ -1,2 +1,3 @@
 pub mod web_client;
-pub mod execute_web_client;
\ No newline at end of file
+pub mod execute_web_client;
+pub mod http_client;
@@ -1,2 +1,3 @@
 pub mod build_graph;
-pub mod run_graph;
\ No newline at end of file
+pub mod run_graph;
+pub mod validate_graph;
\ No newline at end of file
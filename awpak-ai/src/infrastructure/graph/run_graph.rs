@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use awpak_utils::result::result::AwpakResult;
 
-use crate::{application, domain::{error::Error, graph::graph::Graph}};
+use crate::{application, domain::{checkpoint::checkpoint::GraphCheckpoint, error::Error, graph::graph::Graph, human_input::human_input::HumanInputHandler}};
 
 
 pub async fn run_graph( 
@@ -11,13 +13,718 @@ pub async fn run_graph(
     application::graph::run_graph::run_graph( input, graph ).await
 }
 
+/// Runs the graph with `handler` as the prompt side of its `HumanInput` nodes
+pub async fn run_graph_with_human_input(
+    input : String,
+    mut graph : Graph,
+    handler : impl HumanInputHandler + 'static
+) -> AwpakResult<Graph, Error>
+{
+    graph.human_input = Some( Arc::new( handler ) );
+
+    run_graph( input, graph ).await
+}
+
+pub async fn resume_graph(
+    checkpoint : GraphCheckpoint,
+    graph : Graph
+) -> AwpakResult<Graph, Error>
+{
+    application::graph::run_graph::resume_graph( checkpoint, graph ).await
+}
+
+pub async fn resume_graph_from_store(
+    graph : Graph
+) -> AwpakResult<Graph, Error>
+{
+    let checkpoint = match &graph.checkpoint
+    {
+        Some( s ) => s.load( graph.id.as_deref() ),
+        None => Err( Error::File( "Graph has no checkpoint store".into() ) )
+    };
+
+    match checkpoint
+    {
+        Ok( Some( c ) ) => resume_graph( c, graph ).await,
+        Ok( None ) => AwpakResult::new_err( graph, Error::File( "Checkpoint not found".into() ) ),
+        Err( e ) => AwpakResult::new_err( graph, e )
+    }
+}
+
 #[cfg(test)]
 mod tests
 {
-    use crate::infrastructure::graph::build_graph::graph_from_json_file_path;
+    use std::collections::HashMap;
+
+    use serde_json::{json, Value};
+
+    use crate::{domain::{cassette::cassette::Cassette, checkpoint::checkpoint::{CheckpointStore, DirCheckpointStore}, data::data::DataComparator, graph::node::NodeExecutor, human_input::human_input::{human_input_channel, HumanInputRequest}, join::join::Join, usage::usage::TokenUsage}, infrastructure::graph::build_graph::{graph_from_json_file_path, graph_from_json_str}};
 
     use super::*;
 
+    #[tokio::test]
+    async fn test_resume_loop_graph_from_checkpoint()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/loop_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let checkpoint = GraphCheckpoint
+        {
+            context : HashMap::from(
+                [
+                    ( "count".to_string(), json!( 1 ) ),
+                    ( "numbers".to_string(), json!( [ 1, 2, 3 ] ) ),
+                    ( "result".to_string(), json!( 1 ) )
+                ]
+            ),
+            next : Some( "sum_numbers".into() ),
+            ..Default::default()
+        };
+
+        let graph = resume_graph( checkpoint, graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( graph.final_output, Some( Ok( "Result: 6".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_resume_loop_graph_keeps_steps()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/loop_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let mut graph = graph.unwrap();
+
+        graph.max_steps = Some( 7 );
+
+        let checkpoint = GraphCheckpoint
+        {
+            context : HashMap::from(
+                [
+                    ( "count".to_string(), json!( 1 ) ),
+                    ( "numbers".to_string(), json!( [ 1, 2, 3 ] ) ),
+                    ( "result".to_string(), json!( 1 ) )
+                ]
+            ),
+            next : Some( "sum_numbers".into() ),
+            steps : 6,
+            visits : HashMap::from( [ ( "add_one_to_count".to_string(), 3 ), ( "sum_numbers".to_string(), 3 ) ] ),
+            trail : vec![ "add_one_to_count".into(), "sum_numbers".into() ],
+            ..Default::default()
+        };
+
+        let graph = resume_graph( checkpoint, graph ).await;
+
+        assert!( graph.is_err() );
+
+        let err = graph.err().unwrap();
+
+        assert!( matches!( err, Error::LimitExceeded( _ ) ) );
+        assert!( err.to_string().contains( "Max steps 7 exceeded" ), "{}", err );
+        assert!( err.to_string().contains( "Trail: ... -> add_one_to_count -> sum_numbers -> sum_numbers" ), "{}", err );
+    }
+
+    #[tokio::test]
+    async fn test_resume_checkpoint_unknown_node()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/loop_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let checkpoint = GraphCheckpoint { next : Some( "not_found".into() ), ..Default::default() };
+
+        let graph = resume_graph( checkpoint, graph.unwrap() ).await;
+
+        assert!( graph.is_err() );
+    }
+
+    #[tokio::test]
+    async fn test_run_graph_saves_checkpoints()
+    {
+        let dir = std::env::temp_dir().join( uuid::Uuid::new_v4().to_string() );
+
+        let graph = graph_from_json_file_path( "test_data/graphs/loop_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let mut graph = graph.unwrap();
+
+        graph.id = Some( "loop".into() );
+        graph.checkpoint = Some( Arc::new( DirCheckpointStore::new( &dir ) ) );
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        assert!( graph.is_ok() );
+
+        let checkpoint = DirCheckpointStore::new( &dir ).load( Some( "loop" ) );
+
+        let _ = std::fs::remove_dir_all( &dir );
+
+        assert!( checkpoint.is_ok() );
+
+        let checkpoint = checkpoint.unwrap();
+
+        assert!( checkpoint.is_some() );
+
+        let checkpoint = checkpoint.unwrap();
+
+        assert!( checkpoint.next.is_none() );
+        assert_eq!( checkpoint.final_output, Some( Ok( "Result: 6".to_string() ) ) );
+
+        let graph = resume_graph_from_store( graph.own() ).await;
+
+        assert!( graph.is_err() );
+    }
+
+    #[tokio::test]
+    async fn test_run_loop_graph_max_steps()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/loop_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let mut graph = graph.unwrap();
+
+        graph.max_steps = Some( 3 );
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        assert!( graph.is_err() );
+
+        let err = graph.err().unwrap();
+
+        assert!( matches!( err, Error::LimitExceeded( _ ) ) );
+
+        assert!( err.to_string().contains( "Max steps 3 exceeded in node sum_numbers" ) );
+        assert!( err.to_string().contains( "Trail: add_one_to_count -> sum_numbers -> add_one_to_count -> sum_numbers" ) );
+
+        let graph = graph_from_json_file_path( "test_data/graphs/loop_graph.json" ).await;
+
+        let mut graph = graph.unwrap();
+
+        graph.max_steps = Some( 7 );
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        assert!( graph.is_ok() );
+    }
+
+    #[tokio::test]
+    async fn test_run_infinite_loop_graph_max_visits()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/infinite_loop_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( "".into(), graph.unwrap() ).await;
+
+        assert!( graph.is_err() );
+
+        let err = graph.err().unwrap();
+
+        assert!( matches!( err, Error::LimitExceeded( _ ) ) );
+
+        assert!( err.to_string().contains( "Max visits 3 exceeded in node node_2" ) );
+        assert!( err.to_string().contains( "Steps: 8" ) );
+    }
+
+    async fn retry_graph( file : &std::path::Path, attempts : usize ) -> Graph
+    {
+        let json = std::fs::read_to_string( "test_data/graphs/retry_graph.json" ).unwrap()
+        .replace( "__FILE__", file.to_str().unwrap() )
+        .replace( r#""attempts": 3"#, &format!( r#""attempts": {}"#, attempts ) );
+
+        let graph = graph_from_json_str( json ).await;
+
+        assert!( graph.is_ok() );
+
+        graph.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_retry_graph_ok()
+    {
+        let file = std::env::temp_dir().join( uuid::Uuid::new_v4().to_string() );
+
+        let graph = run_graph( "".into(), retry_graph( &file, 3 ).await ).await;
+
+        let _ = std::fs::remove_file( &file );
+
+        assert!( graph.is_ok() );
+
+        assert_eq!( graph.own().final_output, Some( Ok( "Attempts: 3".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_retry_graph_on_error()
+    {
+        let file = std::env::temp_dir().join( uuid::Uuid::new_v4().to_string() );
+
+        let graph = run_graph( "".into(), retry_graph( &file, 2 ).await ).await;
+
+        let _ = std::fs::remove_file( &file );
+
+        assert!( graph.is_ok() );
+
+        let final_output = graph.own().final_output;
+
+        assert!( matches!( &final_output, Some( Err( e ) ) if e.starts_with( "ParseData" ) ), "{:?}", final_output );
+    }
+
+    #[tokio::test]
+    async fn test_run_retry_graph_not_retryable_error()
+    {
+        let file = std::env::temp_dir().join( uuid::Uuid::new_v4().to_string() );
+
+        let mut graph = retry_graph( &file, 3 ).await;
+
+        let node = graph.nodes.get_mut( "count_attempts" ).unwrap();
+
+        node.retry.as_mut().unwrap().errors = vec![ "Command".into() ];
+        node.on_error = None;
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        let content = std::fs::read_to_string( &file );
+
+        let _ = std::fs::remove_file( &file );
+
+        assert!( matches!( graph.err().map( | e | e.root() ), Some( Error::ParseData( _ ) ) ) );
+        assert_eq!( content.unwrap(), "x" );
+    }
+
+    #[tokio::test]
+    async fn test_run_graph_node_error()
+    {
+        let json = r#"{
+            "first" : {
+                "id" : "node_1",
+                "executor" : { "Command" : { "command" : { "Static" : "awpak_command_not_found" } } },
+                "destination" : [ { "next" : { "ExitOk" : [] }, "condition" : "True" } ]
+            }
+        }"#;
+
+        let graph = graph_from_json_str( json ).await;
+
+        assert!( graph.is_ok() );
+
+        let mut graph = graph.unwrap();
+
+        graph.id = Some( "graph_1".into() );
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        let err = graph.err().unwrap();
+
+        let node = err.node().unwrap();
+
+        assert_eq!( node.graph_id.as_deref(), Some( "graph_1" ) );
+        assert_eq!( node.node_id, "node_1" );
+        assert_eq!( node.executor, "Command" );
+        assert_eq!( err.kind(), "Command" );
+        assert!( matches!( err.root(), Error::Command( _ ) ) );
+        assert!( err.to_string().starts_with( "Command: NodeExecutor::Command node_1\n" ) );
+
+        let mut source = std::error::Error::source( err );
+
+        while let Some( s ) = source && s.downcast_ref::<std::io::Error>().is_none()
+        {
+            source = s.source();
+        }
+
+        assert!( source.is_some() );
+    }
+
+    #[tokio::test]
+    async fn test_run_retry_condition()
+    {
+        let json = r#"{
+            "context" : { "count" : 0 },
+            "first" : {
+                "id" : "node_1",
+                "executor" : {
+                    "ContextMut" : [
+                        {
+                            "from" : { "Operation" : { "Add" : { "num_1" : { "Context" : { "path" : "count" } }, "num_2" : { "Static" : 1 } } } },
+                            "to" : { "path" : "count", "ty" : "Number" },
+                            "condition" : "True"
+                        }
+                    ]
+                },
+                "retry" : {
+                    "attempts" : 5,
+                    "condition" : { "Lt" : { "from_1" : { "Context" : { "path" : "count" } }, "from_2" : { "Static" : 3 } } }
+                },
+                "destination" : [
+                    { "next" : { "ExitOk" : [ { "from" : { "Context" : { "path" : "count" } } } ] }, "condition" : "True" }
+                ]
+            }
+        }"#;
+
+        let graph = graph_from_json_str( json ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( "".into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        assert_eq!( graph.own().final_output, Some( Ok( "3".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_fan_out_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/fan_out_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( "".into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( graph.final_output, Some( Ok( "only a, b".to_string() ) ) );
+        assert_eq!( graph.context.get( "results" ), Some( &json!( [ "a", "b" ] ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_fan_out_graph_branch_exits()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/fan_out_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let mut graph = graph.unwrap();
+
+        graph.nodes.get_mut( "start" ).unwrap().destination[ 2 ].condition = DataComparator::True;
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        assert!( graph.is_err() );
+
+        let err = graph.err().unwrap();
+
+        assert_eq!( err.kind(), "Validation" );
+        assert_eq!( err.node().map( | n | n.node_id.as_str() ), Some( "start" ) );
+        assert!( err.to_string().contains( "Branch branch_c exited before reaching a Join node" ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_fan_out_graph_join_branch_not_started()
+    {
+        let mut graph = graph_from_json_file_path( "test_data/graphs/fan_out_graph.json" ).await.unwrap();
+
+        graph.nodes.get_mut( "join" ).unwrap().executor = NodeExecutor::Join( Join { branches : vec![ "branch_c".into() ], ..Default::default() } );
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        let err = graph.err().unwrap();
+
+        assert_eq!( err.kind(), "Validation" );
+        assert_eq!( err.node().map( | n | n.node_id.as_str() ), Some( "start" ) );
+        assert!( err.to_string().contains( "Branch branch_c was not started from start" ), "{}", err );
+    }
+
+    #[tokio::test]
+    async fn test_run_fan_out_graph_join_named_branches()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/fan_out_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let mut graph = graph.unwrap();
+
+        graph.nodes.get_mut( "join" ).unwrap().executor = NodeExecutor::Join( Join { branches : vec![ "branch_b".into() ], ..Default::default() } );
+
+        let graph = run_graph( "".into(), graph ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( graph.final_output, Some( Ok( ", b".to_string() ) ) );
+        assert_eq!( graph.context.get( "results" ), Some( &json!( [ "b" ] ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_parallel_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/parallel_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( "".into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( 
+            graph.context.get( "results" ), 
+            Some( &json!( [ "Sum result: 5", "Sum result: 7", 3, null ] ) ) 
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_for_each_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/for_each_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( "[1,2,3]".into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( 
+            graph.context.get( "results" ), 
+            Some( &json!( [ "Sum result: 4", "Sum result: 5", "Sum result: 6" ] ) ) 
+        );
+
+        assert_eq!( graph.context.get( "positions" ), Some( &json!( [ 0, 1, 2 ] ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_for_each_graph_not_array_err()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/for_each_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( "{\"a\":1}".into(), graph.unwrap() ).await;
+
+        assert!( graph.is_err() );
+    }
+
+    #[tokio::test]
+    async fn test_run_expr_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/expr_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( r#"{ "count" : 5, "name" : " Bob " }"#.into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( graph.context.get( "total" ), Some( &json!( 13 ) ) );
+        assert_eq!( graph.context.get( "label" ), Some( &json!( "BOB-4" ) ) );
+        assert_eq!( graph.context.get( "kind" ), Some( &json!( "expensive" ) ) );
+
+        assert_eq!( graph.final_output, Some( Ok( "Total 13".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_expr_graph_condition_false()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/expr_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( r#"{ "count" : 1, "name" : "ann" }"#.into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( graph.context.get( "label" ), None );
+
+        assert_eq!( graph.final_output, Some( Err( "Small total".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_operations_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/operations_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let input = r#"{
+            "price" : 5,
+            "name" : " Ann  Smith ",
+            "text" : "a=1, b=22",
+            "tags" : [ "x", "y", "z", "w" ],
+            "items" : [ { "name" : "pen", "qty" : 1 }, { "name" : "book", "qty" : 3 } ],
+            "meta" : { "a" : 1, "b" : 2 }
+        }"#;
+
+        let graph = run_graph( input.into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        let get = | key : &str | graph.context.get( key ).cloned().unwrap_or( Value::Null );
+
+        assert_eq!( get( "math" ), json!( { "total" : 7.5, "modulo" : 2 } ) );
+        assert_eq!( get( "strings" ), json!( {
+            "upper" : "ANN  SMITH",
+            "replaced" : "_Ann_Smith_",
+            "captures" : [ { "key" : "a", "value" : "1" }, { "key" : "b", "value" : "22" } ]
+        } ) );
+        assert_eq!( get( "arrays" ), json!( {
+            "sliced" : "y,z",
+            "filtered" : [ { "name" : "book", "qty" : 3 } ],
+            "mapped" : [ "pen", "book" ]
+        } ) );
+        assert_eq!( get( "objects" ), json!( { "keys" : [ "a", "b" ], "merged" : { "a" : 1, "b" : 3, "c" : 4 } } ) );
+        assert_eq!( get( "json" ), json!( { "round_trip" : { "a" : 1, "b" : 2 } } ) );
+        assert_eq!( get( "encoding" ), json!( {
+            "base64" : "hello",
+            "sha256" : "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "md5" : "900150983cd24fb0d6963f7d28e17f72"
+        } ) );
+        assert_eq!( get( "dates" ).get( "formatted" ), Some( &json!( "05/03/2024" ) ) );
+        assert_eq!( get( "dates" ).get( "year" ).and_then( | y | y.as_str() ).map( | y | y.len() ), Some( 4 ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_template_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/template_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let input = r#"{ "user" : { "name" : "Ann" }, "items" : [ { "name" : "pen", "qty" : 1 }, { "name" : "book", "qty" : 3 } ] }"#;
+
+        let graph = run_graph( input.into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        assert_eq!( graph.own().final_output, Some( Ok( "Dear ANN,\n1. pen\n2. book x3\nTotal: 2".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_template_graph_empty_items()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/template_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( r#"{ "user" : { "name" : "Bob" }, "items" : [] }"#.into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        assert_eq!( graph.own().final_output, Some( Ok( "Dear BOB,\nNo itemsTotal: 0".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_schema_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/schema_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( r#"{ "name" : "Ann", "score" : 7 }"#.into(), graph.unwrap() ).await;
+
+        assert!( graph.is_ok() );
+
+        assert_eq!( graph.own().final_output, Some( Ok( "Ann".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_schema_graph_invalid_input()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/schema_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( r#"{ "name" : 3 }"#.into(), graph.unwrap() ).await;
+
+        assert!( graph.is_err() );
+
+        let err = graph.err().unwrap();
+
+        assert_eq!( err.kind(), "Schema" );
+        assert!( err.to_string().contains( "/name" ), "{}", err );
+        assert!( err.to_string().contains( "\"score\" is a required property" ), "{}", err );
+    }
+
+    #[tokio::test]
+    async fn test_run_schema_graph_invalid_outputs()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/schema_graph.json" ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = run_graph( r#"{ "name" : "Ann", "score" : 70 }"#.into(), graph.unwrap() ).await;
+
+        let err = graph.err().unwrap();
+
+        assert_eq!( err.kind(), "Schema" );
+        assert_eq!( err.node().map( | n | n.node_id.as_str() ), Some( "score" ) );
+        assert!( err.to_string().contains( "Output score" ), "{}", err );
+
+        let graph = graph_from_json_file_path( "test_data/graphs/schema_graph.json" ).await;
+
+        let graph = run_graph( r#"{ "name" : "Al", "score" : 1 }"#.into(), graph.unwrap() ).await;
+
+        let err = graph.err().unwrap();
+
+        assert_eq!( err.kind(), "Schema" );
+        assert_eq!( err.node().map( | n | n.node_id.as_str() ), Some( "label" ) );
+        assert!( err.to_string().contains( "/label" ), "{}", err );
+    }
+
+    #[tokio::test]
+    async fn test_run_agent_output_schema_repair()
+    {
+        let json = r#"
+        {
+            "first": {
+                "id": "weather",
+                "executor": {
+                    "Agent": {
+                        "provider": {
+                            "Mock": {
+                                "responses": [
+                                    { "text": "The weather is sunny" },
+                                    { "pattern": "^Your previous response is not valid", "text": "```json\n{ \"weather\": \"sunny\" }\n```" }
+                                ]
+                            }
+                        },
+                        "prompt": [ { "from": { "Input": {} } } ],
+                        "output_schema": { "type": "object", "properties": { "weather": { "type": "string" } }, "required": [ "weather" ] },
+                        "output_retries": 1
+                    }
+                },
+                "output": { "path": "forecast" },
+                "destination": [
+                    { "next": { "ExitOk": [ { "from": { "Context": { "path": "forecast/weather" } } } ] }, "condition": "True" }
+                ]
+            },
+            "nodes": []
+        }
+        "#;
+
+        let graph = graph_from_json_str( json ).await.unwrap();
+
+        let graph = run_graph( "Weather in Madrid?".into(), graph ).await.own();
+
+        assert_eq!( graph.context.get( "forecast" ), Some( &json!( { "weather" : "sunny" } ) ) );
+        assert_eq!( graph.final_output, Some( Ok( "sunny".to_string() ) ) );
+
+        let graph = graph_from_json_str( json.replace( r#""output_retries": 1"#, r#""output_retries": 0"# ) ).await.unwrap();
+
+        let result = run_graph( "Weather in Madrid?".into(), graph ).await;
+
+        let err = result.err().unwrap();
+
+        assert_eq!( err.node().map( | n | n.node_id.as_str() ), Some( "weather" ) );
+        assert!( err.to_string().contains( "Agent output. Attempts: 1" ), "{}", err );
+    }
+
     #[tokio::test]
     async fn test_run_loop_from_parsed_input_context_mut_ok()
     {
@@ -275,4 +982,310 @@ mod tests
 
         assert!( graph.final_output.is_none() );
     }
+
+    #[tokio::test]
+    async fn test_run_mock_agent_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/mock_agent_graph.json" ).await.unwrap();
+
+        let graph = run_graph( "say hello".into(), graph ).await;
+
+        assert!( graph.is_ok(), "{:?}", graph.err() );
+
+        let graph = graph.own();
+
+        assert_eq!( graph.context.get( "answer" ), Some( &json!( "Echo returned hello" ) ) );
+        assert_eq!( graph.final_output, Some( Ok( "The tool said hello".to_string() ) ) );
+
+        let usage = graph.usage.get();
+
+        assert!( usage.nodes.get( "assistant" ).unwrap().total_tokens > 0 );
+        assert_eq!( usage.nodes.get( "summary" ).unwrap().output_tokens, 4 );
+    }
+
+    fn mock_answers_graph() -> &'static str
+    {
+        r#"
+        {
+            "first": {
+                "id": "assistant",
+                "executor": {
+                    "Agent": {
+                        "provider": { "Mock": { "responses": [ { "text": "First answer" }, { "text": "Second answer" } ] } },
+                        "prompt": [ { "from": { "Input": {} } } ]
+                    }
+                },
+                "output": { "path": "answer" },
+                "destination": [ { "next": { "ExitOk": [ { "from": { "Context": { "path": "answer" } } } ] }, "condition": "True" } ]
+            },
+            "nodes": []
+        }
+        "#
+    }
+
+    #[tokio::test]
+    async fn test_run_mock_agent_graph_call_index_per_run()
+    {
+        let graph = graph_from_json_str( mock_answers_graph() ).await.unwrap();
+
+        for _ in 0..2
+        {
+            let output = run_graph( "hello".into(), graph.clone() ).await.own().final_output;
+
+            assert_eq!( output, Some( Ok( "First answer".to_string() ) ) );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_mock_agent_graph_keeps_call_index()
+    {
+        let dir = std::env::temp_dir().join( uuid::Uuid::new_v4().to_string() );
+
+        let mut graph = graph_from_json_str( mock_answers_graph() ).await.unwrap();
+
+        graph.checkpoint = Some( Arc::new( DirCheckpointStore::new( &dir ) ) );
+
+        assert!( run_graph( "hello".into(), graph.clone() ).await.is_ok() );
+
+        let checkpoint = DirCheckpointStore::new( &dir ).load( None );
+
+        let _ = std::fs::remove_dir_all( &dir );
+
+        let mut checkpoint = checkpoint.unwrap().unwrap();
+
+        checkpoint.next = Some( "assistant".into() );
+
+        graph.checkpoint = None;
+        graph.mock_calls = Arc::default();
+
+        let output = resume_graph( checkpoint, graph ).await.own().final_output;
+
+        assert_eq!( output, Some( Ok( "Second answer".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_cassette_graph_record_and_replay()
+    {
+        let path = std::env::temp_dir().join( format!( "{}.cassette.jsonl", uuid::Uuid::new_v4() ) );
+
+        let mut graph = graph_from_json_file_path( "test_data/graphs/cassette_graph.json" ).await.unwrap();
+
+        graph.cassette = Some( Arc::new( Cassette::record( &path ).unwrap() ) );
+
+        let recorded = run_graph( "hello".into(), graph ).await.own().final_output;
+
+        assert!( recorded.as_ref().is_some_and( | o | o.as_ref().is_ok_and( | o | o.starts_with( "First answer at " ) ) ) );
+
+        // A new graph has a new mock. Without the cassette it would answer again "First answer" with another time
+        let mut graph = graph_from_json_file_path( "test_data/graphs/cassette_graph.json" ).await.unwrap();
+
+        graph.cassette = Some( Arc::new( Cassette::replay( &path ).unwrap() ) );
+
+        let replayed = run_graph( "hello".into(), graph.clone() ).await.own().final_output;
+
+        assert_eq!( replayed, recorded );
+
+        let result = run_graph( "hello".into(), graph ).await;
+
+        let _ = std::fs::remove_file( &path );
+
+        assert_eq!( result.err().unwrap().kind(), Error::Command( "".into() ).kind() );
+    }
+
+    #[tokio::test]
+    async fn test_run_fallback_graph_ok()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/fallback_graph.json" ).await.unwrap();
+
+        let graph = run_graph( "short question".into(), graph ).await.own();
+
+        assert_eq!( graph.final_output, Some( Ok( "Answer of small".to_string() ) ) );
+        assert_eq!( graph.context.get( "provider" ), Some( &json!( { "index" : 1, "provider" : "Mock", "model" : "small" } ) ) );
+
+        let graph = graph_from_json_file_path( "test_data/graphs/fallback_graph.json" ).await.unwrap();
+
+        let graph = run_graph( "long question".into(), graph ).await.own();
+
+        assert_eq!( graph.final_output, Some( Ok( "Answer of large".to_string() ) ) );
+        assert_eq!( graph.context.get( "provider" ).and_then( | p | p.get( "index" ) ), Some( &json!( 2 ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_fallback_graph_not_fallback_error()
+    {
+        let json = std::fs::read_to_string( "test_data/graphs/fallback_graph.json" ).unwrap()
+        .replace( "rate limit\" }, \"ContextTooLong\"", "timeout\" }" );
+
+        let graph = graph_from_json_str( &json ).await.unwrap();
+
+        let result = run_graph( "short question".into(), graph ).await;
+
+        assert!( result.is_err() );
+
+        let err = result.err().unwrap().to_string();
+
+        assert!( err.contains( "Fallback provider 0 Mock" ) && err.contains( "429" ), "{}", err );
+    }
+
+    #[tokio::test]
+    async fn test_run_fallback_graph_timeout()
+    {
+        let json = std::fs::read_to_string( "test_data/graphs/fallback_graph.json" ).unwrap()
+        .replace( "{ \"error\": \"429 Too Many Requests: rate limit\" } ] } },", "{ \"text\": \"Late answer\", \"delay\": 2000 } ] } },\n\"timeout\": 50," )
+        .replace( "[ { \"Contains\": \"rate limit\" }, \"ContextTooLong\" ]", "[ \"Timeout\" ]" );
+
+        let graph = graph_from_json_str( &json ).await.unwrap();
+
+        let graph = run_graph( "short question".into(), graph ).await.own();
+
+        assert_eq!( graph.final_output, Some( Ok( "Answer of small".to_string() ) ) );
+        assert_eq!( graph.context.get( "provider" ).and_then( | p | p.get( "index" ) ), Some( &json!( 1 ) ) );
+
+        let graph = graph_from_json_str( json.replace( "[ \"Timeout\" ]", "[ \"ContextTooLong\" ]" ) ).await.unwrap();
+
+        let result = run_graph( "short question".into(), graph ).await;
+
+        assert!( result.is_err() );
+
+        let err = result.err().unwrap().to_string();
+
+        assert!( err.contains( "Fallback provider 0 Mock" ) && err.contains( "Timeout after 50 ms" ), "{}", err );
+    }
+
+    #[tokio::test]
+    async fn test_run_human_input_graph_callback()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/human_input_graph.json" ).await.unwrap();
+
+        let graph = run_graph_with_human_input(
+            r#"{ "file" : "a.txt" }"#.into(),
+            graph,
+            | r : HumanInputRequest | async move
+            {
+                assert_eq!( r.node_id.as_deref(), Some( "confirm" ) );
+                assert_eq!( r.question, "Delete a.txt?" );
+                assert_eq!( r.choices, vec![ "yes".to_string(), "no".to_string() ] );
+
+                Ok( "yes".to_string() )
+            }
+        ).await;
+
+        assert!( graph.is_ok() );
+
+        let graph = graph.own();
+
+        assert_eq!( graph.context.get( "answer" ), Some( &json!( "yes" ) ) );
+        assert_eq!( graph.final_output, Some( Ok( "Deleted a.txt".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_human_input_graph_channel()
+    {
+        let mut graph = graph_from_json_file_path( "test_data/graphs/human_input_graph.json" ).await.unwrap();
+
+        let ( handler, mut receiver ) = human_input_channel( 1 );
+
+        graph.human_input = Some( Arc::new( handler ) );
+
+        tokio::spawn(
+            async move
+            {
+                while let Some( q ) = receiver.recv().await
+                {
+                    q.answer( "no" );
+                }
+            }
+        );
+
+        let graph = run_graph( r#"{ "file" : "a.txt" }"#.into(), graph ).await;
+
+        assert!( graph.is_ok() );
+
+        assert_eq!( graph.own().final_output, Some( Err( "Cancelled".to_string() ) ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_human_input_graph_err()
+    {
+        let graph = graph_from_json_file_path( "test_data/graphs/human_input_graph.json" ).await.unwrap();
+
+        let result = run_graph( r#"{ "file" : "a.txt" }"#.into(), graph.clone() ).await;
+
+        assert_eq!( result.err().unwrap().kind(), "HumanInput" );
+
+        let mut graph = graph;
+
+        graph.human_input = Some( Arc::new( | _ : HumanInputRequest | async { Ok( "maybe".to_string() ) } ) );
+
+        let result = run_graph( r#"{ "file" : "a.txt" }"#.into(), graph ).await;
+
+        let err = result.err().unwrap();
+
+        assert_eq!( err.kind(), "HumanInput" );
+        assert!( err.to_string().contains( "Answer maybe is not one of" ) );
+    }
+
+    #[tokio::test]
+    async fn test_run_graph_max_tokens_budget()
+    {
+        // Each Mock call has 6 words in the prompt and 4 in the response, so it uses 10 tokens
+        let json = r#"
+        {
+            "max_tokens_budget": 15,
+            "first": {
+                "id": "first",
+                "executor": AGENT,
+                "destination": [ { "next": { "Node": "second" }, "condition": "True" } ]
+            },
+            "nodes": [
+                {
+                    "id": "second",
+                    "executor": AGENT,
+                    "destination": [ { "next": { "Node": "third" }, "condition": "True" } ]
+                },
+                {
+                    "id": "third",
+                    "executor": AGENT,
+                    "destination": [ { "next": { "ExitOk": [] }, "condition": "True" } ]
+                }
+            ]
+        }
+        "#
+        .replace( "AGENT", r#"{ "Agent": { "provider": { "Mock": { "responses": [ { "text": "four words of answer" } ] } }, "prompt": [ { "from": { "Static": "one two three four five six" } } ] } }"# );
+
+        let mut graph = graph_from_json_str( json ).await.unwrap();
+
+        let result = run_graph( "".into(), graph.clone() ).await;
+
+        let err = result.err().unwrap();
+
+        assert_eq!( err.kind(), "LimitExceeded" );
+        assert!( err.to_string().contains( "Max tokens budget 15 exceeded in node third" ) );
+
+        let usage = graph.usage.get();
+
+        assert_eq!( usage.run, TokenUsage::new( 12, 8, 20 ) );
+        assert_eq!( usage.nodes.get( "first" ), Some( &TokenUsage::new( 6, 4, 10 ) ) );
+
+        graph.max_tokens_budget = None;
+
+        assert!( run_graph( "".into(), graph.clone() ).await.is_ok() );
+
+        let usage = graph.usage.get();
+
+        assert_eq!( usage.run.total_tokens, 30 );
+        assert_eq!( usage.total.total_tokens, 50 );
+
+        graph.max_tokens_budget = Some( 15 );
+        graph.usage = Arc::default();
+
+        let checkpoint = GraphCheckpoint { next : Some( "third".into() ), usage : usage.clone(), ..Default::default() };
+
+        let result = resume_graph( checkpoint, graph.clone() ).await;
+
+        let err = result.err().unwrap();
+
+        assert!( err.to_string().contains( "Max tokens budget 15 exceeded in node third" ), "{}", err );
+        assert_eq!( graph.usage.get(), usage );
+    }
 }
\ No newline at end of file
@@ -0,0 +1,168 @@
+use crate::domain::{error::Error, graph::{build_graph::{graph_config_from_path, graph_config_from_str}, diagnostic::Diagnostic, graph::GraphConfig, validate_graph::validate_graph}};
+
+
+pub fn validate_graph_config( config : &GraphConfig ) -> Vec<Diagnostic>
+{
+    validate_graph( config )
+}
+
+pub fn validate_graph_from_json_file_path( path : impl AsRef<str> ) -> Result<Vec<Diagnostic>, Error>
+{
+    Ok( validate_graph( &graph_config_from_path( path.as_ref() )? ) )
+}
+
+pub fn validate_graph_from_json_str( json : impl AsRef<str> ) -> Result<Vec<Diagnostic>, Error>
+{
+    Ok( validate_graph( &graph_config_from_str( json )? ) )
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::{domain::graph::diagnostic::DiagnosticLevel, infrastructure::graph::build_graph::graph_from_json_str};
+
+    use super::*;
+
+    #[test]
+    fn test_validate_test_graphs_without_errors()
+    {
+        let paths = [
+            "test_data/graphs/add_3_graph.json",
+            "test_data/graphs/add_two_numbers_graph.json",
+            "test_data/graphs/context_mut_graph.json",
+            "test_data/graphs/echo_graph.json",
+            "test_data/graphs/graph_node_executor_graph.json",
+            "test_data/graphs/loop_from_parsed_input_graph.json",
+            "test_data/graphs/loop_graph.json",
+            "test_data/graphs/retry_graph.json",
+            "test_data/graphs/fan_out_graph.json",
+            "test_data/graphs/parallel_graph.json",
+            "test_data/graphs/for_each_graph.json",
+            "test_data/graphs/expr_graph.json",
+            "test_data/graphs/operations_graph.json",
+            "test_data/graphs/template_graph.json",
+            "test_data/graphs/schema_graph.json",
+            "test_data/graphs/fallback_graph.json"
+        ];
+
+        for path in paths
+        {
+            let diagnostics = validate_graph_from_json_file_path( path );
+
+            assert!( diagnostics.is_ok() );
+
+            let diagnostics = diagnostics.unwrap();
+
+            assert!( diagnostics.iter().all( | d | ! d.is_error() ), "{}: {:?}", path, diagnostics );
+        }
+    }
+
+    #[test]
+    fn test_validate_invalid_graph()
+    {
+        let diagnostics = validate_graph_from_json_file_path( "test_data/graphs/invalid_graph.json" );
+
+        assert!( diagnostics.is_ok() );
+
+        let diagnostics = diagnostics.unwrap();
+
+        let has = | level : DiagnosticLevel, node : &str, msg : &str |
+        {
+            diagnostics.iter().any( | d | d.level == level && d.node.as_deref() == Some( node ) && d.message.contains( msg ) )
+        };
+
+        assert!( has( DiagnosticLevel::Error, "node_1", "unknown node typo" ) );
+        assert!( has( DiagnosticLevel::Error, "node_1", "Invalid regex" ) );
+        assert!( has( DiagnosticLevel::Error, "node_2", "no destinations" ) );
+        assert!( has( DiagnosticLevel::Error, "node_2", "is not an Agent node" ) );
+        assert!( has( DiagnosticLevel::Error, "node_2", "Store missing_store" ) );
+        assert!( has( DiagnosticLevel::Error, "node_2", "Invalid expression: context.count +" ) );
+        assert!( has( DiagnosticLevel::Error, "node_2", "Unknown function unknown_fn" ) );
+        assert!( has( DiagnosticLevel::Error, "node_1", "Output output. Schema: Invalid schema" ) );
+        assert!( has( DiagnosticLevel::Error, "node_2", "ChildProcess transport and empty command" ) );
+        assert!( has( DiagnosticLevel::Warning, "orphan", "unreachable" ) );
+        assert!( has( DiagnosticLevel::Warning, "node_1", "never_written" ) );
+        assert!( ! has( DiagnosticLevel::Warning, "node_1", "Context path output" ) );
+    }
+
+    #[test]
+    fn test_validate_fallback_providers()
+    {
+        let mock = r#"{ "provider" : { "Mock" : { "responses" : [] } } }"#;
+        let nested = format!( r#"{{ "provider" : {{ "Fallback" : {{ "providers" : [ {} ] }} }} }}"#, mock );
+
+        let json = format!(
+            r#"{{
+                "first" : {{
+                    "id" : "empty",
+                    "executor" : {{ "Agent" : {{ "provider" : {{ "Fallback" : {{ "providers" : [] }} }}, "prompt" : [] }} }},
+                    "destination" : [ {{ "next" : {{ "Node" : "nested" }}, "condition" : "True" }} ]
+                }},
+                "nodes" : [
+                    {{
+                        "id" : "nested",
+                        "executor" : {{ "Agent" : {{ "provider" : {{ "Fallback" : {{ "providers" : [ {}, {} ] }} }}, "prompt" : [] }} }},
+                        "destination" : [ {{ "next" : {{ "Node" : "parallel" }}, "condition" : "True" }} ]
+                    }},
+                    {{
+                        "id" : "parallel",
+                        "executor" : {{ "Parallel" : {{ "executors" : [ {{ "Agent" : {{ "executor" : {{ "provider" : {{ "Fallback" : {{ "providers" : [ {} ], "output" : "provider" }} }}, "prompt" : [] }}, "condition" : "True" }} }} ] }} }},
+                        "destination" : [ {{ "next" : {{ "ExitOk" : [] }}, "condition" : "True" }} ]
+                    }}
+                ]
+            }}"#,
+            mock, nested, mock
+        );
+
+        let diagnostics = validate_graph_from_json_str( json ).unwrap();
+
+        let has = | node : &str, msg : &str |
+        {
+            diagnostics.iter().any( | d | d.is_error() && d.node.as_deref() == Some( node ) && d.message.contains( msg ) )
+        };
+
+        assert!( has( "empty", "Fallback without providers" ), "{:?}", diagnostics );
+        assert!( has( "nested", "Fallback provider 1 is a Fallback" ), "{:?}", diagnostics );
+        assert!( ! has( "nested", "Fallback provider 0" ), "{:?}", diagnostics );
+        assert!( has( "parallel", "Fallback output is not supported in Parallel executors" ), "{:?}", diagnostics );
+    }
+
+    #[test]
+    fn test_validate_mock_patterns()
+    {
+        let json = r#"{
+            "first" : {
+                "id" : "mock",
+                "executor" : { "Agent" : { "provider" : { "Mock" : { "responses" : [ { "pattern" : "^ok" }, { "pattern" : "(" } ] } }, "prompt" : [] } },
+                "destination" : [ { "next" : { "ExitOk" : [] }, "condition" : "True" } ]
+            },
+            "nodes" : []
+        }"#;
+
+        let diagnostics = validate_graph_from_json_str( json ).unwrap();
+
+        assert_eq!( diagnostics.iter().filter( | d | d.is_error() ).count(), 1, "{:?}", diagnostics );
+        assert!( diagnostics.iter().any( | d | d.node.as_deref() == Some( "mock" ) && d.message.contains( "Invalid regex: (" ) ), "{:?}", diagnostics );
+    }
+
+    #[tokio::test]
+    async fn test_build_strict_graph_fails_before_execution()
+    {
+        let json = r#"{
+            "strict" : true,
+            "first" : {
+                "id" : "node_1",
+                "executor" : { "ContextMut" : [] },
+                "destination" : [ { "next" : { "Node" : "typo" }, "condition" : "True" } ]
+            }
+        }"#;
+
+        let graph = graph_from_json_str( json ).await;
+
+        assert!( matches!( graph, Err( Error::Validation( _ ) ) ) );
+
+        let graph = graph_from_json_str( json.replace( r#""strict" : true"#, r#""strict" : false"# ) ).await;
+
+        assert!( graph.is_ok() );
+    }
+}